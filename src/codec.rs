@@ -0,0 +1,838 @@
+//! Framing codecs for byte streams received over a serial port.
+//!
+//! The codecs implement the `tokio_util::codec` [`Decoder`] and [`Encoder`]
+//! traits, so they can be used with `tokio_util::codec::Framed` around an
+//! async port. For blocking ports, [`FramedPort`] drives any such codec over
+//! a `Box<dyn SerialPort>`.
+//!
+//! | Codec                    | Frames                                          |
+//! | ------------------------ | ----------------------------------------------- |
+//! | [`LinesCodec`]           | Text lines ending in CR, LF or CRLF             |
+//! | [`LengthDelimitedCodec`] | Payloads prefixed with their length             |
+//! | [`CobsCodec`]            | COBS encoded payloads ending in a zero byte     |
+//! | [`SlipCodec`]            | SLIP (RFC 1055) encoded payloads                |
+//! | [`FixedSizeCodec`]       | Frames of a fixed number of bytes               |
+//!
+//! Malformed frames are reported as errors of kind `InvalidData`; decoding
+//! can continue with the next frame afterwards.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use serialport::codec::{FramedPort, LineEnding, LinesCodec};
+//! use serialport::mock::MockPort;
+//!
+//! let port = MockPort::new(serialport::new("mock", 9600).timeout(Duration::from_millis(100)))
+//!     .expect_write(b"AT+GMR\r\n")
+//!     .respond(b"v1.2\r\nOK\r\n", Duration::ZERO);
+//!
+//! let mut framed = FramedPort::new(Box::new(port), LinesCodec::new(LineEnding::CrLf));
+//! framed.write_frame("AT+GMR")?;
+//! assert_eq!(framed.read_frame()?, "v1.2");
+//! assert_eq!(framed.read_frame()?, "OK");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::io;
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::SerialPort;
+
+/// The default limit of [`LengthDelimitedCodec`], which keeps a corrupted
+/// length field from allocating gigabytes.
+const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+fn frame_too_long() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "frame exceeds maximum length")
+}
+
+/// Splits the next frame ending in `delimiter` off `src`, without the
+/// delimiter.
+///
+/// Once more than `max_length` bytes arrive without a delimiter, an error is
+/// returned and the rest of that frame is skipped.
+fn next_delimited(
+    src: &mut BytesMut,
+    delimiter: &[u8],
+    max_length: usize,
+    discarding: &mut bool,
+) -> io::Result<Option<BytesMut>> {
+    loop {
+        let Some(position) = src
+            .windows(delimiter.len())
+            .position(|window| window == delimiter)
+        else {
+            // Keep what could be the start of a delimiter
+            let keep = src.len().min(delimiter.len() - 1);
+
+            if *discarding {
+                src.advance(src.len() - keep);
+            } else if src.len() - keep > max_length {
+                src.advance(src.len() - keep);
+                *discarding = true;
+                return Err(frame_too_long());
+            }
+
+            return Ok(None);
+        };
+
+        let mut frame = src.split_to(position + delimiter.len());
+        frame.truncate(position);
+
+        if std::mem::take(discarding) {
+            continue;
+        }
+
+        if frame.len() > max_length {
+            return Err(frame_too_long());
+        }
+
+        return Ok(Some(frame));
+    }
+}
+
+/// The terminator of a text line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// Carriage return (`\r`)
+    Cr,
+    /// Line feed (`\n`)
+    Lf,
+    /// Carriage return followed by line feed (`\r\n`)
+    CrLf,
+}
+
+impl LineEnding {
+    /// Returns the bytes of the terminator.
+    pub fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Cr => b"\r",
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+/// A codec for UTF-8 text lines.
+///
+/// Decoded lines do not include the terminator, and encoded lines have it
+/// appended. Lines that are not valid UTF-8 fail with `InvalidData`.
+///
+/// # Examples
+///
+/// ```rust
+/// use bytes::BytesMut;
+/// use tokio_util::codec::{Decoder, Encoder};
+/// use serialport::codec::{LineEnding, LinesCodec};
+///
+/// let mut codec = LinesCodec::new(LineEnding::Cr).with_max_length(16);
+///
+/// let mut buffer = BytesMut::from(&b"OK\rER"[..]);
+/// assert_eq!(codec.decode(&mut buffer)?, Some("OK".to_string()));
+/// assert_eq!(codec.decode(&mut buffer)?, None);
+///
+/// let mut output = BytesMut::new();
+/// codec.encode("ATZ", &mut output)?;
+/// assert_eq!(&output[..], b"ATZ\r");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct LinesCodec {
+    ending: LineEnding,
+    max_length: usize,
+    discarding: bool,
+}
+
+impl LinesCodec {
+    /// Creates a codec for lines ending in `ending`, of any length.
+    pub fn new(ending: LineEnding) -> Self {
+        Self {
+            ending,
+            max_length: usize::MAX,
+            discarding: false,
+        }
+    }
+
+    /// Limits decoded lines to `max_length` bytes, not counting the
+    /// terminator. Longer lines fail with `InvalidData` and are skipped.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Returns the line terminator.
+    pub fn ending(&self) -> LineEnding {
+        self.ending
+    }
+
+    /// Returns the maximum length of a decoded line.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+}
+
+impl Decoder for LinesCodec {
+    type Item = String;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<String>> {
+        let Some(line) = next_delimited(
+            src,
+            self.ending.as_bytes(),
+            self.max_length,
+            &mut self.discarding,
+        )?
+        else {
+            return Ok(None);
+        };
+
+        String::from_utf8(line.to_vec())
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl<T: AsRef<str>> Encoder<T> for LinesCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, line: T, dst: &mut BytesMut) -> io::Result<()> {
+        let line = line.as_ref();
+        let ending = self.ending.as_bytes();
+
+        dst.reserve(line.len() + ending.len());
+        dst.put_slice(line.as_bytes());
+        dst.put_slice(ending);
+
+        Ok(())
+    }
+}
+
+/// A codec for payloads prefixed with their length.
+///
+/// The length field counts the payload only. By default it is two bytes in
+/// big-endian order.
+///
+/// # Examples
+///
+/// ```rust
+/// use bytes::BytesMut;
+/// use tokio_util::codec::{Decoder, Encoder};
+/// use serialport::codec::LengthDelimitedCodec;
+///
+/// let mut codec = LengthDelimitedCodec::new().length_field_length(1);
+///
+/// let mut buffer = BytesMut::new();
+/// codec.encode(b"abc", &mut buffer)?;
+/// assert_eq!(&buffer[..], b"\x03abc");
+///
+/// let frame = codec.decode(&mut buffer)?.unwrap();
+/// assert_eq!(&frame[..], b"abc");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct LengthDelimitedCodec {
+    length_field_length: usize,
+    little_endian: bool,
+    max_frame_length: usize,
+    /// Bytes of an oversized frame that are still to be skipped
+    discarding: usize,
+}
+
+impl LengthDelimitedCodec {
+    /// Creates a codec with a two byte big-endian length field and payloads
+    /// of up to 8 MiB.
+    pub fn new() -> Self {
+        Self {
+            length_field_length: 2,
+            little_endian: false,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            discarding: 0,
+        }
+    }
+
+    /// Sets the size of the length field in bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `length` is not between 1 and 4.
+    pub fn length_field_length(mut self, length: usize) -> Self {
+        assert!(
+            (1..=4).contains(&length),
+            "length field must be 1 to 4 bytes long"
+        );
+        self.length_field_length = length;
+        self
+    }
+
+    /// Reads and writes the length field in little-endian order.
+    pub fn little_endian(mut self) -> Self {
+        self.little_endian = true;
+        self
+    }
+
+    /// Limits decoded payloads to `max_frame_length` bytes, 8 MiB by
+    /// default. Longer frames fail with `InvalidData` and are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use bytes::BytesMut;
+    /// use tokio_util::codec::Decoder;
+    /// use serialport::codec::LengthDelimitedCodec;
+    ///
+    /// let mut codec = LengthDelimitedCodec::new().length_field_length(4);
+    ///
+    /// // A length field garbled by line noise does not allocate 4 GiB
+    /// let mut buffer = BytesMut::from(&b"\xFF\xFF\xFF\xFFabc"[..]);
+    /// let err = codec.decode(&mut buffer).unwrap_err();
+    /// assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    ///
+    /// // A frame within the limit only reserves what has arrived so far
+    /// let mut codec = codec.max_frame_length(usize::MAX);
+    /// let mut buffer = BytesMut::from(&b"\x7F\xFF\xFF\xFFabc"[..]);
+    /// assert!(codec.decode(&mut buffer)?.is_none());
+    /// assert!(buffer.capacity() < 1024);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn max_frame_length(mut self, max_frame_length: usize) -> Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    fn max_length(&self) -> usize {
+        let max = (1u64 << (8 * self.length_field_length)) - 1;
+        usize::try_from(max).unwrap_or(usize::MAX)
+    }
+}
+
+impl Default for LengthDelimitedCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for LengthDelimitedCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        if self.discarding > 0 {
+            let skip = src.len().min(self.discarding);
+            src.advance(skip);
+            self.discarding -= skip;
+
+            if self.discarding > 0 {
+                return Ok(None);
+            }
+        }
+
+        let header = self.length_field_length;
+        if src.len() < header {
+            return Ok(None);
+        }
+
+        let mut field = &src[..header];
+        let length = if self.little_endian {
+            field.get_uint_le(header)
+        } else {
+            field.get_uint(header)
+        };
+
+        let frame = usize::try_from(length)
+            .ok()
+            .filter(|&length| length <= self.max_frame_length)
+            .and_then(|length| Some((length, header.checked_add(length)?)));
+
+        let Some((length, frame)) = frame else {
+            // Skip the oversized frame as it arrives
+            src.advance(header);
+            self.discarding = usize::try_from(length).unwrap_or(usize::MAX);
+            return Err(frame_too_long());
+        };
+
+        if src.len() < frame {
+            // The length field may be line noise, so the buffer only grows
+            // along with the data that actually arrives
+            src.reserve((frame - src.len()).min(src.len()));
+            return Ok(None);
+        }
+
+        src.advance(header);
+        Ok(Some(src.split_to(length)))
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for LengthDelimitedCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, payload: T, dst: &mut BytesMut) -> io::Result<()> {
+        let payload = payload.as_ref();
+
+        if payload.len() > self.max_length() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "payload too long for length field",
+            ));
+        }
+
+        dst.reserve(self.length_field_length + payload.len());
+        if self.little_endian {
+            dst.put_uint_le(payload.len() as u64, self.length_field_length);
+        } else {
+            dst.put_uint(payload.len() as u64, self.length_field_length);
+        }
+        dst.put_slice(payload);
+
+        Ok(())
+    }
+}
+
+/// A codec for payloads in Consistent Overhead Byte Stuffing.
+///
+/// Encoded frames contain no zero bytes except for the one that ends them,
+/// so a receiver can always find the start of the next frame.
+///
+/// # Examples
+///
+/// ```rust
+/// use bytes::BytesMut;
+/// use tokio_util::codec::{Decoder, Encoder};
+/// use serialport::codec::CobsCodec;
+///
+/// let mut codec = CobsCodec::new();
+///
+/// let mut buffer = BytesMut::new();
+/// codec.encode(b"\x11\x00\x22", &mut buffer)?;
+/// assert_eq!(&buffer[..], b"\x02\x11\x02\x22\x00");
+///
+/// let frame = codec.decode(&mut buffer)?.unwrap();
+/// assert_eq!(&frame[..], b"\x11\x00\x22");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct CobsCodec {
+    max_length: usize,
+    discarding: bool,
+}
+
+impl CobsCodec {
+    /// Creates a codec for frames of any length.
+    pub fn new() -> Self {
+        Self {
+            max_length: usize::MAX,
+            discarding: false,
+        }
+    }
+
+    /// Limits encoded frames to `max_length` bytes, not counting the final
+    /// zero. Longer frames fail with `InvalidData` and are skipped.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+}
+
+impl Default for CobsCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for CobsCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        loop {
+            let Some(encoded) = next_delimited(src, &[0], self.max_length, &mut self.discarding)?
+            else {
+                return Ok(None);
+            };
+
+            // Zero bytes between frames carry nothing
+            if encoded.is_empty() {
+                continue;
+            }
+
+            let mut frame = BytesMut::with_capacity(encoded.len());
+            let mut rest = &encoded[..];
+
+            while let Some((&code, data)) = rest.split_first() {
+                let length = usize::from(code) - 1;
+                if length > data.len() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "malformed COBS frame",
+                    ));
+                }
+
+                frame.put_slice(&data[..length]);
+                rest = &data[length..];
+
+                if code != 0xFF && !rest.is_empty() {
+                    frame.put_u8(0);
+                }
+            }
+
+            return Ok(Some(frame));
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for CobsCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, payload: T, dst: &mut BytesMut) -> io::Result<()> {
+        let payload = payload.as_ref();
+        dst.reserve(payload.len() + payload.len() / 254 + 2);
+
+        let mut code_index = dst.len();
+        dst.put_u8(0);
+
+        for &byte in payload {
+            if byte != 0 {
+                dst.put_u8(byte);
+            }
+
+            let code = dst.len() - code_index;
+            if byte == 0 || code == 0xFF {
+                dst[code_index] = code as u8;
+                code_index = dst.len();
+                dst.put_u8(0);
+            }
+        }
+
+        dst[code_index] = (dst.len() - code_index) as u8;
+        dst.put_u8(0);
+
+        Ok(())
+    }
+}
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// A codec for payloads in the Serial Line Internet Protocol framing of
+/// RFC 1055.
+///
+/// Encoded frames start and end with `END` (0xC0), which flushes any line
+/// noise received before the frame.
+///
+/// # Examples
+///
+/// ```rust
+/// use bytes::BytesMut;
+/// use tokio_util::codec::{Decoder, Encoder};
+/// use serialport::codec::SlipCodec;
+///
+/// let mut codec = SlipCodec::new();
+///
+/// let mut buffer = BytesMut::new();
+/// codec.encode(b"\x01\xC0\x02", &mut buffer)?;
+/// assert_eq!(&buffer[..], b"\xC0\x01\xDB\xDC\x02\xC0");
+///
+/// let frame = codec.decode(&mut buffer)?.unwrap();
+/// assert_eq!(&frame[..], b"\x01\xC0\x02");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct SlipCodec {
+    max_length: usize,
+    discarding: bool,
+}
+
+impl SlipCodec {
+    /// Creates a codec for frames of any length.
+    pub fn new() -> Self {
+        Self {
+            max_length: usize::MAX,
+            discarding: false,
+        }
+    }
+
+    /// Limits encoded frames to `max_length` bytes. Longer frames fail with
+    /// `InvalidData` and are skipped.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+}
+
+impl Default for SlipCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for SlipCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        loop {
+            let Some(encoded) =
+                next_delimited(src, &[SLIP_END], self.max_length, &mut self.discarding)?
+            else {
+                return Ok(None);
+            };
+
+            // Back-to-back END bytes delimit empty frames
+            if encoded.is_empty() {
+                continue;
+            }
+
+            let mut frame = BytesMut::with_capacity(encoded.len());
+            let mut bytes = encoded.iter();
+
+            while let Some(&byte) = bytes.next() {
+                let byte = match byte {
+                    SLIP_ESC => match bytes.next() {
+                        Some(&SLIP_ESC_END) => SLIP_END,
+                        Some(&SLIP_ESC_ESC) => SLIP_ESC,
+                        _ => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                "invalid SLIP escape sequence",
+                            ));
+                        }
+                    },
+                    byte => byte,
+                };
+                frame.put_u8(byte);
+            }
+
+            return Ok(Some(frame));
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for SlipCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, payload: T, dst: &mut BytesMut) -> io::Result<()> {
+        let payload = payload.as_ref();
+        dst.reserve(payload.len() + 2);
+
+        dst.put_u8(SLIP_END);
+        for &byte in payload {
+            match byte {
+                SLIP_END => dst.put_slice(&[SLIP_ESC, SLIP_ESC_END]),
+                SLIP_ESC => dst.put_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+                byte => dst.put_u8(byte),
+            }
+        }
+        dst.put_u8(SLIP_END);
+
+        Ok(())
+    }
+}
+
+/// A codec for frames of a fixed size.
+///
+/// # Examples
+///
+/// ```rust
+/// use bytes::BytesMut;
+/// use tokio_util::codec::{Decoder, Encoder};
+/// use serialport::codec::FixedSizeCodec;
+///
+/// let mut codec = FixedSizeCodec::new(4);
+///
+/// let mut buffer = BytesMut::from(&b"abcdef"[..]);
+/// assert_eq!(&codec.decode(&mut buffer)?.unwrap()[..], b"abcd");
+/// assert_eq!(codec.decode(&mut buffer)?, None);
+///
+/// let result = codec.encode(b"abc", &mut buffer);
+/// assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedSizeCodec {
+    size: usize,
+}
+
+impl FixedSizeCodec {
+    /// Creates a codec for frames of `size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "frame size must not be zero");
+        Self { size }
+    }
+
+    /// Returns the size of a frame.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl Decoder for FixedSizeCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        if src.len() < self.size {
+            src.reserve(self.size - src.len());
+            return Ok(None);
+        }
+
+        Ok(Some(src.split_to(self.size)))
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for FixedSizeCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: T, dst: &mut BytesMut) -> io::Result<()> {
+        let frame = frame.as_ref();
+
+        if frame.len() != self.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame does not have the fixed size",
+            ));
+        }
+
+        dst.put_slice(frame);
+        Ok(())
+    }
+}
+
+/// A blocking serial port that reads and writes whole frames through a codec.
+///
+/// Each read from the port waits for at most the port's timeout. When a
+/// read times out, the error is returned and the data received so far stays
+/// buffered, so the next call to [`read_frame`](FramedPort::read_frame)
+/// continues with the same frame.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use serialport::codec::{FramedPort, SlipCodec};
+///
+/// # #[cfg(unix)] {
+/// let (device, port) = serialport::new("", 115200)
+///     .timeout(Duration::from_millis(100))
+///     .build_pair()?;
+///
+/// let mut device = FramedPort::new(device, SlipCodec::new());
+/// let mut port = FramedPort::new(port, SlipCodec::new());
+///
+/// device.write_frame(b"\x01\x02")?;
+/// device.write_frame(b"\xC0")?;
+/// assert_eq!(&port.read_frame()?[..], b"\x01\x02");
+/// assert_eq!(&port.read_frame()?[..], b"\xC0");
+///
+/// // Nothing else was sent
+/// assert_eq!(port.read_frame().unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+/// # }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct FramedPort<C> {
+    port: Box<dyn SerialPort>,
+    codec: C,
+    read_buffer: BytesMut,
+    write_buffer: BytesMut,
+}
+
+impl<C> FramedPort<C> {
+    /// Wraps `port`, framing its data with `codec`.
+    pub fn new(port: Box<dyn SerialPort>, codec: C) -> Self {
+        Self {
+            port,
+            codec,
+            read_buffer: BytesMut::new(),
+            write_buffer: BytesMut::new(),
+        }
+    }
+
+    /// Returns the port, for example to change its settings.
+    pub fn get_ref(&self) -> &dyn SerialPort {
+        self.port.as_ref()
+    }
+
+    /// Returns the port mutably. Data read from the port directly bypasses
+    /// the codec.
+    pub fn get_mut(&mut self) -> &mut dyn SerialPort {
+        self.port.as_mut()
+    }
+
+    /// Returns the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns the codec mutably.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Returns data that was received but is not part of a complete frame yet.
+    pub fn read_buffer(&self) -> &[u8] {
+        &self.read_buffer
+    }
+
+    /// Returns the port. Buffered data that is not part of a complete frame
+    /// yet is lost.
+    pub fn into_inner(self) -> Box<dyn SerialPort> {
+        self.port
+    }
+
+    /// Reads the next frame.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the decoded frame, or an error if the
+    /// port failed, timed out or the codec rejected the data. If the port
+    /// reports end of file before a frame is complete, the error has kind
+    /// `UnexpectedEof`.
+    pub fn read_frame(&mut self) -> Result<C::Item, C::Error>
+    where
+        C: Decoder,
+    {
+        let mut chunk = [0u8; 256];
+
+        loop {
+            if let Some(frame) = self.codec.decode(&mut self.read_buffer)? {
+                return Ok(frame);
+            }
+
+            match self.port.read(&mut chunk) {
+                Ok(0) => {
+                    return match self.codec.decode_eof(&mut self.read_buffer)? {
+                        Some(frame) => Ok(frame),
+                        None => Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                    };
+                }
+                Ok(n) => self.read_buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Encodes `item` and writes it to the port.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` once the whole frame was handed to the port, or an
+    /// error if the codec rejected the item or the port failed.
+    pub fn write_frame<I>(&mut self, item: I) -> Result<(), C::Error>
+    where
+        C: Encoder<I>,
+    {
+        self.write_buffer.clear();
+        self.codec.encode(item, &mut self.write_buffer)?;
+        self.port.write_all(&self.write_buffer)?;
+
+        Ok(())
+    }
+}
@@ -11,17 +11,17 @@ pub trait Communication {
     /// # Examples
     ///
     /// ```rust
-    /// use serialport::SerialPortBuilder;
-    ///
-    /// let mut port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
+    /// port.close()?;
     ///
     /// if port.is_open() {
     ///     println!("Port is ready for communication");
     /// } else {
     ///     port.open()?;
     /// }
+    /// assert!(port.is_open());
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
     fn is_open(&self) -> bool;
@@ -39,11 +39,9 @@ pub trait Communication {
     /// # Examples
     ///
     /// ```rust
-    /// use serialport::SerialPortBuilder;
-    ///
-    /// let mut port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// // Ports built with a path are opened right away
+    /// let (_master, mut port) = serialport::pair()?;
     ///
     /// let result = port.open();
     /// assert!(result.is_err());
@@ -51,7 +49,7 @@ pub trait Communication {
     ///     result.unwrap_err().kind(),
     ///     std::io::ErrorKind::AlreadyExists
     /// );
-    ///
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
     fn open(&mut self) -> Result<()>;
@@ -70,16 +68,15 @@ pub trait Communication {
     /// # Examples
     ///
     /// ```rust
-    /// use serialport::SerialPortBuilder;
-    ///
-    /// let mut port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
     ///
     /// // Use the port...
     ///
     /// port.close()?;
     /// println!("Port closed successfully");
+    /// assert!(!port.is_open());
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
     fn close(&mut self) -> Result<()>;
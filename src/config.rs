@@ -3,7 +3,7 @@
 //! This module provides type-safe enums for serial port configuration and a builder
 //! pattern for creating serial port instances with specific settings.
 
-use std::fmt;
+use std::{fmt, io, str::FromStr};
 
 /// Number of data bits per character in serial communication.
 ///
@@ -48,6 +48,21 @@ impl fmt::Display for DataBits {
     }
 }
 
+impl FromStr for DataBits {
+    type Err = io::Error;
+
+    /// Parses a number of data bits from 5 to 8.
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s.trim() {
+            "5" => Ok(DataBits::Five),
+            "6" => Ok(DataBits::Six),
+            "7" => Ok(DataBits::Seven),
+            "8" => Ok(DataBits::Eight),
+            _ => Err(invalid_setting("data bits", s)),
+        }
+    }
+}
+
 /// Flow control mechanism for managing data transmission.
 ///
 /// Flow control prevents data loss by controlling when the sender
@@ -88,6 +103,21 @@ impl fmt::Display for FlowControl {
     }
 }
 
+impl FromStr for FlowControl {
+    type Err = io::Error;
+
+    /// Parses flow control, ignoring case: `none`, `xonxoff` (or
+    /// `XON/XOFF`, `software`) or `rtscts` (or `RTS/CTS`, `hardware`).
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(FlowControl::None),
+            "xonxoff" | "xon/xoff" | "software" => Ok(FlowControl::Software),
+            "rtscts" | "rts/cts" | "hardware" => Ok(FlowControl::Hardware),
+            _ => Err(invalid_setting("flow control", s)),
+        }
+    }
+}
+
 /// Parity bit configuration for error detection.
 ///
 /// Parity bits are used to detect single-bit errors in transmission.
@@ -140,6 +170,36 @@ impl fmt::Display for Parity {
     }
 }
 
+impl Parity {
+    /// Returns the letter for the parity in the `8N1` notation.
+    fn letter(self) -> char {
+        match self {
+            Parity::None => 'N',
+            Parity::Odd => 'O',
+            Parity::Even => 'E',
+            Parity::Mark => 'M',
+            Parity::Space => 'S',
+        }
+    }
+}
+
+impl FromStr for Parity {
+    type Err = io::Error;
+
+    /// Parses a parity name or its letter, ignoring case: `None` or `N`,
+    /// `Odd` or `O`, `Even` or `E`, `Mark` or `M`, `Space` or `S`.
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "n" | "none" => Ok(Parity::None),
+            "o" | "odd" => Ok(Parity::Odd),
+            "e" | "even" => Ok(Parity::Even),
+            "m" | "mark" => Ok(Parity::Mark),
+            "s" | "space" => Ok(Parity::Space),
+            _ => Err(invalid_setting("parity", s)),
+        }
+    }
+}
+
 /// Number of stop bits used to signal the end of a character.
 ///
 /// Stop bits provide a pause between characters, allowing the receiver
@@ -178,6 +238,144 @@ impl fmt::Display for StopBits {
     }
 }
 
+impl FromStr for StopBits {
+    type Err = io::Error;
+
+    /// Parses a number of stop bits: `1`, `1.5` or `2`.
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s.trim() {
+            "1" => Ok(StopBits::One),
+            "1.5" => Ok(StopBits::OnePointFive),
+            "2" => Ok(StopBits::Two),
+            _ => Err(invalid_setting("stop bits", s)),
+        }
+    }
+}
+
+fn invalid_setting(setting: &str, value: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid {setting}: {value:?}"),
+    )
+}
+
+/// The baud rate, character framing and flow control of a serial line.
+///
+/// Settings are written in the compact notation used by terminal programs
+/// and datasheets: the baud rate, then data bits, parity letter and stop
+/// bits, then optionally the flow control, separated by commas or spaces.
+/// Parity letters are `N`one, `O`dd, `E`ven, `M`ark and `S`pace, and flow
+/// control is `none`, `rtscts` or `xonxoff`. Without the framing, `8N1` is
+/// assumed.
+///
+/// Settings are displayed in the same notation, with commas and without
+/// flow control when there is none, so they round-trip through strings.
+///
+/// # Examples
+///
+/// ```rust
+/// use serialport::config::{FlowControl, LineSettings, Parity, StopBits};
+///
+/// let settings: LineSettings = "9600 7E2 rtscts".parse()?;
+/// assert_eq!(settings.baud_rate, 9600);
+/// assert_eq!(settings.parity, Parity::Even);
+/// assert_eq!(settings.stop_bits, StopBits::Two);
+/// assert_eq!(settings.flow_control, FlowControl::Hardware);
+/// assert_eq!(settings.to_string(), "9600,7E2,rtscts");
+///
+/// assert_eq!("115200".parse::<LineSettings>()?.to_string(), "115200,8N1");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineSettings {
+    /// The baud rate in symbols-per-second
+    pub baud_rate: u32,
+    /// Number of bits used to represent a character sent on the line
+    pub data_bits: DataBits,
+    /// The type of parity to use for error checking
+    pub parity: Parity,
+    /// Number of bits to use to signal the end of a character
+    pub stop_bits: StopBits,
+    /// The type of signalling to use for controlling data transfer
+    pub flow_control: FlowControl,
+}
+
+impl Default for LineSettings {
+    /// Returns 9600 baud, 8N1 without flow control.
+    fn default() -> Self {
+        Self {
+            baud_rate: 9600,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+        }
+    }
+}
+
+impl fmt::Display for LineSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{}{}{}",
+            self.baud_rate,
+            self.data_bits,
+            self.parity.letter(),
+            self.stop_bits
+        )?;
+
+        match self.flow_control {
+            FlowControl::None => Ok(()),
+            FlowControl::Software => write!(f, ",xonxoff"),
+            FlowControl::Hardware => write!(f, ",rtscts"),
+        }
+    }
+}
+
+impl FromStr for LineSettings {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        let mut fields = s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|field| !field.is_empty());
+
+        let mut settings = LineSettings::default();
+
+        let baud_rate = fields.next().unwrap_or_default();
+        // parse would also accept a sign
+        settings.baud_rate = match baud_rate.parse() {
+            Ok(rate) if rate > 0 && baud_rate.bytes().all(|c| c.is_ascii_digit()) => rate,
+            _ => return Err(invalid_setting("baud rate", baud_rate)),
+        };
+
+        if let Some(framing) = fields.next() {
+            let invalid = || invalid_setting("framing", framing);
+
+            let (data_bits, rest) = framing.split_at_checked(1).ok_or_else(invalid)?;
+            let (parity, stop_bits) = rest.split_at_checked(1).ok_or_else(invalid)?;
+
+            settings.data_bits = data_bits.parse().map_err(|_| invalid())?;
+            settings.parity = parity.parse().map_err(|_| invalid())?;
+            settings.stop_bits = stop_bits.parse().map_err(|_| invalid())?;
+        }
+
+        if let Some(flow_control) = fields.next() {
+            settings.flow_control = flow_control.parse()?;
+        }
+
+        if let Some(field) = fields.next() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unexpected setting: {field:?}"),
+            ));
+        }
+
+        Ok(settings)
+    }
+}
+
 /// Specifies which buffer(s) to clear in a serial port.
 ///
 /// Used to indicate whether to clear the input buffer, output buffer, or both.
@@ -192,6 +390,160 @@ pub enum ClearBuffer {
     All,
 }
 
+/// RS-485 half-duplex settings.
+///
+/// An RS-485 transceiver only drives the bus while its driver-enable input is
+/// asserted, which is usually wired to RTS. In RS-485 mode the port asserts
+/// RTS around each transmission and releases the bus afterwards. Linux
+/// drivers that support RS-485 switch RTS in the kernel; other ports toggle
+/// RTS in software around each write, waiting until the data has left the
+/// port before releasing the bus.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use serialport::{SerialPortBuilder, config::Rs485Config};
+///
+/// let builder = SerialPortBuilder::new().rs485(Rs485Config {
+///     enabled: true,
+///     delay_after_send: Duration::from_millis(1),
+///     ..Rs485Config::default()
+/// });
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct Rs485Config {
+    /// Whether RS-485 mode is enabled
+    pub enabled: bool,
+    /// The level of RTS while sending, `true` for asserted
+    pub rts_on_send: bool,
+    /// The level of RTS after sending, `true` for asserted
+    pub rts_after_send: bool,
+    /// Time between switching RTS and starting to send
+    #[cfg_attr(feature = "serde", serde(with = "crate::profile::duration"))]
+    pub delay_before_send: std::time::Duration,
+    /// Time between the end of the transmission and switching RTS back
+    #[cfg_attr(feature = "serde", serde(with = "crate::profile::duration"))]
+    pub delay_after_send: std::time::Duration,
+    /// Whether data is received while sending. Transceivers that keep their
+    /// receiver enabled echo every transmitted byte back.
+    pub rx_during_tx: bool,
+}
+
+impl Default for Rs485Config {
+    /// Returns a disabled configuration that asserts RTS only while sending,
+    /// without delays and without receiving during transmission.
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rts_on_send: true,
+            rts_after_send: false,
+            delay_before_send: std::time::Duration::ZERO,
+            delay_after_send: std::time::Duration::ZERO,
+            rx_during_tx: false,
+        }
+    }
+}
+
+/// A set of line status events reported by `SerialPort::wait_for_event`.
+///
+/// Events are combined with `|` to build the mask of events to wait for,
+/// and the same type reports which of them occurred.
+///
+/// # Examples
+///
+/// ```rust
+/// use serialport::config::LineEvents;
+///
+/// let mask = LineEvents::CLEAR_TO_SEND | LineEvents::CARRIER_DETECT;
+/// assert!(mask.contains(LineEvents::CARRIER_DETECT));
+/// assert!(!mask.contains(LineEvents::BREAK));
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineEvents(u8);
+
+impl LineEvents {
+    /// The CTS (Clear To Send) line changed state
+    pub const CLEAR_TO_SEND: LineEvents = LineEvents(1 << 0);
+    /// The DSR (Data Set Ready) line changed state
+    pub const DATA_SET_READY: LineEvents = LineEvents(1 << 1);
+    /// The RI (Ring Indicator) line changed state
+    pub const RING_INDICATOR: LineEvents = LineEvents(1 << 2);
+    /// The CD (Carrier Detect) line changed state
+    pub const CARRIER_DETECT: LineEvents = LineEvents(1 << 3);
+    /// A break condition was received
+    pub const BREAK: LineEvents = LineEvents(1 << 4);
+    /// A character was received with a framing error
+    pub const FRAMING_ERROR: LineEvents = LineEvents(1 << 5);
+    /// A character was received with a parity error
+    pub const PARITY_ERROR: LineEvents = LineEvents(1 << 6);
+    /// Received data was lost because the hardware buffer overflowed
+    pub const OVERRUN: LineEvents = LineEvents(1 << 7);
+
+    /// Changes of any of the modem status lines
+    pub const MODEM_LINES: LineEvents = LineEvents(0b0000_1111);
+    /// Any break or receive error
+    pub const LINE_ERRORS: LineEvents = LineEvents(0b1111_0000);
+
+    /// Returns an empty set of events.
+    pub const fn empty() -> LineEvents {
+        LineEvents(0)
+    }
+
+    /// Returns the set of all events.
+    pub const fn all() -> LineEvents {
+        LineEvents(0xFF)
+    }
+
+    /// Returns `true` if no events are set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if all events in `other` are also set in `self`.
+    pub const fn contains(self, other: LineEvents) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns `true` if any event in `other` is also set in `self`.
+    pub const fn intersects(self, other: LineEvents) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl std::ops::BitOr for LineEvents {
+    type Output = LineEvents;
+
+    fn bitor(self, rhs: LineEvents) -> LineEvents {
+        LineEvents(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for LineEvents {
+    fn bitor_assign(&mut self, rhs: LineEvents) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl std::ops::BitAnd for LineEvents {
+    type Output = LineEvents;
+
+    fn bitand(self, rhs: LineEvents) -> LineEvents {
+        LineEvents(self.0 & rhs.0)
+    }
+}
+
+impl std::ops::Not for LineEvents {
+    type Output = LineEvents;
+
+    fn not(self) -> LineEvents {
+        LineEvents(!self.0)
+    }
+}
+
 /// Contains all possible USB information about a `SerialPort`
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -212,6 +564,146 @@ pub struct UsbPortInfo {
     pub interface: Option<u8>,
 }
 
+/// Selects a USB serial port by its identifiers instead of its path.
+///
+/// Device paths such as `/dev/ttyUSB0` or `COM3` depend on the order in which
+/// adapters were plugged in, while the vendor and product IDs and the serial
+/// number stay the same.
+///
+/// # Examples
+///
+/// ```rust
+/// use serialport::config::{UsbFilter, UsbPortInfo};
+///
+/// let filter = UsbFilter::new(0x0403, 0x6001).serial_number("A5XK3RJT");
+///
+/// let info = UsbPortInfo {
+///     vid: 0x0403,
+///     pid: 0x6001,
+///     serial_number: Some("A5XK3RJT".to_string()),
+///     manufacturer: None,
+///     product: None,
+///     interface: None,
+/// };
+/// assert!(filter.matches(&info));
+/// assert!(!UsbFilter::new(0x0403, 0x6015).matches(&info));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct UsbFilter {
+    /// Vendor ID
+    #[cfg_attr(feature = "serde", serde(with = "crate::profile::usb_id"))]
+    pub vid: u16,
+    /// Product ID
+    #[cfg_attr(feature = "serde", serde(with = "crate::profile::usb_id"))]
+    pub pid: u16,
+    /// Serial number, or `None` to match any device with the IDs
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub serial_number: Option<String>,
+    /// Interface number, or `None` to match any port of the device
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub interface: Option<u8>,
+}
+
+impl UsbFilter {
+    /// Creates a filter matching any device with the given vendor and
+    /// product IDs.
+    pub fn new(vid: u16, pid: u16) -> Self {
+        Self {
+            vid,
+            pid,
+            serial_number: None,
+            interface: None,
+        }
+    }
+
+    /// Restricts the filter to the device with the given serial number.
+    #[must_use]
+    pub fn serial_number(mut self, serial_number: impl Into<String>) -> Self {
+        self.serial_number = Some(serial_number.into());
+        self
+    }
+
+    /// Restricts the filter to the port on the given interface of the
+    /// device.
+    #[must_use]
+    pub fn interface(mut self, interface: u8) -> Self {
+        self.interface = Some(interface);
+        self
+    }
+
+    /// Returns whether the USB device matches the filter.
+    pub fn matches(&self, info: &UsbPortInfo) -> bool {
+        info.vid == self.vid
+            && info.pid == self.pid
+            && self
+                .serial_number
+                .as_ref()
+                .is_none_or(|serial_number| info.serial_number.as_ref() == Some(serial_number))
+            && self
+                .interface
+                .is_none_or(|interface| info.interface == Some(interface))
+    }
+
+    /// Finds the path of the one available port that matches the filter.
+    ///
+    /// # Returns
+    ///
+    /// Returns the port name, or an error of kind `NotFound` if no port
+    /// matches, or `InvalidInput` if several do.
+    pub fn find(&self) -> io::Result<String> {
+        let mut matches =
+            crate::available_ports()?
+                .into_iter()
+                .filter(|port| match &port.port_type {
+                    SerialPortType::UsbPort(info) => self.matches(info),
+                    _ => false,
+                });
+
+        let port = matches.next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no serial port found for USB device {}", self),
+            )
+        })?;
+
+        if let Some(other) = matches.next() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "USB device {} matches several ports ({}, {})",
+                    self, port.port_name, other.port_name
+                ),
+            ));
+        }
+
+        Ok(port.port_name)
+    }
+}
+
+impl std::fmt::Display for UsbFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04x}:{:04x}", self.vid, self.pid)?;
+
+        if let Some(serial_number) = &self.serial_number {
+            write!(f, " ({})", serial_number)?;
+        }
+
+        if let Some(interface) = self.interface {
+            write!(f, " interface {}", interface)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// The physical type of a `SerialPort`
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -235,3 +727,204 @@ pub struct SerialPortInfo {
     /// The hardware device type that exposes this port
     pub port_type: SerialPortType,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(
+        baud_rate: u32,
+        data_bits: DataBits,
+        parity: Parity,
+        stop_bits: StopBits,
+        flow_control: FlowControl,
+    ) -> LineSettings {
+        LineSettings {
+            baud_rate,
+            data_bits,
+            parity,
+            stop_bits,
+            flow_control,
+        }
+    }
+
+    #[test]
+    fn line_settings_framing() {
+        let cases = [
+            (
+                "9600,8N1",
+                settings(
+                    9600,
+                    DataBits::Eight,
+                    Parity::None,
+                    StopBits::One,
+                    FlowControl::None,
+                ),
+            ),
+            (
+                "19200,7E2",
+                settings(
+                    19200,
+                    DataBits::Seven,
+                    Parity::Even,
+                    StopBits::Two,
+                    FlowControl::None,
+                ),
+            ),
+            (
+                "300,5M1.5",
+                settings(
+                    300,
+                    DataBits::Five,
+                    Parity::Mark,
+                    StopBits::OnePointFive,
+                    FlowControl::None,
+                ),
+            ),
+            (
+                "1200,6S1",
+                settings(
+                    1200,
+                    DataBits::Six,
+                    Parity::Space,
+                    StopBits::One,
+                    FlowControl::None,
+                ),
+            ),
+            (
+                "115200,8O1,rtscts",
+                settings(
+                    115200,
+                    DataBits::Eight,
+                    Parity::Odd,
+                    StopBits::One,
+                    FlowControl::Hardware,
+                ),
+            ),
+            (
+                "57600,7N1,xonxoff",
+                settings(
+                    57600,
+                    DataBits::Seven,
+                    Parity::None,
+                    StopBits::One,
+                    FlowControl::Software,
+                ),
+            ),
+        ];
+
+        for (s, expected) in cases {
+            assert_eq!(s.parse::<LineSettings>().unwrap(), expected, "{s}");
+            assert_eq!(expected.to_string(), s);
+        }
+    }
+
+    #[test]
+    fn line_settings_defaults() {
+        assert_eq!(
+            "4800".parse::<LineSettings>().unwrap(),
+            LineSettings {
+                baud_rate: 4800,
+                ..LineSettings::default()
+            }
+        );
+        assert_eq!(LineSettings::default().to_string(), "9600,8N1");
+        assert_eq!(
+            "9600,8N1,none".parse::<LineSettings>().unwrap().to_string(),
+            "9600,8N1"
+        );
+    }
+
+    #[test]
+    fn line_settings_case_and_separators() {
+        let expected = settings(
+            9600,
+            DataBits::Seven,
+            Parity::Even,
+            StopBits::Two,
+            FlowControl::Hardware,
+        );
+
+        for s in [
+            "9600,7e2,rtscts",
+            "9600 7E2 RTSCTS",
+            "  9600 ,\t7E2,, RtsCts\n",
+            "9600,7E2,RTS/CTS",
+        ] {
+            assert_eq!(s.parse::<LineSettings>().unwrap(), expected, "{s:?}");
+        }
+    }
+
+    #[test]
+    fn line_settings_round_trip() {
+        for data_bits in [
+            DataBits::Five,
+            DataBits::Six,
+            DataBits::Seven,
+            DataBits::Eight,
+        ] {
+            for parity in [
+                Parity::None,
+                Parity::Odd,
+                Parity::Even,
+                Parity::Mark,
+                Parity::Space,
+            ] {
+                for stop_bits in [StopBits::One, StopBits::OnePointFive, StopBits::Two] {
+                    for flow_control in [
+                        FlowControl::None,
+                        FlowControl::Software,
+                        FlowControl::Hardware,
+                    ] {
+                        let settings = settings(38400, data_bits, parity, stop_bits, flow_control);
+                        assert_eq!(
+                            settings.to_string().parse::<LineSettings>().unwrap(),
+                            settings
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn line_settings_invalid() {
+        for s in [
+            "",
+            " , ",
+            "0",
+            "-9600",
+            "+9600",
+            "9600.5",
+            "4294967296",
+            "fast",
+            "8N1",
+            "9600,8N",
+            "9600,8",
+            "9600,9N1",
+            "9600,4N1",
+            "9600,8X1",
+            "9600,8N3",
+            "9600,8N1.0",
+            "9600,8N11",
+            "9600,N81",
+            "9600,8-N-1",
+            "9600,éN1",
+            "9600,8N1,dtrdsr",
+            "9600,8N1,rtscts,extra",
+            "9600;8N1",
+        ] {
+            let error = s.parse::<LineSettings>().unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{s:?}");
+        }
+    }
+
+    #[test]
+    fn line_settings_error_names_the_field() {
+        let error = "9600,8N3".parse::<LineSettings>().unwrap_err();
+        assert_eq!(error.to_string(), r#"invalid framing: "8N3""#);
+
+        let error = "9600,8N1,rtscts,extra".parse::<LineSettings>().unwrap_err();
+        assert_eq!(error.to_string(), r#"unexpected setting: "extra""#);
+    }
+}
@@ -13,7 +13,7 @@
 //!
 //! ## Quick Start
 //!
-//! ```rust
+//! ```rust,no_run
 //! use std::time::Duration;
 //! use serialport::config::{DataBits, FlowControl, Parity, StopBits};
 //!
@@ -51,6 +51,7 @@
 //!
 //! Currently supported platforms:
 //! - **Windows**: Full native support using WinAPI
+//! - **Linux**: Native support using termios
 //!
 //! ## Why This Library?
 //!
@@ -62,21 +63,45 @@
 //! This library addresses these issues by providing direct platform integration
 //! and proper state management.
 
-use std::{io, time::Duration};
+use std::{
+    io::{self, ErrorKind},
+    time::{Duration, Instant},
+};
 
+#[cfg(feature = "codec")]
+pub mod codec;
 pub mod communication;
 pub mod config;
+pub mod mock;
+pub mod modbus;
+#[cfg(feature = "serde")]
+pub mod profile;
+pub mod reconnect;
+pub mod split;
+pub mod xmodem;
+pub mod ymodem;
 
 #[cfg(windows)]
 mod windows;
 #[cfg(windows)]
 pub use windows::ComPort;
 
+#[cfg(unix)]
+mod posix;
+#[cfg(all(unix, feature = "async-io"))]
+pub use posix::AsyncIoSerialPort;
+#[cfg(all(unix, feature = "tokio"))]
+pub use posix::AsyncSerialPort;
+#[cfg(unix)]
+pub use posix::TTYPort;
+
 use communication::Communication;
 use config::{
-    ClearBuffer, DataBits, FlowControl, Parity, SerialPortInfo, SerialPortType, StopBits,
-    UsbPortInfo,
+    ClearBuffer, DataBits, FlowControl, LineEvents, LineSettings, Parity, Rs485Config,
+    SerialPortInfo, StopBits, UsbFilter,
 };
+#[cfg(any(windows, target_os = "linux"))]
+use config::{SerialPortType, UsbPortInfo};
 
 /// Builder for creating and configuring serial ports.
 ///
@@ -84,9 +109,12 @@ use config::{
 /// configuring serial port parameters before creating the actual port.
 /// All configuration methods return `self` to allow method chaining.
 ///
+/// With the `serde` feature the builder can be serialized and deserialized.
+/// See the `profile` module for the format.
+///
 /// # Examples
 ///
-/// ```rust
+/// ```rust,no_run
 /// use std::time::Duration;
 /// use serialport::config::{DataBits, Parity, StopBits, FlowControl};
 ///
@@ -101,6 +129,8 @@ use config::{
 /// ```
 #[must_use]
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "profile::BuilderConfig"))]
 pub struct SerialPortBuilder {
     /// The port name, usually the device path
     path: String,
@@ -115,7 +145,19 @@ pub struct SerialPortBuilder {
     /// Number of bits to use to signal the end of a character
     stop_bits: StopBits,
     /// Amount of time to wait to receive data before timing out
-    timeout: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "profile::duration"))]
+    read_timeout: Duration,
+    /// Amount of time to wait for room in the output buffer before timing out
+    #[cfg_attr(feature = "serde", serde(with = "profile::duration"))]
+    write_timeout: Duration,
+    /// Idle time after which a read returns the data received so far
+    #[cfg_attr(feature = "serde", serde(with = "profile::duration"))]
+    inter_byte_timeout: Duration,
+    /// RS-485 half-duplex settings
+    rs485: Rs485Config,
+    /// The USB device to look up when the port is opened, instead of `path`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    usb: Option<UsbFilter>,
 }
 
 impl SerialPortBuilder {
@@ -128,7 +170,9 @@ impl SerialPortBuilder {
     /// - Flow control: None
     /// - Parity: None
     /// - Stop bits: 1
-    /// - Timeout: 0 seconds (non-blocking)
+    /// - Read and write timeouts: 0 seconds (non-blocking)
+    /// - Inter-byte timeout: Disabled
+    /// - RS-485: Disabled
     ///
     /// # Examples
     ///
@@ -145,7 +189,11 @@ impl SerialPortBuilder {
             flow_control: FlowControl::None,
             parity: Parity::None,
             stop_bits: StopBits::One,
-            timeout: Duration::ZERO,
+            read_timeout: Duration::ZERO,
+            write_timeout: Duration::ZERO,
+            inter_byte_timeout: Duration::ZERO,
+            rs485: Rs485Config::default(),
+            usb: None,
         }
     }
 
@@ -171,6 +219,74 @@ impl SerialPortBuilder {
     /// ```
     pub fn path<'a>(mut self, path: std::borrow::Cow<'a, str>) -> Self {
         self.path = path.into_owned();
+        self.usb = None;
+        self
+    }
+
+    /// Selects the port by the vendor and product IDs of its USB device
+    /// instead of by path.
+    ///
+    /// The device is looked up with [`available_ports`] every time the port
+    /// is opened, so a port that is closed and reopened finds the device
+    /// again after it was unplugged and came back under a different path.
+    /// Opening fails with `NotFound` if no port matches, and with
+    /// `InvalidInput` if several do; use [`serial_number`](Self::serial_number)
+    /// and [`interface`](Self::interface) to tell identical adapters and the
+    /// ports of multi-port adapters apart. Setting a path replaces the USB
+    /// device again.
+    ///
+    /// # Arguments
+    ///
+    /// * `vid` - The USB vendor ID
+    /// * `pid` - The USB product ID
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use serialport::SerialPortBuilder;
+    ///
+    /// // An FTDI FT232R adapter with a known serial number
+    /// let port = SerialPortBuilder::new()
+    ///     .usb_device(0x0403, 0x6001)
+    ///     .serial_number("A5XK3RJT")
+    ///     .baud_rate(115200)
+    ///     .build()?;
+    ///
+    /// println!("Opened {:?}", port.path());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn usb_device(mut self, vid: u16, pid: u16) -> Self {
+        self.usb = Some(UsbFilter::new(vid, pid));
+        self
+    }
+
+    /// Restricts the USB device selected with [`usb_device`](Self::usb_device)
+    /// to the one with the given serial number.
+    ///
+    /// Has no effect unless a USB device was selected.
+    ///
+    /// # Arguments
+    ///
+    /// * `serial_number` - The serial number reported by the device
+    pub fn serial_number(mut self, serial_number: impl Into<String>) -> Self {
+        if let Some(usb) = self.usb.take() {
+            self.usb = Some(usb.serial_number(serial_number));
+        }
+        self
+    }
+
+    /// Restricts the USB device selected with [`usb_device`](Self::usb_device)
+    /// to the port on the given interface, for adapters with several ports.
+    ///
+    /// Has no effect unless a USB device was selected.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The USB interface number of the port
+    pub fn interface(mut self, interface: u8) -> Self {
+        if let Some(usb) = self.usb.take() {
+            self.usb = Some(usb.interface(interface));
+        }
         self
     }
 
@@ -286,15 +402,20 @@ impl SerialPortBuilder {
         self
     }
 
-    /// Sets the read timeout duration.
+    /// Sets the read and write timeout duration.
     ///
-    /// This determines how long read operations will wait for data before
-    /// timing out. A timeout of zero means non-blocking operation - reads
-    /// will return immediately if no data is available.
+    /// This determines how long read operations will wait for data, and
+    /// write operations for room in the output buffer, before timing out. A
+    /// timeout of zero means non-blocking operation - reads will return
+    /// immediately if no data is available.
+    ///
+    /// Use [`read_timeout`](SerialPortBuilder::read_timeout) and
+    /// [`write_timeout`](SerialPortBuilder::write_timeout) to set them
+    /// separately.
     ///
     /// # Arguments
     ///
-    /// * `timeout` - The timeout duration for read operations
+    /// * `timeout` - The timeout duration for read and write operations
     ///
     /// # Examples
     ///
@@ -309,7 +430,129 @@ impl SerialPortBuilder {
     ///     .timeout(Duration::from_secs(0));      // Non-blocking
     /// ```
     pub fn timeout(mut self, timeout: Duration) -> Self {
-        self.timeout = timeout;
+        self.read_timeout = timeout;
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Sets the read timeout duration.
+    ///
+    /// A read waits up to this long for the first byte to arrive. A timeout
+    /// of zero makes reads return immediately if no data is available.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The timeout duration for read operations
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use serialport::SerialPortBuilder;
+    ///
+    /// let builder = SerialPortBuilder::new()
+    ///     .read_timeout(Duration::from_secs(1))
+    ///     .write_timeout(Duration::from_millis(100));
+    /// ```
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Sets the write timeout duration.
+    ///
+    /// A write waits up to this long for room in the output buffer, which
+    /// only fills up when the device holds off transmission through flow
+    /// control or when data is written faster than the baud rate.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The timeout duration for write operations
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Sets the inter-byte timeout duration.
+    ///
+    /// When set, a read that received data keeps reading until the buffer
+    /// is full, the line has been idle for this long, or the read timeout
+    /// has passed since the read started. This is the "N bytes or T idle"
+    /// mode of termios `VMIN`/`VTIME`, with N being the size of the buffer,
+    /// but with millisecond resolution. It is what detects the end of a
+    /// frame in protocols such as Modbus RTU, which separate frames with
+    /// silence.
+    ///
+    /// A timeout of zero, the default, disables it: reads return whatever
+    /// data is available as soon as there is some. It also has no effect
+    /// while the read timeout is zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The idle time that ends a read
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use serialport::SerialPortBuilder;
+    ///
+    /// // 3.5 characters of 11 bits at 9600 baud end a Modbus RTU frame
+    /// let builder = SerialPortBuilder::new()
+    ///     .baud_rate(9600)
+    ///     .read_timeout(Duration::from_secs(1))
+    ///     .inter_byte_timeout(Duration::from_micros(4010));
+    /// ```
+    pub fn inter_byte_timeout(mut self, timeout: Duration) -> Self {
+        self.inter_byte_timeout = timeout;
+        self
+    }
+
+    /// Sets the RS-485 half-duplex settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `rs485` - The RS-485 settings to use
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serialport::{SerialPortBuilder, config::Rs485Config};
+    ///
+    /// let builder = SerialPortBuilder::new()
+    ///     .rs485(Rs485Config {
+    ///         enabled: true,
+    ///         ..Rs485Config::default()
+    ///     });
+    /// ```
+    pub fn rs485(mut self, rs485: Rs485Config) -> Self {
+        self.rs485 = rs485;
+        self
+    }
+
+    /// Sets the baud rate, data bits, parity, stop bits and flow control at
+    /// once.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The line settings to use
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serialport::config::LineSettings;
+    ///
+    /// let settings: LineSettings = "19200,8E1".parse()?;
+    /// let builder = serialport::new("/dev/ttyUSB0", 9600).line_settings(settings);
+    /// assert_eq!(builder.to_string(), "19200,8E1");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn line_settings(mut self, settings: LineSettings) -> Self {
+        self.baud_rate = settings.baud_rate;
+        self.data_bits = settings.data_bits;
+        self.parity = settings.parity;
+        self.stop_bits = settings.stop_bits;
+        self.flow_control = settings.flow_control;
         self
     }
 
@@ -335,23 +578,149 @@ impl SerialPortBuilder {
     ///
     /// # Examples
     ///
-    /// ```rust
+    /// ```rust,no_run
     /// let port = serialport::new("COM1", 115200)
     ///     .build()?;
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    #[must_use]
     pub fn build(self) -> io::Result<Box<dyn SerialPort>> {
         #[cfg(windows)]
         return ComPort::new(self).map(|port| Box::new(port) as Box<dyn SerialPort>);
 
-        // Placeholder for non-Windows implementation
-        #[cfg(not(windows))]
+        #[cfg(unix)]
+        return TTYPort::new(self).map(|port| Box::new(port) as Box<dyn SerialPort>);
+
+        // Placeholder for other platforms
+        #[cfg(not(any(unix, windows)))]
         Err(std::io::Error::new(
             std::io::ErrorKind::Other,
             "Serial port builder is not implemented for this platform",
         ))
     }
+
+    /// Creates a pair of connected virtual serial ports with the configured settings.
+    ///
+    /// The ports are backed by a pseudo-terminal, so bytes written to one port
+    /// can be read from the other. This makes it possible to exercise code
+    /// written against `SerialPort` without any serial hardware. The configured
+    /// path is ignored.
+    ///
+    /// The first port is the pseudo-terminal master. It has no path and cannot
+    /// be reopened once closed. The second port is the slave, which can be
+    /// closed and reopened by its path while the master is alive.
+    ///
+    /// Baud rate, stop bits, flow control and timeout are applied to both ends
+    /// and reported back by the getters. Linux pseudo-terminals always use 8
+    /// data bits without parity, whatever was requested.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the master and slave ports, or an error
+    /// if the pseudo-terminal could not be allocated or configured.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::io::{Read, Write};
+    /// use std::time::Duration;
+    /// use serialport::config::StopBits;
+    ///
+    /// let (mut master, mut slave) = serialport::new("", 115200)
+    ///     .stop_bits(StopBits::Two)
+    ///     .timeout(Duration::from_millis(100))
+    ///     .build_pair()?;
+    ///
+    /// assert_eq!(slave.baud_rate()?, 115200);
+    /// assert_eq!(slave.stop_bits()?, StopBits::Two);
+    ///
+    /// master.write_all(b"ping")?;
+    ///
+    /// let mut buffer = [0u8; 4];
+    /// slave.read_exact(&mut buffer)?;
+    /// assert_eq!(&buffer, b"ping");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn build_pair(self) -> io::Result<(Box<dyn SerialPort>, Box<dyn SerialPort>)> {
+        TTYPort::pair(self).map(|(master, slave)| {
+            (
+                Box::new(master) as Box<dyn SerialPort>,
+                Box::new(slave) as Box<dyn SerialPort>,
+            )
+        })
+    }
+
+    /// Creates a new serial port for use with tokio with the configured settings.
+    ///
+    /// Like [`build`](SerialPortBuilder::build), the port is opened right
+    /// away if a path is set. It must be called from within a tokio runtime.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing either the configured port or an error
+    /// if it could not be opened or registered with the runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().enable_all().build()?.block_on(async {
+    /// let mut port = serialport::new("/dev/ttyUSB0", 115200).build_async()?;
+    /// port.write_all(b"AT\r\n").await?;
+    /// # Ok::<(), std::io::Error>(())
+    /// # })?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(all(unix, feature = "tokio"))]
+    pub fn build_async(self) -> io::Result<AsyncSerialPort> {
+        AsyncSerialPort::new(self)
+    }
+
+    /// Creates a new serial port for use with async-io based executors with
+    /// the configured settings.
+    ///
+    /// Like [`build`](SerialPortBuilder::build), the port is opened right
+    /// away if a path is set.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing either the configured port or an error
+    /// if it could not be opened or registered with the reactor.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use futures_lite::AsyncWriteExt;
+    ///
+    /// # async_io::block_on(async {
+    /// let mut port = serialport::new("/dev/ttyUSB0", 115200).build_async_io()?;
+    /// port.write_all(b"AT\r\n").await?;
+    /// # Ok::<(), std::io::Error>(())
+    /// # })?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(all(unix, feature = "async-io"))]
+    pub fn build_async_io(self) -> io::Result<AsyncIoSerialPort> {
+        AsyncIoSerialPort::new(self)
+    }
+}
+
+impl SerialPortBuilder {
+    /// Returns whether the builder names a device that can be opened.
+    pub(crate) fn has_device(&self) -> bool {
+        !self.path.is_empty() || self.usb.is_some()
+    }
+
+    /// Looks up the selected USB device, if any, and makes its port the path
+    /// to open.
+    pub(crate) fn resolve_usb(&mut self) -> io::Result<()> {
+        if let Some(usb) = &self.usb {
+            self.path = usb.find()?;
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for SerialPortBuilder {
@@ -363,6 +732,50 @@ impl Default for SerialPortBuilder {
     }
 }
 
+impl From<&SerialPortBuilder> for LineSettings {
+    fn from(builder: &SerialPortBuilder) -> Self {
+        LineSettings {
+            baud_rate: builder.baud_rate,
+            data_bits: builder.data_bits,
+            parity: builder.parity,
+            stop_bits: builder.stop_bits,
+            flow_control: builder.flow_control,
+        }
+    }
+}
+
+impl std::str::FromStr for SerialPortBuilder {
+    type Err = io::Error;
+
+    /// Parses line settings such as `"115200,8N1"` or `"9600 7E2 rtscts"`
+    /// into a builder with default values for everything else. See
+    /// [`LineSettings`] for the notation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serialport::SerialPortBuilder;
+    ///
+    /// let builder: SerialPortBuilder = "9600 7E2 rtscts".parse()?;
+    /// let builder = builder.path("/dev/ttyS0".into());
+    /// assert_eq!(builder.to_string(), "9600,7E2,rtscts");
+    ///
+    /// assert!("9600,8X1".parse::<SerialPortBuilder>().is_err());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn from_str(s: &str) -> io::Result<Self> {
+        Ok(SerialPortBuilder::new().line_settings(s.parse()?))
+    }
+}
+
+impl std::fmt::Display for SerialPortBuilder {
+    /// Formats the line settings of the builder, such as `115200,8N1`. The
+    /// path and timeouts are not included.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        LineSettings::from(self).fmt(f)
+    }
+}
+
 mod private {
     pub trait Private {
         /// Sets the raw path of the serial port.
@@ -400,21 +813,26 @@ mod private {
 /// - Query and change port settings (baud rate, data bits, parity, stop bits, flow control, timeout)
 /// - Get the port path/name
 /// - Query the number of bytes available for reading or writing
+/// - Read up to a terminator or line ending within an overall deadline
 /// - Clear port buffers
+/// - Drive and read the modem control lines (RTS, DTR, CTS, DSR, RI, CD)
+/// - Send break conditions
+/// - Wait for modem line changes and line errors
 /// - Clone the port handle
+/// - Split into reader and writer halves that can be used from different threads
 ///
 /// # Example
 ///
 /// ```rust
 /// use std::time::Duration;
-/// use serialport::{SerialPortBuilder, config::DataBits};
+/// use serialport::config::DataBits;
 ///
-/// let mut port = SerialPortBuilder::new()
-///     .path("COM1".into())
-///     .baud_rate(9600)
+/// # #[cfg(unix)] {
+/// // A virtual port pair stands in for a real device such as "COM1"
+/// let (_master, mut port) = serialport::new("", 9600)
 ///     .data_bits(DataBits::Eight)
-///     .timeout(Duration::from_secs(1))
-///     .build()?;
+///     .timeout(Duration::from_millis(100))
+///     .build_pair()?;
 ///
 /// if !port.is_open() {
 ///     port.open()?;
@@ -422,8 +840,8 @@ mod private {
 ///
 /// port.write_all(b"Hello, serial port!")?;
 /// let mut buffer = [0u8; 128];
-/// // This read will generate an error since we are not sending any data during the test
-/// // and the timeout is set to 1000ms.
+/// // This read will generate an error since the other end is not sending any data
+/// // and the timeout is set to 100ms.
 /// // In a real application, you would expect data to be available.
 /// let bytes_read_result = port.read(&mut buffer);
 ///
@@ -434,6 +852,7 @@ mod private {
 /// );
 ///
 /// port.close()?;
+/// # }
 /// # Ok::<(), std::io::Error>(())
 /// ```
 ///
@@ -482,16 +901,24 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     /// # Examples
     ///
     /// ```rust
-    /// use serialport::SerialPortBuilder;
+    /// use std::time::Duration;
     ///
-    /// let port1 = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (mut master, mut port1) = serialport::new("", 9600)
+    ///     .timeout(Duration::from_millis(100))
+    ///     .build_pair()?;
     ///
     /// let mut port2 = port1.try_clone()?;
     ///
     /// // Use port1 for reading and port2 for writing
     /// // (in separate threads if needed)
+    /// let writer = std::thread::spawn(move || port2.write_all(b"ping"));
+    /// writer.join().unwrap()?;
+    ///
+    /// let mut buffer = [0u8; 4];
+    /// master.read_exact(&mut buffer)?;
+    /// assert_eq!(&buffer, b"ping");
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
     fn try_clone(&self) -> io::Result<Box<dyn SerialPort>>;
@@ -507,19 +934,24 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     /// # Examples
     ///
     /// ```rust
-    /// use serialport::SerialPortBuilder;
-    ///
-    /// let port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (master, port) = serialport::pair()?;
     ///
     /// println!("Using port: {}", port.path().unwrap_or("Unknown".to_string()));
+    ///
+    /// // The master end of a virtual pair has no device path
+    /// assert_eq!(master.path(), None);
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
     fn path(&self) -> Option<String>;
 
     /// Gets the current baud rate.
     ///
+    /// This is the rate the driver actually applied, which can differ
+    /// slightly from the requested one when the hardware cannot produce it
+    /// exactly.
+    ///
     /// # Returns
     ///
     /// Returns a `Result<u32, std::io::Error>` containing either the
@@ -530,12 +962,14 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     /// ```rust
     /// use serialport::SerialPortBuilder;
     ///
-    /// let port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
+    /// # #[cfg(unix)] {
+    /// let (_master, port) = SerialPortBuilder::new()
     ///     .baud_rate(115200)
-    ///     .build()?;
+    ///     .build_pair()?;
     ///
     /// println!("Baud rate: {}", port.baud_rate()?);
+    /// assert_eq!(port.baud_rate()?, 115200);
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
     fn baud_rate(&self) -> io::Result<u32>;
@@ -576,9 +1010,39 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     ///
     /// # Returns
     ///
-    /// Returns the current timeout duration for read operations.
+    /// Returns the current timeout duration for read operations, the same as
+    /// [`read_timeout`](SerialPort::read_timeout).
     fn timeout(&self) -> Duration;
 
+    /// Gets the current read timeout.
+    ///
+    /// # Returns
+    ///
+    /// Returns how long a read waits for the first byte to arrive.
+    fn read_timeout(&self) -> Duration;
+
+    /// Gets the current write timeout.
+    ///
+    /// # Returns
+    ///
+    /// Returns how long a write waits for room in the output buffer.
+    fn write_timeout(&self) -> Duration;
+
+    /// Gets the current inter-byte timeout.
+    ///
+    /// # Returns
+    ///
+    /// Returns the idle time after which a read returns the data received so
+    /// far, or zero if reads return as soon as data is available.
+    fn inter_byte_timeout(&self) -> Duration;
+
+    /// Gets the current RS-485 settings.
+    ///
+    /// # Returns
+    ///
+    /// Returns the RS-485 settings last set on the port or its builder.
+    fn rs485(&self) -> Rs485Config;
+
     /// Gets the number of bytes available to be read from the input buffer.
     ///
     /// This function returns the number of bytes that have been received
@@ -599,14 +1063,13 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     /// # Examples
     ///
     /// ```rust
-    /// use serialport::SerialPortBuilder;
-    ///
-    /// let port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, port) = serialport::pair()?;
     ///
     /// let available = port.bytes_to_read()?;
     /// println!("Bytes available for reading: {}", available);
+    /// assert_eq!(available, 0);
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
     fn bytes_to_read(&self) -> io::Result<u32>;
@@ -631,15 +1094,13 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     /// # Examples
     ///
     /// ```rust
-    /// use serialport::SerialPortBuilder;
-    ///
-    /// let mut port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
     ///
     /// port.write_all(b"Hello, world!")?;
     /// let pending = port.bytes_to_write()?;
     /// println!("Bytes pending transmission: {}", pending);
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
     fn bytes_to_write(&self) -> io::Result<u32>;
@@ -662,16 +1123,22 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     ///
     /// ```rust
     /// use std::io::Error;
-    /// use serialport::SerialPortBuilder;
     ///
-    /// let mut port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
+    ///
+    /// // Take the path of a second virtual port, releasing it for reuse
+    /// let (_other_master, other) = serialport::pair()?;
+    /// let other_path = other.path().unwrap();
+    /// drop(other);
     ///
     /// // Change the port path
-    /// if let Err(e) = port.set_path("COM2".into()) {
+    /// if let Err(e) = port.set_path(other_path.clone().into()) {
     ///     eprintln!("Failed to change port: {}", e);
     /// }
+    /// assert_eq!(port.path(), Some(other_path));
+    /// assert!(port.is_open());
+    /// # }
     /// # Ok::<(), Error>(())
     /// ```
     fn set_path<'a>(&mut self, path: std::borrow::Cow<'a, str>) -> io::Result<()> {
@@ -691,6 +1158,10 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
 
     /// Sets the baud rate for the serial port.
     ///
+    /// Non-standard rates such as 31250 (MIDI) or 250000 (DMX512) are
+    /// supported on Windows and Linux, provided the hardware can produce
+    /// them.
+    ///
     /// # Arguments
     ///
     /// * `baud_rate` - The desired baud rate (e.g., 9600, 115200)
@@ -698,20 +1169,28 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     /// # Returns
     ///
     /// Returns `Ok(())` if the baud rate was successfully set,
-    /// or an error if the operation failed.
+    /// or an error if the operation failed. A rate the port cannot produce
+    /// gives an error of kind `InvalidInput`.
     ///
     /// # Examples
     ///
     /// ```rust
     /// use std::io::Error;
     ///
-    /// let mut port = serialport::new("COM1", 9600)
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::new("", 9600)
+    ///     .build_pair()?;
     ///
     /// // Set a new baud rate
     /// if let Err(e) = port.set_baud_rate(115200) {
     ///     eprintln!("Failed to set baud rate: {}", e);
     /// }
+    /// assert_eq!(port.baud_rate()?, 115200);
+    ///
+    /// // Rates without a standard constant work too
+    /// port.set_baud_rate(31250)?;
+    /// assert_eq!(port.baud_rate()?, 31250);
+    /// # }
     /// # Ok::<(), Error>(())
     /// ```
     fn set_baud_rate(&mut self, baud_rate: u32) -> io::Result<()>;
@@ -731,16 +1210,17 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     ///
     /// ```rust
     /// use std::io::Error;
-    /// use serialport::{SerialPortBuilder, config::DataBits};
+    /// use serialport::config::DataBits;
     ///
-    /// let mut port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
     ///
     /// // Set to 8 data bits (most common)
     /// if let Err(e) = port.set_data_bits(DataBits::Eight) {
     ///     eprintln!("Failed to set data bits: {}", e);
     /// }
+    /// assert_eq!(port.data_bits()?, DataBits::Eight);
+    /// # }
     /// # Ok::<(), Error>(())
     /// ```
     fn set_data_bits(&mut self, data_bits: DataBits) -> io::Result<()>;
@@ -763,16 +1243,17 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     ///
     /// ```rust
     /// use std::io::Error;
-    /// use serialport::{SerialPortBuilder, config::FlowControl};
+    /// use serialport::config::FlowControl;
     ///
-    /// let mut port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
     ///
     /// // Disable flow control (most common for simple applications)
     /// if let Err(e) = port.set_flow_control(FlowControl::None) {
     ///     eprintln!("Failed to set flow control: {}", e);
     /// }
+    /// assert_eq!(port.flow_control()?, FlowControl::None);
+    /// # }
     /// # Ok::<(), Error>(())
     /// ```
     fn set_flow_control(&mut self, flow_control: FlowControl) -> io::Result<()>;
@@ -795,16 +1276,17 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     ///
     /// ```rust
     /// use std::io::Error;
-    /// use serialport::{SerialPortBuilder, config::Parity};
+    /// use serialport::config::Parity;
     ///
-    /// let mut port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
     ///
     /// // Set no parity (most common)
     /// if let Err(e) = port.set_parity(Parity::None) {
     ///     eprintln!("Failed to set parity: {}", e);
     /// }
+    /// assert_eq!(port.parity()?, Parity::None);
+    /// # }
     /// # Ok::<(), Error>(())
     /// ```
     fn set_parity(&mut self, parity: Parity) -> io::Result<()>;
@@ -828,28 +1310,29 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     ///
     /// ```rust
     /// use std::io::Error;
-    /// use serialport::{SerialPortBuilder, config::StopBits};
+    /// use serialport::config::StopBits;
     ///
-    /// let mut port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
     ///
     /// // Set one stop bit (most common)
     /// if let Err(e) = port.set_stop_bits(StopBits::One) {
     ///     eprintln!("Failed to set stop bits: {}", e);
     /// }
+    /// assert_eq!(port.stop_bits()?, StopBits::One);
+    /// # }
     /// # Ok::<(), Error>(())
     /// ```
     fn set_stop_bits(&mut self, stop_bits: StopBits) -> io::Result<()>;
 
-    /// Sets the timeout for read operations.
+    /// Sets the timeout for read and write operations.
     ///
     /// This timeout controls how long I/O operations will wait before timing out.
     /// Read operations will wait this duration for incoming data, and write
     /// operations will wait this duration to send data. If the timeout is zero,
     /// operations return immediately without blocking - reads return an error
     /// if no data is available, and writes return an error if the operation
-    /// cannot complete instantly.
+    /// cannot complete instantly. Both the read and the write timeout are set.
     ///
     /// # Arguments
     ///
@@ -865,60 +1348,779 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
     /// ```rust
     /// use std::time::Duration;
     /// use std::io::Error;
-    /// use serialport::SerialPortBuilder;
     ///
-    /// let mut port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
     ///
     /// // Set a 5-second timeout
     /// if let Err(e) = port.set_timeout(Duration::from_secs(5)) {
     ///     eprintln!("Failed to set timeout: {}", e);
     /// }
+    /// assert_eq!(port.timeout(), Duration::from_secs(5));
+    /// # }
     /// # Ok::<(), Error>(())
     /// ```
     fn set_timeout(&mut self, timeout: std::time::Duration) -> io::Result<()>;
 
-    /// Clears the specified input or output buffer.
+    /// Sets the read timeout.
     ///
-    /// This function discards any data in the specified buffer(s), which can
-    /// be useful for clearing stale data before beginning a new communication
-    /// session or recovering from communication errors.
+    /// A read waits up to this long for the first byte to arrive. With an
+    /// inter-byte timeout, it also bounds the duration of the whole read.
     ///
     /// # Arguments
     ///
-    /// * `buffer_to_clear` - Specifies which buffer(s) to clear (input, output, or both)
+    /// * `timeout` - The desired timeout duration
     ///
     /// # Returns
     ///
-    /// Returns `Ok(())` if the buffer was successfully cleared, or an error
-    /// if the operation failed.
-    ///
-    /// # Errors
-    ///
-    /// This function may return the following errors:
-    /// - `NoDevice` if the device was disconnected
-    /// - `Io` for any other type of I/O error
+    /// Returns `Ok(())` if the timeout was successfully set,
+    /// or an error if the operation failed.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use serialport::{SerialPortBuilder, config::ClearBuffer};
+    /// use std::time::Duration;
     ///
-    /// let mut port = SerialPortBuilder::new()
-    ///     .path("COM1".into())
-    ///     .build()?;
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
+    ///
+    /// port.set_read_timeout(Duration::from_secs(1))?;
+    /// port.set_write_timeout(Duration::ZERO)?;
+    /// assert_eq!(port.read_timeout(), Duration::from_secs(1));
+    /// assert_eq!(port.write_timeout(), Duration::ZERO);
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()>;
+
+    /// Sets the write timeout.
+    ///
+    /// A write waits up to this long for room in the output buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The desired timeout duration
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the timeout was successfully set,
+    /// or an error if the operation failed.
+    fn set_write_timeout(&mut self, timeout: Duration) -> io::Result<()>;
+
+    /// Sets the inter-byte timeout.
+    ///
+    /// When set, a read that received data keeps reading until the buffer
+    /// is full, the line has been idle for `timeout`, or the read timeout has
+    /// passed since the read started. See
+    /// [`SerialPortBuilder::inter_byte_timeout`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The idle time that ends a read, or zero to disable it
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the timeout was successfully set,
+    /// or an error if the operation failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// # #[cfg(unix)] {
+    /// let (mut device, mut port) = serialport::pair()?;
+    /// port.set_read_timeout(Duration::from_secs(1))?;
+    /// port.set_inter_byte_timeout(Duration::from_millis(50))?;
+    ///
+    /// // A frame sent in two parts with a short gap is read in one go
+    /// let sender = std::thread::spawn(move || -> std::io::Result<_> {
+    ///     device.write_all(b"\x01\x03")?;
+    ///     std::thread::sleep(Duration::from_millis(10));
+    ///     device.write_all(b"\x02\x00\x01")?;
+    ///     Ok(device)
+    /// });
+    ///
+    /// let mut buffer = [0u8; 256];
+    /// let bytes_read = port.read(&mut buffer)?;
+    /// assert_eq!(&buffer[..bytes_read], b"\x01\x03\x02\x00\x01");
+    /// let _device = sender.join().unwrap()?;
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn set_inter_byte_timeout(&mut self, timeout: Duration) -> io::Result<()>;
+
+    /// Sets the RS-485 half-duplex settings.
+    ///
+    /// On Linux the settings are handed to the driver with `TIOCSRS485`. If
+    /// the driver has no RS-485 support, and on other platforms, RTS is
+    /// switched in software: each `write` sets RTS to its `rts_on_send`
+    /// level, sends all of the data, waits until it has left the port and
+    /// then sets RTS to its `rts_after_send` level. Unless `rx_during_tx` is
+    /// set, data received during the transmission is discarded.
+    ///
+    /// RS-485 mode takes over the RTS line, so it should not be combined
+    /// with hardware flow control or `write_request_to_send`.
+    ///
+    /// # Arguments
+    ///
+    /// * `rs485` - The desired RS-485 settings
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the settings were successfully applied,
+    /// or an error if the operation failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::io::{Read, Write};
+    /// use std::time::Duration;
+    /// use serialport::config::{LineEvents, Rs485Config};
+    ///
+    /// # #[cfg(unix)] {
+    /// let (mut master, mut slave) = serialport::pair()?;
+    /// slave.set_timeout(Duration::from_millis(100))?;
+    ///
+    /// master.set_rs485(Rs485Config {
+    ///     enabled: true,
+    ///     ..Rs485Config::default()
+    /// })?;
+    ///
+    /// // The bus is released while idle...
+    /// assert!(!slave.read_clear_to_send()?);
+    /// slave.wait_for_event(LineEvents::CLEAR_TO_SEND, Duration::ZERO)?;
+    ///
+    /// // ...and driven for the duration of each write
+    /// master.write_all(b"ping")?;
+    /// let events = slave.wait_for_event(LineEvents::CLEAR_TO_SEND, Duration::ZERO)?;
+    /// assert_eq!(events, LineEvents::CLEAR_TO_SEND);
+    /// assert!(!slave.read_clear_to_send()?);
+    ///
+    /// let mut buffer = [0u8; 4];
+    /// slave.read_exact(&mut buffer)?;
+    /// assert_eq!(&buffer, b"ping");
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn set_rs485(&mut self, rs485: Rs485Config) -> io::Result<()>;
+
+    /// Clears the specified input or output buffer.
+    ///
+    /// This function discards any data in the specified buffer(s), which can
+    /// be useful for clearing stale data before beginning a new communication
+    /// session or recovering from communication errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer_to_clear` - Specifies which buffer(s) to clear (input, output, or both)
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the buffer was successfully cleared, or an error
+    /// if the operation failed.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    /// - `NoDevice` if the device was disconnected
+    /// - `Io` for any other type of I/O error
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serialport::config::ClearBuffer;
+    ///
+    /// # #[cfg(unix)] {
+    /// let (mut master, port) = serialport::pair()?;
+    /// master.write_all(b"stale data")?;
+    /// # std::thread::sleep(std::time::Duration::from_millis(50));
     ///
     /// // Clear input buffer to discard any stale received data
     /// port.clear(ClearBuffer::Input)?;
+    /// assert_eq!(port.bytes_to_read()?, 0);
     ///
     /// // Clear both input and output buffers
     /// port.clear(ClearBuffer::All)?;
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
     fn clear(&self, buffer_to_clear: ClearBuffer) -> io::Result<()>;
+
+    /// Sets the state of the RTS (Request To Send) control line.
+    ///
+    /// Besides hardware flow control, RTS is commonly used as a general
+    /// purpose output, for example to reset microcontrollers or to key a
+    /// radio transmitter. Changing RTS while hardware flow control is
+    /// enabled may fail, since the driver controls the line.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - `true` to assert the line, `false` to clear it
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the line was successfully set,
+    /// or an error if the operation failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// // The ends of a virtual pair are wired like a null-modem cable
+    /// let (mut master, slave) = serialport::pair()?;
+    ///
+    /// master.write_request_to_send(false)?;
+    /// assert!(!slave.read_clear_to_send()?);
+    ///
+    /// master.write_request_to_send(true)?;
+    /// assert!(slave.read_clear_to_send()?);
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn write_request_to_send(&mut self, level: bool) -> io::Result<()>;
+
+    /// Sets the state of the DTR (Data Terminal Ready) control line.
+    ///
+    /// DTR signals that the terminal is present. Many development boards use
+    /// it, together with RTS, to reset the microcontroller or enter its
+    /// bootloader.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - `true` to assert the line, `false` to clear it
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the line was successfully set,
+    /// or an error if the operation failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
+    ///
+    /// // Pulse DTR low to reset an attached board
+    /// port.write_data_terminal_ready(false)?;
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// port.write_data_terminal_ready(true)?;
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn write_data_terminal_ready(&mut self, level: bool) -> io::Result<()>;
+
+    /// Reads the state of the CTS (Clear To Send) control line.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result<bool, std::io::Error>` containing either `true` if
+    /// the line is asserted, or an error if retrieval failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// let (_master, port) = serialport::pair()?;
+    ///
+    /// if port.read_clear_to_send()? {
+    ///     println!("Peer is ready to receive");
+    /// }
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn read_clear_to_send(&self) -> io::Result<bool>;
+
+    /// Reads the state of the DSR (Data Set Ready) control line.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result<bool, std::io::Error>` containing either `true` if
+    /// the line is asserted, or an error if retrieval failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// let (mut master, slave) = serialport::pair()?;
+    ///
+    /// master.write_data_terminal_ready(false)?;
+    /// assert!(!slave.read_data_set_ready()?);
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn read_data_set_ready(&self) -> io::Result<bool>;
+
+    /// Reads the state of the RI (Ring Indicator) control line.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result<bool, std::io::Error>` containing either `true` if
+    /// the line is asserted, or an error if retrieval failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// let (_master, port) = serialport::pair()?;
+    ///
+    /// // A null-modem cable does not carry RI
+    /// assert!(!port.read_ring_indicator()?);
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn read_ring_indicator(&self) -> io::Result<bool>;
+
+    /// Reads the state of the CD (Carrier Detect) control line.
+    ///
+    /// Modems clear CD when the connection to the remote end is lost.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result<bool, std::io::Error>` containing either `true` if
+    /// the line is asserted, or an error if retrieval failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// let (master, mut slave) = serialport::pair()?;
+    /// assert!(master.read_carrier_detect()?);
+    ///
+    /// // Closing a port drops its DTR, which the other end sees as carrier loss
+    /// slave.close()?;
+    /// assert!(!master.read_carrier_detect()?);
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn read_carrier_detect(&self) -> io::Result<bool>;
+
+    /// Starts a break condition by holding the transmit line low.
+    ///
+    /// The line stays in the break state, and no data is transmitted, until
+    /// [`clear_break`](SerialPort::clear_break) is called. Use
+    /// [`send_break`](SerialPort::send_break) for a break of a given length.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the break condition was started,
+    /// or an error if the operation failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
+    ///
+    /// // Wake up a LIN bus node
+    /// port.set_break()?;
+    /// std::thread::sleep(Duration::from_millis(1));
+    /// port.clear_break()?;
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn set_break(&mut self) -> io::Result<()>;
+
+    /// Ends a break condition started by [`set_break`](SerialPort::set_break).
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the break condition was ended,
+    /// or an error if the operation failed.
+    fn clear_break(&mut self) -> io::Result<()>;
+
+    /// Transmits a break of the given length.
+    ///
+    /// Data that is still queued for transmission is sent before the break
+    /// starts. The call blocks until the break has ended.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - How long to hold the break. `Duration::ZERO` sends a
+    ///   break of the platform's default length, between 0.25 and 0.5 seconds.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the break was sent,
+    /// or an error if the operation failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::io::Write;
+    /// use std::time::Duration;
+    /// use serialport::config::LineEvents;
+    ///
+    /// # #[cfg(unix)] {
+    /// let (mut master, mut slave) = serialport::pair()?;
+    ///
+    /// // A DMX512 packet starts with a break of at least 88µs
+    /// master.send_break(Duration::from_micros(100))?;
+    /// master.write_all(&[0x00; 513])?;
+    ///
+    /// let events = slave.wait_for_event(LineEvents::BREAK, Duration::from_millis(100))?;
+    /// assert_eq!(events, LineEvents::BREAK);
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn send_break(&mut self, duration: Duration) -> io::Result<()>;
+
+    /// Waits for a modem line change or line error.
+    ///
+    /// Events are reported once each: the call returns the events in `mask`
+    /// that occurred since the port was opened or since they were last
+    /// returned, waiting up to `timeout` for one to occur. Events outside
+    /// `mask` stay pending for a later call.
+    ///
+    /// On Linux the driver counts every transition, so short pulses are not
+    /// missed, but they may be reported up to a few milliseconds late. On
+    /// Windows the driver reports the events that occur during the call,
+    /// short pulses included; changes between calls are found by comparing
+    /// the line levels, which misses pulses that are over by then.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - The events to wait for.
+    /// * `timeout` - The maximum time to wait. `Duration::ZERO` checks for
+    ///   pending events without waiting and `Duration::MAX` waits forever.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result<LineEvents, std::io::Error>` containing either the
+    /// non-empty subset of `mask` that occurred, or an error of kind
+    /// `TimedOut` if none did in time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// use std::time::Duration;
+    /// use serialport::config::LineEvents;
+    ///
+    /// let (mut master, mut slave) = serialport::pair()?;
+    ///
+    /// master.write_request_to_send(false)?;
+    /// let events = slave.wait_for_event(LineEvents::MODEM_LINES, Duration::from_millis(100))?;
+    /// assert_eq!(events, LineEvents::CLEAR_TO_SEND);
+    ///
+    /// // The change has been reported, so nothing is pending any more
+    /// let err = slave.wait_for_event(LineEvents::all(), Duration::ZERO).unwrap_err();
+    /// assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn wait_for_event(&mut self, mask: LineEvents, timeout: Duration) -> io::Result<LineEvents>;
+
+    /// Gets the baud rate, data bits, parity, stop bits and flow control.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result<LineSettings, std::io::Error>` containing either the
+    /// current line settings, which display as for example `115200,8N1`, or
+    /// an error if retrieval failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// let (_master, port) = serialport::new("", 115200).build_pair()?;
+    /// assert_eq!(port.line_settings()?.to_string(), "115200,8N1");
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn line_settings(&self) -> io::Result<LineSettings> {
+        Ok(LineSettings {
+            baud_rate: self.baud_rate()?,
+            data_bits: self.data_bits()?,
+            parity: self.parity()?,
+            stop_bits: self.stop_bits()?,
+            flow_control: self.flow_control()?,
+        })
+    }
+
+    /// Sets the baud rate, data bits, parity, stop bits and flow control.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The desired line settings
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if all settings were applied, or the error of the
+    /// first one that failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// let (_master, mut port) = serialport::pair()?;
+    ///
+    /// port.set_line_settings("57600,8N2".parse()?)?;
+    /// assert_eq!(port.line_settings()?.to_string(), "57600,8N2");
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn set_line_settings(&mut self, settings: LineSettings) -> io::Result<()> {
+        self.set_baud_rate(settings.baud_rate)?;
+        self.set_data_bits(settings.data_bits)?;
+        self.set_parity(settings.parity)?;
+        self.set_stop_bits(settings.stop_bits)?;
+        self.set_flow_control(settings.flow_control)
+    }
+
+    /// Reads until `terminator` is received or `deadline` passes.
+    ///
+    /// Bytes are accumulated across as many reads as it takes, so slow
+    /// devices and gaps in the data do not end the read early. The
+    /// read timeout of the port is only shortened when a read would wait
+    /// past the deadline, and is restored before the method returns. Each
+    /// read takes as much as has arrived without going past the point where
+    /// the terminator could end, so nothing after the terminator is consumed
+    /// and stays in the port for the next read. A device that keeps sending
+    /// without the terminator does not hold the read beyond the deadline.
+    ///
+    /// # Arguments
+    ///
+    /// * `terminator` - The byte sequence that ends the data, such as `b"\r\n"`.
+    /// * `deadline` - The time by which the terminator must have been received.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result<Vec<u8>, std::io::Error>` containing either the data
+    /// including the terminator, or an error. If the deadline passes first,
+    /// the error has kind `TimedOut` and carries the data received so far as
+    /// a [`PartialRead`]. An empty terminator gives an error of kind
+    /// `InvalidInput`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// use std::time::{Duration, Instant};
+    /// use serialport::PartialRead;
+    ///
+    /// let (mut device, mut port) = serialport::pair()?;
+    ///
+    /// device.write_all(b"+CSQ: 21,0\r\nOK\r\n")?;
+    /// let deadline = Instant::now() + Duration::from_millis(100);
+    /// assert_eq!(port.read_until(b"OK\r\n", deadline)?, b"+CSQ: 21,0\r\nOK\r\n");
+    ///
+    /// // An incomplete response is handed back with the error
+    /// device.write_all(b"+CSQ: 2")?;
+    /// let deadline = Instant::now() + Duration::from_millis(50);
+    /// let err = port.read_until(b"\r\n", deadline).unwrap_err();
+    /// assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    ///
+    /// let partial = err.into_inner().unwrap().downcast::<PartialRead>().unwrap();
+    /// assert_eq!(partial.data(), b"+CSQ: 2");
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// The timeout of the port is only changed when a read has to wait, so
+    /// data that has already arrived is read without reconfiguring the port:
+    ///
+    /// ```rust
+    /// use std::time::{Duration, Instant};
+    /// use serialport::SerialPort;
+    /// use serialport::mock::{MockCall, MockPort};
+    ///
+    /// let mut port = MockPort::new(serialport::new("mock", 9600).timeout(Duration::from_secs(1)))
+    ///     .respond(b"OK\r\n", Duration::ZERO);
+    /// let handle = port.handle();
+    ///
+    /// std::thread::sleep(Duration::from_millis(10));
+    /// let deadline = Instant::now() + Duration::from_millis(100);
+    /// assert_eq!(port.read_until(b"\r\n", deadline)?, b"OK\r\n");
+    ///
+    /// let calls = handle.calls();
+    /// assert!(!calls.iter().any(|call| matches!(call, MockCall::SetReadTimeout(_))));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// A device that keeps sending without the terminator is cut off at the
+    /// deadline:
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let (mut device, mut port) = serialport::pair()?;
+    ///
+    /// std::thread::scope(|s| {
+    ///     let start = Instant::now();
+    ///
+    ///     s.spawn(move || {
+    ///         while start.elapsed() < Duration::from_millis(500) {
+    ///             let _ = device.write_all(b"noise ");
+    ///             std::thread::sleep(Duration::from_millis(1));
+    ///         }
+    ///     });
+    ///
+    ///     let err = port.read_until(b"\r\n", start + Duration::from_millis(50)).unwrap_err();
+    ///     assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    ///     assert!(start.elapsed() < Duration::from_millis(250));
+    /// });
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn read_until(&mut self, terminator: &[u8], deadline: Instant) -> io::Result<Vec<u8>> {
+        if terminator.is_empty() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "terminator must not be empty",
+            ));
+        }
+
+        let timeout = self.read_timeout();
+        let mut current = timeout;
+        let mut data = Vec::new();
+        let mut chunk = vec![0u8; terminator.len()];
+
+        let result = loop {
+            if data.ends_with(terminator) {
+                break Ok(());
+            }
+
+            // A device that keeps sending without the terminator is cut off
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break Err(ErrorKind::TimedOut.into());
+            }
+
+            // Nothing past the earliest point the terminator could end is read
+            let wanted = terminator.len() - terminator_overlap(&data, terminator);
+
+            // The timeout is only shortened when a read has to wait and the
+            // timeout of the port would let it wait past the deadline
+            let wanted = if remaining < current || current.is_zero() {
+                match self.bytes_to_read() {
+                    Ok(0) => {
+                        if let Err(e) = self.set_read_timeout(remaining) {
+                            break Err(e);
+                        }
+                        current = remaining;
+                        wanted
+                    }
+                    Ok(available) => wanted.min(available as usize),
+                    Err(e) => break Err(e),
+                }
+            } else {
+                wanted
+            };
+
+            match self.read(&mut chunk[..wanted]) {
+                Ok(0) => break Err(ErrorKind::UnexpectedEof.into()),
+                Ok(n) => data.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                // Only the deadline ends the read, not the timeout of a single read
+                Err(e) if e.kind() == ErrorKind::TimedOut => {}
+                Err(e) => break Err(e),
+            }
+        };
+
+        let restored = match current == timeout {
+            true => Ok(()),
+            false => self.set_read_timeout(timeout),
+        };
+
+        match result {
+            Ok(()) => restored.map(|()| data),
+            Err(e) if e.kind() == ErrorKind::TimedOut => {
+                Err(io::Error::new(ErrorKind::TimedOut, PartialRead { data }))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads a line ending in `\n`, or until `deadline` passes.
+    ///
+    /// This is [`read_until`](SerialPort::read_until) with a line feed as
+    /// terminator, for text protocols such as AT commands and SCPI.
+    ///
+    /// # Arguments
+    ///
+    /// * `deadline` - The time by which the line must have been received.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result<String, std::io::Error>` containing either the line
+    /// including its `\n` (and a `\r` before it, if any), or an error. If the
+    /// deadline passes first, the error has kind `TimedOut` and carries the
+    /// data received so far as a [`PartialRead`]. A line that is not valid
+    /// UTF-8 gives an error of kind `InvalidData`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let (mut device, mut port) = serialport::pair()?;
+    ///
+    /// // The line arrives in pieces, each within the per-read timeout of zero
+    /// let writer = std::thread::spawn(move || -> std::io::Result<_> {
+    ///     device.write_all(b"KEYSIGHT,34461A,")?;
+    ///     std::thread::sleep(Duration::from_millis(20));
+    ///     device.write_all(b"MY123,A.03\n")?;
+    ///     Ok(device)
+    /// });
+    ///
+    /// let line = port.read_line(Instant::now() + Duration::from_secs(1))?;
+    /// assert_eq!(line, "KEYSIGHT,34461A,MY123,A.03\n");
+    /// let _device = writer.join().unwrap()?;
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn read_line(&mut self, deadline: Instant) -> io::Result<String> {
+        let line = self.read_until(b"\n", deadline)?;
+
+        String::from_utf8(line).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+/// Returns how many bytes at the end of `data` are the start of
+/// `terminator`, short of the whole terminator.
+fn terminator_overlap(data: &[u8], terminator: &[u8]) -> usize {
+    (1..terminator.len())
+        .rev()
+        .find(|&len| data.ends_with(&terminator[..len]))
+        .unwrap_or(0)
+}
+
+/// The data received before a read timed out.
+///
+/// Returned inside the `TimedOut` error of [`SerialPort::read_until`] and
+/// [`SerialPort::read_line`], and retrieved with `into_inner` and `downcast`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialRead {
+    data: Vec<u8>,
+}
+
+impl PartialRead {
+    /// Returns the data received before the deadline.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes the error, returning the data received before the deadline.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl std::fmt::Display for PartialRead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "timed out after receiving {} bytes", self.data.len())
+    }
 }
 
+impl std::error::Error for PartialRead {}
+
 /// Construct a builder of `SerialPort` objects
 ///
 /// `SerialPort` objects are built using the Builder pattern through the `new` function. The
@@ -926,7 +2128,7 @@ pub trait SerialPort: Send + Communication + io::Read + io::Write + private::Pri
 /// multiple serial ports a little easier.
 ///
 /// To open a new serial port:
-/// ```rust
+/// ```rust,no_run
 /// serialport::new("COM1", 9600).build().expect("Failed to open port");
 /// ```
 pub fn new<'a>(path: impl Into<std::borrow::Cow<'a, str>>, baud_rate: u32) -> SerialPortBuilder {
@@ -937,10 +2139,40 @@ pub fn new<'a>(path: impl Into<std::borrow::Cow<'a, str>>, baud_rate: u32) -> Se
         flow_control: FlowControl::None,
         parity: Parity::None,
         stop_bits: StopBits::One,
-        timeout: Duration::ZERO,
+        read_timeout: Duration::ZERO,
+        write_timeout: Duration::ZERO,
+        inter_byte_timeout: Duration::ZERO,
+        rs485: Rs485Config::default(),
+        usb: None,
     }
 }
 
+/// Creates a pair of connected virtual serial ports with default settings.
+///
+/// This is equivalent to calling `SerialPortBuilder::new().build_pair()`.
+/// See [`SerialPortBuilder::build_pair`] for details.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::io::{Read, Write};
+/// use std::time::Duration;
+///
+/// let (mut master, mut slave) = serialport::pair()?;
+/// master.set_timeout(Duration::from_millis(100))?;
+///
+/// slave.write_all(b"Hello, Serial!")?;
+///
+/// let mut buffer = [0u8; 64];
+/// let bytes_read = master.read(&mut buffer)?;
+/// assert_eq!(&buffer[..bytes_read], b"Hello, Serial!");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(unix)]
+pub fn pair() -> io::Result<(Box<dyn SerialPort>, Box<dyn SerialPort>)> {
+    SerialPortBuilder::new().build_pair()
+}
+
 /// Returns a list of all serial ports on the system.
 ///
 /// This method scans the system for available serial port devices
@@ -0,0 +1,760 @@
+//! In-memory serial port for testing code written against `SerialPort`.
+//!
+//! A [`MockPort`] is scripted with the writes it expects and the data it should
+//! answer with. It keeps track of everything written to it and of every
+//! configuration change, which can be inspected through a [`MockHandle`] after
+//! the port itself has been handed over as a `Box<dyn SerialPort>`.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::{
+    SerialPort, SerialPortBuilder,
+    communication::Communication,
+    config::{ClearBuffer, DataBits, FlowControl, LineEvents, Parity, Rs485Config, StopBits},
+    private,
+};
+
+/// A call made on a [`MockPort`] that changed its state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockCall {
+    /// `Communication::open` succeeded
+    Open,
+    /// `Communication::close` was called on an open port
+    Close,
+    /// `SerialPort::set_baud_rate` was called
+    SetBaudRate(u32),
+    /// `SerialPort::set_data_bits` was called
+    SetDataBits(DataBits),
+    /// `SerialPort::set_flow_control` was called
+    SetFlowControl(FlowControl),
+    /// `SerialPort::set_parity` was called
+    SetParity(Parity),
+    /// `SerialPort::set_stop_bits` was called
+    SetStopBits(StopBits),
+    /// `SerialPort::set_timeout` was called
+    SetTimeout(Duration),
+    /// `SerialPort::set_read_timeout` was called
+    SetReadTimeout(Duration),
+    /// `SerialPort::set_write_timeout` was called
+    SetWriteTimeout(Duration),
+    /// `SerialPort::set_inter_byte_timeout` was called
+    SetInterByteTimeout(Duration),
+    /// `SerialPort::set_rs485` was called
+    SetRs485(Rs485Config),
+    /// `SerialPort::clear` was called
+    Clear(ClearBuffer),
+    /// `SerialPort::write_request_to_send` was called
+    WriteRequestToSend(bool),
+    /// `SerialPort::write_data_terminal_ready` was called
+    WriteDataTerminalReady(bool),
+    /// `SerialPort::set_break` was called
+    SetBreak,
+    /// `SerialPort::clear_break` was called
+    ClearBreak,
+    /// `SerialPort::send_break` was called
+    SendBreak(Duration),
+}
+
+#[derive(Debug)]
+enum Step {
+    Write(Vec<u8>),
+    Respond(Vec<u8>, Duration),
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    script: VecDeque<Step>,
+    /// Bytes written since the last matched `Step::Write`
+    pending: Vec<u8>,
+    /// Response data together with the instant it becomes readable
+    input: VecDeque<(Instant, Vec<u8>)>,
+    writes: Vec<Vec<u8>>,
+    calls: Vec<MockCall>,
+    clear_to_send: bool,
+    data_set_ready: bool,
+    ring_indicator: bool,
+    carrier_detect: bool,
+    /// Line events not yet returned by `wait_for_event`
+    events: LineEvents,
+    /// Whether the device has been unplugged
+    unplugged: bool,
+}
+
+impl MockState {
+    /// Schedules the responses at the front of the script.
+    fn advance(&mut self) {
+        while let Some(Step::Respond(..)) = self.script.front() {
+            if let Some(Step::Respond(data, delay)) = self.script.pop_front() {
+                self.input.push_back((Instant::now() + delay, data));
+            }
+        }
+    }
+
+    /// Matches newly written bytes against the expected writes in the script.
+    fn expect(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.pending.extend_from_slice(buf);
+
+        while let Some(Step::Write(expected)) = self.script.front() {
+            let len = usize::min(expected.len(), self.pending.len());
+
+            if expected[..len] != self.pending[..len] {
+                let error = io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "unexpected write: expected {:02X?}, got {:02X?}",
+                        expected, self.pending
+                    ),
+                );
+                self.pending.clear();
+                return Err(error);
+            }
+
+            if len < expected.len() {
+                break;
+            }
+
+            self.pending.drain(..len);
+            self.script.pop_front();
+            self.advance();
+        }
+
+        if self.script.is_empty() {
+            self.pending.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Fails with `BrokenPipe` while the device is unplugged.
+    fn check_plugged(&self) -> io::Result<()> {
+        if self.unplugged {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the device has been unplugged",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn bytes_ready(&self, now: Instant) -> usize {
+        self.input
+            .iter()
+            .take_while(|(ready, _)| *ready <= now)
+            .map(|(_, data)| data.len())
+            .sum()
+    }
+
+    fn read_ready(&mut self, buf: &mut [u8], now: Instant) -> usize {
+        let mut bytes_read = 0;
+
+        while bytes_read < buf.len() {
+            let Some((ready, data)) = self.input.front_mut() else {
+                break;
+            };
+
+            if *ready > now {
+                break;
+            }
+
+            let len = usize::min(data.len(), buf.len() - bytes_read);
+            buf[bytes_read..bytes_read + len].copy_from_slice(&data[..len]);
+            data.drain(..len);
+            bytes_read += len;
+
+            if data.is_empty() {
+                self.input.pop_front();
+            }
+        }
+
+        bytes_read
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<MockState>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// An in-memory serial port that replays a script of expected writes and responses.
+///
+/// The port behaves like a hardware port opened with the given builder: the
+/// getters report the configured settings, reads honor the configured timeout
+/// and return `TimedOut` when no response is due, and I/O on a closed port
+/// fails with `NotConnected`. Like other ports, it is opened on creation when
+/// the builder has a path.
+///
+/// Writes that match the next expected write in the script release the
+/// responses that follow it. Each response becomes readable after its delay,
+/// measured from the matching write. Responses at the start of the script are
+/// released by the first I/O operation. A write that does not match fails with
+/// `InvalidData`; once the script is exhausted all writes are accepted.
+///
+/// [`MockHandle::unplug`] simulates the device being disconnected, for
+/// testing how code recovers from it.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::io::{BufRead, BufReader};
+/// use std::time::Duration;
+/// use serialport::{SerialPort, config::Parity};
+/// use serialport::mock::{MockCall, MockPort};
+///
+/// let port = MockPort::new(serialport::new("mock", 9600).timeout(Duration::from_millis(100)))
+///     .expect_write(b"AT\r\n")
+///     .respond(b"OK\r\n", Duration::from_millis(5));
+/// let handle = port.handle();
+///
+/// // Hand the port to code that only knows about `SerialPort`
+/// let mut port: Box<dyn SerialPort> = Box::new(port);
+/// port.set_parity(Parity::Even)?;
+/// port.write_all(b"AT\r\n")?;
+///
+/// let mut line = String::new();
+/// BufReader::new(&mut port).read_line(&mut line)?;
+/// assert_eq!(line, "OK\r\n");
+///
+/// // Nothing else is scheduled, so the next read times out
+/// let mut buffer = [0u8; 8];
+/// assert_eq!(
+///     port.read(&mut buffer).unwrap_err().kind(),
+///     std::io::ErrorKind::TimedOut
+/// );
+///
+/// assert!(handle.is_done());
+/// assert_eq!(handle.written(), b"AT\r\n");
+/// assert_eq!(handle.calls(), [MockCall::SetParity(Parity::Even)]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct MockPort {
+    is_open: bool,
+    builder: SerialPortBuilder,
+    shared: Arc<Shared>,
+}
+
+impl MockPort {
+    pub fn new(builder: SerialPortBuilder) -> Self {
+        Self {
+            is_open: !builder.path.is_empty(),
+            builder,
+            shared: Arc::default(),
+        }
+    }
+
+    /// Appends a write that the port expects to receive next.
+    pub fn expect_write(self, data: impl AsRef<[u8]>) -> Self {
+        self.shared
+            .lock()
+            .script
+            .push_back(Step::Write(data.as_ref().to_vec()));
+        self
+    }
+
+    /// Appends data that becomes readable `delay` after the preceding expected write.
+    pub fn respond(self, data: impl AsRef<[u8]>, delay: Duration) -> Self {
+        self.shared
+            .lock()
+            .script
+            .push_back(Step::Respond(data.as_ref().to_vec(), delay));
+        self
+    }
+
+    /// Returns a handle for inspecting the port once it has been moved elsewhere.
+    pub fn handle(&self) -> MockHandle {
+        MockHandle {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    pub fn try_clone_native(&self) -> io::Result<Self> {
+        Ok(Self {
+            is_open: self.is_open,
+            builder: self.builder.clone(),
+            shared: Arc::clone(&self.shared),
+        })
+    }
+
+    fn record(&self, call: MockCall) {
+        self.shared.lock().calls.push(call);
+    }
+}
+
+/// Inspects the state of a [`MockPort`] and all of its clones.
+#[derive(Debug, Clone)]
+pub struct MockHandle {
+    shared: Arc<Shared>,
+}
+
+impl MockHandle {
+    /// Returns the data passed to each successful `write` call, in order.
+    pub fn writes(&self) -> Vec<Vec<u8>> {
+        self.shared.lock().writes.clone()
+    }
+
+    /// Returns all written bytes concatenated.
+    pub fn written(&self) -> Vec<u8> {
+        self.shared.lock().writes.concat()
+    }
+
+    /// Returns the state-changing calls made on the port, in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.shared.lock().calls.clone()
+    }
+
+    /// Returns `true` if every expected write has been received and every
+    /// response has been scheduled.
+    pub fn is_done(&self) -> bool {
+        self.shared.lock().script.is_empty()
+    }
+
+    /// Sets the CTS line reported by the port. All input lines start cleared.
+    pub fn set_clear_to_send(&self, level: bool) {
+        self.set_line(LineEvents::CLEAR_TO_SEND, level, |state| {
+            &mut state.clear_to_send
+        });
+    }
+
+    /// Sets the DSR line reported by the port.
+    pub fn set_data_set_ready(&self, level: bool) {
+        self.set_line(LineEvents::DATA_SET_READY, level, |state| {
+            &mut state.data_set_ready
+        });
+    }
+
+    /// Sets the RI line reported by the port.
+    pub fn set_ring_indicator(&self, level: bool) {
+        self.set_line(LineEvents::RING_INDICATOR, level, |state| {
+            &mut state.ring_indicator
+        });
+    }
+
+    /// Sets the CD line reported by the port.
+    pub fn set_carrier_detect(&self, level: bool) {
+        self.set_line(LineEvents::CARRIER_DETECT, level, |state| {
+            &mut state.carrier_detect
+        });
+    }
+
+    /// Simulates unplugging the device. I/O on open ports fails with
+    /// `BrokenPipe`, and opening a port fails with `NotFound`, until the
+    /// device is plugged back in.
+    pub fn unplug(&self) {
+        self.shared.lock().unplugged = true;
+        self.shared.changed.notify_all();
+    }
+
+    /// Plugs the device back in after [`unplug`](Self::unplug). Ports that
+    /// were open have to be closed and reopened to be used again.
+    pub fn plug(&self) {
+        self.shared.lock().unplugged = false;
+    }
+
+    /// Raises line events, such as a break or a framing error, that are
+    /// reported by `SerialPort::wait_for_event`. Changing an input line with
+    /// the setters above raises its event automatically.
+    pub fn signal(&self, events: LineEvents) {
+        self.shared.lock().events |= events;
+        self.shared.changed.notify_all();
+    }
+
+    fn set_line(
+        &self,
+        event: LineEvents,
+        level: bool,
+        line: impl FnOnce(&mut MockState) -> &mut bool,
+    ) {
+        let mut state = self.shared.lock();
+        let line = line(&mut state);
+
+        if *line != level {
+            *line = level;
+            state.events |= event;
+            self.shared.changed.notify_all();
+        }
+    }
+}
+
+impl Communication for MockPort {
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        if self.builder.path.is_empty() {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        }
+
+        if self.is_open {
+            return Err(std::io::ErrorKind::AlreadyExists.into());
+        }
+
+        if self.shared.lock().unplugged {
+            return Err(std::io::ErrorKind::NotFound.into());
+        }
+
+        self.is_open = true;
+        self.record(MockCall::Open);
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        if !self.is_open {
+            return Ok(());
+        }
+
+        self.is_open = false;
+        self.record(MockCall::Close);
+
+        Ok(())
+    }
+}
+
+impl SerialPort for MockPort {
+    fn try_clone(&self) -> io::Result<Box<dyn SerialPort>> {
+        self.try_clone_native()
+            .map(|port| Box::new(port) as Box<dyn SerialPort>)
+    }
+
+    fn path(&self) -> Option<String> {
+        Some(self.builder.path.clone())
+    }
+
+    fn baud_rate(&self) -> io::Result<u32> {
+        Ok(self.builder.baud_rate)
+    }
+
+    fn data_bits(&self) -> io::Result<DataBits> {
+        Ok(self.builder.data_bits)
+    }
+
+    fn flow_control(&self) -> io::Result<FlowControl> {
+        Ok(self.builder.flow_control)
+    }
+
+    fn parity(&self) -> io::Result<Parity> {
+        Ok(self.builder.parity)
+    }
+
+    fn stop_bits(&self) -> io::Result<StopBits> {
+        Ok(self.builder.stop_bits)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.builder.read_timeout
+    }
+
+    fn read_timeout(&self) -> Duration {
+        self.builder.read_timeout
+    }
+
+    fn write_timeout(&self) -> Duration {
+        self.builder.write_timeout
+    }
+
+    fn inter_byte_timeout(&self) -> Duration {
+        self.builder.inter_byte_timeout
+    }
+
+    fn rs485(&self) -> Rs485Config {
+        self.builder.rs485
+    }
+
+    fn bytes_to_read(&self) -> io::Result<u32> {
+        let mut state = self.shared.lock();
+        state.check_plugged()?;
+        state.advance();
+
+        Ok(state.bytes_ready(Instant::now()) as u32)
+    }
+
+    fn bytes_to_write(&self) -> io::Result<u32> {
+        Ok(0)
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> io::Result<()> {
+        self.builder.baud_rate = baud_rate;
+        self.record(MockCall::SetBaudRate(baud_rate));
+
+        Ok(())
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> io::Result<()> {
+        self.builder.data_bits = data_bits;
+        self.record(MockCall::SetDataBits(data_bits));
+
+        Ok(())
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> io::Result<()> {
+        self.builder.flow_control = flow_control;
+        self.record(MockCall::SetFlowControl(flow_control));
+
+        Ok(())
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> io::Result<()> {
+        self.builder.parity = parity;
+        self.record(MockCall::SetParity(parity));
+
+        Ok(())
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> io::Result<()> {
+        self.builder.stop_bits = stop_bits;
+        self.record(MockCall::SetStopBits(stop_bits));
+
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.builder.read_timeout = timeout;
+        self.builder.write_timeout = timeout;
+        self.record(MockCall::SetTimeout(timeout));
+
+        Ok(())
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.builder.read_timeout = timeout;
+        self.record(MockCall::SetReadTimeout(timeout));
+
+        Ok(())
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.builder.write_timeout = timeout;
+        self.record(MockCall::SetWriteTimeout(timeout));
+
+        Ok(())
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.builder.inter_byte_timeout = timeout;
+        self.record(MockCall::SetInterByteTimeout(timeout));
+
+        Ok(())
+    }
+
+    fn set_rs485(&mut self, rs485: Rs485Config) -> io::Result<()> {
+        self.builder.rs485 = rs485;
+        self.record(MockCall::SetRs485(rs485));
+
+        Ok(())
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> io::Result<()> {
+        let mut state = self.shared.lock();
+        state.calls.push(MockCall::Clear(buffer_to_clear));
+
+        // Only data that has already "arrived" is discarded; scheduled
+        // responses are still in flight on the line
+        if buffer_to_clear != ClearBuffer::Output {
+            let now = Instant::now();
+            state.input.retain(|(ready, _)| *ready > now);
+        }
+
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> io::Result<()> {
+        self.record(MockCall::WriteRequestToSend(level));
+
+        Ok(())
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> io::Result<()> {
+        self.record(MockCall::WriteDataTerminalReady(level));
+
+        Ok(())
+    }
+
+    fn read_clear_to_send(&self) -> io::Result<bool> {
+        Ok(self.shared.lock().clear_to_send)
+    }
+
+    fn read_data_set_ready(&self) -> io::Result<bool> {
+        Ok(self.shared.lock().data_set_ready)
+    }
+
+    fn read_ring_indicator(&self) -> io::Result<bool> {
+        Ok(self.shared.lock().ring_indicator)
+    }
+
+    fn read_carrier_detect(&self) -> io::Result<bool> {
+        Ok(self.shared.lock().carrier_detect)
+    }
+
+    fn set_break(&mut self) -> io::Result<()> {
+        self.record(MockCall::SetBreak);
+
+        Ok(())
+    }
+
+    fn clear_break(&mut self) -> io::Result<()> {
+        self.record(MockCall::ClearBreak);
+
+        Ok(())
+    }
+
+    fn send_break(&mut self, duration: Duration) -> io::Result<()> {
+        self.record(MockCall::SendBreak(duration));
+
+        Ok(())
+    }
+
+    fn wait_for_event(&mut self, mask: LineEvents, timeout: Duration) -> io::Result<LineEvents> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        let deadline = Instant::now().checked_add(timeout);
+        let mut state = self.shared.lock();
+
+        loop {
+            let events = state.events & mask;
+            if !events.is_empty() {
+                state.events = state.events & !events;
+                return Ok(events);
+            }
+
+            state = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(std::io::ErrorKind::TimedOut.into());
+                    }
+
+                    self.shared
+                        .changed
+                        .wait_timeout(state, deadline - now)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self
+                    .shared
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+}
+
+impl private::Private for MockPort {
+    fn set_raw_path<'a>(&mut self, path: std::borrow::Cow<'a, str>) -> io::Result<()> {
+        self.builder.path = path.into_owned();
+        self.builder.usb = None;
+        Ok(())
+    }
+}
+
+impl std::io::Read for MockPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        let read_timeout = self.builder.read_timeout;
+        let inter_byte_timeout = self.builder.inter_byte_timeout;
+        let deadline = Instant::now().checked_add(read_timeout);
+        let mut state = self.shared.lock();
+
+        let mut bytes_read = 0;
+        // Once data has arrived, the time at which the line counts as idle
+        let mut idle = None;
+
+        loop {
+            state.check_plugged()?;
+            state.advance();
+
+            let now = Instant::now();
+            let received = state.read_ready(&mut buf[bytes_read..], now);
+            bytes_read += received;
+
+            if bytes_read == buf.len() {
+                return Ok(bytes_read);
+            }
+
+            if bytes_read > 0 {
+                if inter_byte_timeout.is_zero() || read_timeout.is_zero() {
+                    return Ok(bytes_read);
+                }
+
+                if received > 0 {
+                    idle = now.checked_add(inter_byte_timeout);
+                }
+            }
+
+            let end = match (idle, deadline) {
+                (Some(idle), Some(deadline)) => Some(Instant::min(idle, deadline)),
+                (idle, deadline) => idle.or(deadline),
+            };
+
+            if end.is_some_and(|end| now >= end) {
+                return match bytes_read {
+                    0 => Err(std::io::ErrorKind::TimedOut.into()),
+                    _ => Ok(bytes_read),
+                };
+            }
+
+            let next = state.input.front().map(|(ready, _)| *ready);
+            let wake = match (next, end) {
+                (Some(next), Some(end)) => Some(Instant::min(next, end)),
+                (next, end) => next.or(end),
+            };
+
+            state = match wake {
+                Some(wake) => {
+                    self.shared
+                        .changed
+                        .wait_timeout(state, wake.saturating_duration_since(now))
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self
+                    .shared
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+}
+
+impl std::io::Write for MockPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        let mut state = self.shared.lock();
+        state.check_plugged()?;
+        state.advance();
+        state.expect(buf)?;
+        state.writes.push(buf.to_vec());
+        self.shared.changed.notify_all();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        self.shared.lock().check_plugged()
+    }
+}
@@ -0,0 +1,611 @@
+//! Modbus ASCII framing.
+//!
+//! An ASCII frame starts with `:`, followed by the unit address, the PDU and
+//! an LRC checksum of both as pairs of hexadecimal digits, and ends with
+//! CR LF. The specification calls for 7 data bits with even parity, which
+//! [`configure`] sets on a port.
+
+use std::io;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    SerialPort,
+    config::{ClearBuffer, DataBits, Parity, StopBits},
+};
+
+use super::{BROADCAST, Client, RegisterMap, rtu::character_time};
+
+/// The longest ASCII frame between the colon and CR LF: the address, a PDU
+/// of 253 bytes and the LRC, as two characters each.
+const MAX_FRAME_LENGTH: usize = 2 * 255;
+
+/// How long the line may be silent within a frame.
+const CHARACTER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Computes the Modbus LRC of `data`, the two's complement of the sum of its
+/// bytes.
+///
+/// # Examples
+///
+/// ```rust
+/// use serialport::modbus::ascii::lrc;
+///
+/// assert_eq!(lrc(&[0x11, 0x03, 0x00, 0x6B, 0x00, 0x03]), 0x7E);
+/// ```
+pub fn lrc(data: &[u8]) -> u8 {
+    data.iter()
+        .fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+        .wrapping_neg()
+}
+
+/// Sets the port to 7 data bits, even parity and one stop bit, as the
+/// specification requires for Modbus ASCII.
+///
+/// Devices that deviate from it, commonly with 8 data bits and no parity,
+/// work as well, as long as both ends agree.
+///
+/// # Examples
+///
+/// ```rust
+/// use serialport::SerialPort;
+/// use serialport::config::{DataBits, Parity, StopBits};
+/// use serialport::modbus::ascii;
+///
+/// let mut port = serialport::mock::MockPort::new(serialport::new("mock", 9600));
+/// ascii::configure(&mut port)?;
+///
+/// assert_eq!(port.data_bits()?, DataBits::Seven);
+/// assert_eq!(port.parity()?, Parity::Even);
+/// assert_eq!(port.stop_bits()?, StopBits::One);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn configure(port: &mut dyn SerialPort) -> io::Result<()> {
+    port.set_data_bits(DataBits::Seven)?;
+    port.set_parity(Parity::Even)?;
+    port.set_stop_bits(StopBits::One)
+}
+
+/// Encodes a unit address and PDU into a frame.
+fn encode(unit: u8, pdu: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(pdu.len() + 2);
+    data.push(unit);
+    data.extend_from_slice(pdu);
+    data.push(lrc(&data));
+
+    let mut frame = Vec::with_capacity(data.len() * 2 + 3);
+    frame.push(b':');
+    frame.extend(
+        data.iter()
+            .flat_map(|byte| format!("{:02X}", byte).into_bytes()),
+    );
+    frame.extend_from_slice(b"\r\n");
+    frame
+}
+
+/// Decodes the characters of a frame between the colon and CR LF, checks
+/// its LRC and returns the unit address and PDU.
+fn decode(frame: &[u8]) -> io::Result<(u8, Vec<u8>)> {
+    if !frame.len().is_multiple_of(2) {
+        return Err(super::invalid_data("odd number of hexadecimal digits"));
+    }
+
+    let data = frame
+        .chunks_exact(2)
+        .map(|pair| {
+            // from_str_radix would also accept a sign
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|pair| pair.bytes().all(|c| c.is_ascii_hexdigit()))
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| super::invalid_data("invalid hexadecimal digit"))
+        })
+        .collect::<io::Result<Vec<u8>>>()?;
+
+    let [unit, pdu @ .., _] = data.as_slice() else {
+        return Err(super::invalid_data("frame is too short"));
+    };
+
+    if pdu.is_empty() {
+        return Err(super::invalid_data("frame is too short"));
+    }
+
+    if lrc(&data) != 0 {
+        return Err(super::invalid_data("LRC mismatch"));
+    }
+
+    Ok((*unit, pdu.to_vec()))
+}
+
+/// Receives the characters of one frame between the colon and CR LF.
+/// Waits for the colon until `deadline`, and for each further character for
+/// up to one second. Characters before the colon are discarded, and a colon
+/// within a frame starts a new one.
+fn receive(port: &mut dyn SerialPort, deadline: Instant) -> io::Result<Vec<u8>> {
+    let mut frame: Option<Vec<u8>> = None;
+    let mut byte = [0u8];
+    let mut current = None;
+
+    loop {
+        // The timeout changes once the frame starts, not with every character
+        let timeout = match frame {
+            None => deadline.saturating_duration_since(Instant::now()),
+            Some(_) => CHARACTER_TIMEOUT,
+        };
+        if current != Some(timeout) {
+            port.set_read_timeout(timeout)?;
+            current = Some(timeout);
+        }
+
+        match port.read(&mut byte) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) if e.kind() == io::ErrorKind::TimedOut && frame.is_some() => {
+                return Err(super::invalid_data("incomplete frame"));
+            }
+            Err(e) => return Err(e),
+        }
+
+        match (&mut frame, byte[0]) {
+            (_, b':') => frame = Some(Vec::with_capacity(MAX_FRAME_LENGTH)),
+            (None, _) => {}
+            (Some(chars), b'\n') if chars.last() == Some(&b'\r') => {
+                chars.pop();
+                return Ok(frame.unwrap_or_default());
+            }
+            (Some(chars), _) if chars.len() > MAX_FRAME_LENGTH => {
+                return Err(super::invalid_data("frame is too long"));
+            }
+            (Some(chars), byte) => chars.push(byte),
+        }
+    }
+}
+
+/// A Modbus ASCII master, which sends requests to the units on a bus and
+/// receives their responses.
+///
+/// It provides the same requests as [`rtu::Master`](super::rtu::Master)
+/// through the [`Client`] trait, so an application can choose the
+/// transmission mode with [`TransmissionMode`](super::TransmissionMode).
+/// The port keeps its settings; [`configure`] switches it to 7E1.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use serialport::mock::MockPort;
+/// use serialport::modbus::{Client, ascii};
+///
+/// let port = MockPort::new(serialport::new("mock", 9600).timeout(Duration::from_millis(100)))
+///     .expect_write(b":1103006B00037E\r\n")
+///     .respond(b":110306AE4156524340CC\r\n", Duration::from_millis(5));
+///
+/// let mut master = ascii::Master::new(Box::new(port));
+/// assert_eq!(master.read_holding_registers(17, 0x006B, 3)?, [0xAE41, 0x5652, 0x4340]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Master {
+    port: Box<dyn SerialPort>,
+    timeout: Duration,
+    broadcast_delay: Duration,
+    /// When the bus is free for the next request
+    idle_at: Instant,
+}
+
+impl Master {
+    /// Creates a master on the port, with a response timeout of one second
+    /// and a delay of 100 ms after broadcasts.
+    pub fn new(port: Box<dyn SerialPort>) -> Self {
+        Self {
+            port,
+            timeout: Duration::from_secs(1),
+            broadcast_delay: Duration::from_millis(100),
+            idle_at: Instant::now(),
+        }
+    }
+
+    /// Returns a reference to the port.
+    pub fn get_ref(&self) -> &dyn SerialPort {
+        &*self.port
+    }
+
+    /// Returns a mutable reference to the port.
+    pub fn get_mut(&mut self) -> &mut dyn SerialPort {
+        &mut *self.port
+    }
+
+    /// Returns the port.
+    pub fn into_inner(self) -> Box<dyn SerialPort> {
+        self.port
+    }
+
+    /// Returns how long to wait for a response after sending a request.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets how long to wait for the start of a response, counted from the
+    /// end of the request. It applies to the requests sent from now on.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns how long the master waits after a broadcast before sending
+    /// the next request.
+    pub fn broadcast_delay(&self) -> Duration {
+        self.broadcast_delay
+    }
+
+    /// Sets how long to wait after a broadcast, which gives the units time to
+    /// process it before the next request.
+    pub fn set_broadcast_delay(&mut self, delay: Duration) {
+        self.broadcast_delay = delay;
+    }
+
+    fn exchange(&mut self, unit: u8, request: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        thread::sleep(self.idle_at.saturating_duration_since(Instant::now()));
+
+        // Whatever arrived since the last response belongs to no request
+        self.port.clear(ClearBuffer::Input)?;
+
+        let frame = encode(unit, request);
+        self.port.write_all(&frame)?;
+        self.port.flush()?;
+
+        // The driver may still be sending the request
+        let sent = Instant::now() + character_time(&*self.port)? * frame.len() as u32;
+
+        if unit == BROADCAST {
+            self.idle_at = sent + self.broadcast_delay;
+            return Ok(None);
+        }
+
+        let response = match receive(&mut *self.port, sent + self.timeout) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("no response from unit {}", unit),
+                ));
+            }
+            result => result?,
+        };
+
+        match decode(&response)? {
+            (address, pdu) if address == unit => Ok(Some(pdu)),
+            (address, _) => Err(super::invalid_data(&format!(
+                "response from unit {} instead of {}",
+                address, unit
+            ))),
+        }
+    }
+}
+
+impl Client for Master {
+    /// Sends a request and receives the response. The read timeout of the
+    /// port is restored afterwards.
+    fn transact(&mut self, unit: u8, request: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        if request.is_empty() || request.len() > 253 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "request must have between 1 and 253 bytes",
+            ));
+        }
+
+        let read_timeout = self.port.read_timeout();
+
+        let result = self.exchange(unit, request);
+        self.port.set_read_timeout(read_timeout)?;
+
+        result
+    }
+}
+
+/// A Modbus ASCII slave, which answers the requests of a master on behalf of
+/// one unit.
+///
+/// It behaves like [`rtu::Slave`](super::rtu::Slave): requests to the unit
+/// are answered from a [`RegisterMap`], requests to other units are ignored
+/// and broadcasts are processed without a response.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(unix)] {
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::time::Duration;
+/// use serialport::modbus::{Client, RegisterBank, ascii};
+///
+/// let (master_port, slave_port) = serialport::pair()?;
+///
+/// let bank = RegisterBank { holding_registers: vec![0; 8], ..Default::default() };
+/// let mut slave = ascii::Slave::new(slave_port, 1, bank);
+/// let mut master = ascii::Master::new(master_port);
+///
+/// let stop = AtomicBool::new(false);
+/// std::thread::scope(|s| {
+///     s.spawn(|| slave.run(&stop));
+///
+///     let values = master.read_write_multiple_registers(1, 0, 4, 2, &[7, 8])?;
+///     assert_eq!(values, [0, 0, 7, 8]);
+///
+///     stop.store(true, Ordering::Relaxed);
+///     Ok::<(), std::io::Error>(())
+/// })?;
+/// # }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Slave<M> {
+    port: Box<dyn SerialPort>,
+    unit: u8,
+    map: M,
+    retry_delay: Duration,
+}
+
+impl<M: RegisterMap> Slave<M> {
+    /// Creates a slave that answers the requests to `unit` from `map`.
+    pub fn new(port: Box<dyn SerialPort>, unit: u8, map: M) -> Self {
+        Self {
+            port,
+            unit,
+            map,
+            retry_delay: Duration::from_millis(100),
+        }
+    }
+
+    /// Returns a reference to the port.
+    pub fn get_ref(&self) -> &dyn SerialPort {
+        &*self.port
+    }
+
+    /// Returns a mutable reference to the port.
+    pub fn get_mut(&mut self) -> &mut dyn SerialPort {
+        &mut *self.port
+    }
+
+    /// Returns the port and the register map.
+    pub fn into_inner(self) -> (Box<dyn SerialPort>, M) {
+        (self.port, self.map)
+    }
+
+    /// Returns a reference to the register map.
+    pub fn map(&self) -> &M {
+        &self.map
+    }
+
+    /// Returns a mutable reference to the register map.
+    pub fn map_mut(&mut self) -> &mut M {
+        &mut self.map
+    }
+
+    /// Returns the unit the slave answers for.
+    pub fn unit(&self) -> u8 {
+        self.unit
+    }
+
+    /// Sets the unit the slave answers for.
+    pub fn set_unit(&mut self, unit: u8) {
+        self.unit = unit;
+    }
+
+    /// Returns how long [`run`](Self::run) waits before reopening the port.
+    pub fn retry_delay(&self) -> Duration {
+        self.retry_delay
+    }
+
+    /// Sets how long [`run`](Self::run) waits between attempts to reopen the
+    /// port.
+    pub fn set_retry_delay(&mut self, delay: Duration) {
+        self.retry_delay = delay;
+    }
+
+    /// Waits up to `timeout` for a request and answers it.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if a request to the unit or a broadcast was processed,
+    /// and `false` if no request arrived in time or the frame was ignored.
+    /// The read timeout of the port is restored afterwards.
+    pub fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        let read_timeout = self.port.read_timeout();
+
+        let result = self.serve(timeout);
+        let restored = self.port.set_read_timeout(read_timeout);
+
+        let processed = result?;
+        restored?;
+
+        Ok(processed)
+    }
+
+    /// Answers requests until `stop` is set, reopening the port whenever it
+    /// is closed or the device disappears, like
+    /// [`rtu::Slave::run`](super::rtu::Slave::run).
+    pub fn run(&mut self, stop: &AtomicBool) -> io::Result<()> {
+        let retry_delay = self.retry_delay;
+        super::run_slave(self, stop, retry_delay, Self::get_mut, Self::poll)
+    }
+
+    fn serve(&mut self, timeout: Duration) -> io::Result<bool> {
+        let frame = match receive(&mut *self.port, Instant::now() + timeout) {
+            Ok(frame) => frame,
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(false),
+            // The next colon starts over
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        let Ok((unit, request)) = decode(&frame) else {
+            return Ok(false);
+        };
+
+        if unit != self.unit && unit != BROADCAST {
+            return Ok(false);
+        }
+
+        let response = super::process(&mut self.map, &request);
+
+        if unit == BROADCAST {
+            return Ok(true);
+        }
+
+        self.port.write_all(&encode(unit, &response))?;
+        self.port.flush()?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mock::MockPort;
+
+    /// The read request and response examples of the Modbus serial line
+    /// specification.
+    const REQUEST: &[u8] = b":1103006B00037E\r\n";
+    const RESPONSE: &[u8] = b":110306AE4156524340CC\r\n";
+
+    fn port(input: &[u8]) -> MockPort {
+        MockPort::new(crate::new("mock", 9600)).respond(input, Duration::ZERO)
+    }
+
+    fn in_one_second() -> Instant {
+        Instant::now() + Duration::from_secs(1)
+    }
+
+    #[test]
+    fn lrc_reference_frames() {
+        assert_eq!(lrc(&[0x11, 0x03, 0x00, 0x6B, 0x00, 0x03]), 0x7E);
+        assert_eq!(
+            lrc(&[0x11, 0x03, 0x06, 0xAE, 0x41, 0x56, 0x52, 0x43, 0x40]),
+            0xCC
+        );
+        assert_eq!(lrc(&[]), 0x00);
+        assert_eq!(lrc(&[0xFF, 0x01]), 0x00);
+    }
+
+    #[test]
+    fn encode_reference_frames() {
+        assert_eq!(encode(0x11, &[0x03, 0x00, 0x6B, 0x00, 0x03]), REQUEST);
+        assert_eq!(
+            encode(0x11, &[0x03, 0x06, 0xAE, 0x41, 0x56, 0x52, 0x43, 0x40]),
+            RESPONSE
+        );
+    }
+
+    #[test]
+    fn decode_reference_frames() {
+        let (unit, pdu) = decode(&REQUEST[1..REQUEST.len() - 2]).unwrap();
+        assert_eq!(
+            (unit, pdu.as_slice()),
+            (0x11, &[0x03, 0x00, 0x6B, 0x00, 0x03][..])
+        );
+
+        let (unit, pdu) = decode(&RESPONSE[1..RESPONSE.len() - 2]).unwrap();
+        assert_eq!(unit, 0x11);
+        assert_eq!(pdu, [0x03, 0x06, 0xAE, 0x41, 0x56, 0x52, 0x43, 0x40]);
+    }
+
+    #[test]
+    fn decode_lowercase_digits() {
+        let (unit, pdu) = decode(b"110306ae4156524340cc").unwrap();
+        assert_eq!(unit, 0x11);
+        assert_eq!(pdu, [0x03, 0x06, 0xAE, 0x41, 0x56, 0x52, 0x43, 0x40]);
+    }
+
+    #[test]
+    fn decode_bad_lrc() {
+        let error = decode(b"1103006B00037F").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "LRC mismatch");
+
+        // A corrupt digit
+        let error = decode(b"1103006B00047E").unwrap_err();
+        assert_eq!(error.to_string(), "LRC mismatch");
+    }
+
+    #[test]
+    fn decode_odd_digit_count() {
+        for frame in [&b"1103006B00037"[..], b"103006B00037E", b"1"] {
+            let error = decode(frame).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(error.to_string(), "odd number of hexadecimal digits");
+        }
+    }
+
+    #[test]
+    fn decode_invalid_digits() {
+        for frame in [&b"1103006G00037E"[..], b"11+3006B00037E", b"1103 06B00037E"] {
+            let error = decode(frame).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(error.to_string(), "invalid hexadecimal digit");
+        }
+    }
+
+    #[test]
+    fn decode_short_frames() {
+        // Nothing, only an address, and an address with its LRC
+        for frame in [&b""[..], b"11", b"11EF"] {
+            let error = decode(frame).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(error.to_string(), "frame is too short");
+        }
+    }
+
+    #[test]
+    fn receive_reference_frame() {
+        let mut port = port(RESPONSE);
+
+        let frame = receive(&mut port, in_one_second()).unwrap();
+        assert_eq!(frame, RESPONSE[1..RESPONSE.len() - 2]);
+    }
+
+    #[test]
+    fn receive_skips_noise_before_colon() {
+        let mut port = port(&[b"\x00\r\nnoise".as_slice(), REQUEST].concat());
+
+        let frame = receive(&mut port, in_one_second()).unwrap();
+        assert_eq!(frame, REQUEST[1..REQUEST.len() - 2]);
+    }
+
+    #[test]
+    fn receive_restarts_at_colon() {
+        let mut port = port(&[b":1103\r".as_slice(), REQUEST].concat());
+
+        let frame = receive(&mut port, in_one_second()).unwrap();
+        assert_eq!(frame, REQUEST[1..REQUEST.len() - 2]);
+    }
+
+    #[test]
+    fn receive_missing_line_end() {
+        for frame in [
+            &b":1103006B00037E"[..],
+            b":1103006B00037E\n",
+            b":1103006B00037E\r",
+        ] {
+            let error = receive(&mut port(frame), in_one_second()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(error.to_string(), "incomplete frame");
+        }
+    }
+
+    #[test]
+    fn receive_too_long() {
+        let mut port = port(&[b":".as_slice(), &[b'0'; MAX_FRAME_LENGTH + 2]].concat());
+
+        let error = receive(&mut port, in_one_second()).unwrap_err();
+        assert_eq!(error.to_string(), "frame is too long");
+    }
+
+    #[test]
+    fn receive_without_frame() {
+        let mut port = port(b"noise");
+
+        let deadline = Instant::now() + Duration::from_millis(50);
+        let error = receive(&mut port, deadline).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+}
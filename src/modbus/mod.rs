@@ -0,0 +1,1272 @@
+//! Modbus over serial lines.
+//!
+//! The [`rtu`] module implements the binary Modbus RTU framing, and the
+//! [`ascii`] module the Modbus ASCII framing. The requests themselves are the
+//! same for every framing and are provided by the [`Client`] trait, which a
+//! master implements by sending a request to a unit and returning its
+//! response. A slave answers requests from a [`RegisterMap`], such as the
+//! in-memory [`RegisterBank`].
+//!
+//! Exception responses are returned as errors of kind `Other` that carry an
+//! [`Exception`], retrieved with `get_ref` and `downcast_ref`:
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use serialport::mock::MockPort;
+//! use serialport::modbus::{Client, Exception, ExceptionCode, rtu};
+//!
+//! let port = MockPort::new(serialport::new("mock", 9600).timeout(Duration::from_millis(100)))
+//!     .expect_write([0x01, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x74, 0x17])
+//!     .respond([0x01, 0x83, 0x02, 0xC0, 0xF1], Duration::from_millis(5));
+//!
+//! let mut master = rtu::Master::new(Box::new(port));
+//! let error = master.read_holding_registers(1, 0x006B, 3).unwrap_err();
+//!
+//! let exception = error.get_ref().and_then(|e| e.downcast_ref::<Exception>());
+//! assert_eq!(exception.map(|e| e.code), Some(ExceptionCode::IllegalDataAddress));
+//! ```
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::{SerialPort, reconnect::is_device_lost};
+
+pub mod ascii;
+pub mod rtu;
+
+/// Function code of Read Coils
+pub const READ_COILS: u8 = 0x01;
+/// Function code of Read Discrete Inputs
+pub const READ_DISCRETE_INPUTS: u8 = 0x02;
+/// Function code of Read Holding Registers
+pub const READ_HOLDING_REGISTERS: u8 = 0x03;
+/// Function code of Read Input Registers
+pub const READ_INPUT_REGISTERS: u8 = 0x04;
+/// Function code of Write Single Coil
+pub const WRITE_SINGLE_COIL: u8 = 0x05;
+/// Function code of Write Single Register
+pub const WRITE_SINGLE_REGISTER: u8 = 0x06;
+/// Function code of Write Multiple Coils
+pub const WRITE_MULTIPLE_COILS: u8 = 0x0F;
+/// Function code of Write Multiple Registers
+pub const WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+/// Function code of Read/Write Multiple Registers
+pub const READ_WRITE_MULTIPLE_REGISTERS: u8 = 0x17;
+/// Function code of Encapsulated Interface Transport, used for Read Device
+/// Identification
+pub const ENCAPSULATED_INTERFACE_TRANSPORT: u8 = 0x2B;
+
+/// The MEI type of Read Device Identification
+const MEI_READ_DEVICE_ID: u8 = 0x0E;
+
+/// The unit address that every unit accepts without responding.
+pub const BROADCAST: u8 = 0;
+
+/// The highest unit address.
+pub const MAX_UNIT: u8 = 247;
+
+/// How long a slave's `run` waits for a request before checking whether it
+/// should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The framing of Modbus messages on a serial line.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use serialport::mock::MockPort;
+/// use serialport::modbus::TransmissionMode;
+///
+/// let mode: TransmissionMode = "ascii".parse()?;
+///
+/// let port = MockPort::new(serialport::new("mock", 9600).timeout(Duration::from_millis(100)))
+///     .expect_write(b":01060001000AEE\r\n")
+///     .respond(b":01060001000AEE\r\n", Duration::from_millis(5));
+///
+/// let mut master = mode.master(Box::new(port));
+/// master.write_single_register(1, 0x0001, 10)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransmissionMode {
+    /// Binary frames with a CRC, separated by silent intervals
+    #[default]
+    Rtu,
+    /// Hexadecimal text frames with an LRC, ending with CR LF
+    Ascii,
+}
+
+impl TransmissionMode {
+    /// Creates a master on the port that uses this framing.
+    pub fn master(self, port: Box<dyn SerialPort>) -> Box<dyn Client + Send> {
+        match self {
+            TransmissionMode::Rtu => Box::new(rtu::Master::new(port)),
+            TransmissionMode::Ascii => Box::new(ascii::Master::new(port)),
+        }
+    }
+}
+
+impl std::fmt::Display for TransmissionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransmissionMode::Rtu => write!(f, "RTU"),
+            TransmissionMode::Ascii => write!(f, "ASCII"),
+        }
+    }
+}
+
+impl std::str::FromStr for TransmissionMode {
+    type Err = io::Error;
+
+    /// Parses `RTU` or `ASCII`, ignoring case.
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "rtu" => Ok(TransmissionMode::Rtu),
+            "ascii" => Ok(TransmissionMode::Ascii),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown Modbus transmission mode: {}", s),
+            )),
+        }
+    }
+}
+
+/// The reason a unit gave for rejecting a request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ExceptionCode {
+    /// The function code is not supported
+    IllegalFunction,
+    /// The address range is not available
+    IllegalDataAddress,
+    /// A value in the request is not allowed
+    IllegalDataValue,
+    /// The unit failed while processing the request
+    ServerDeviceFailure,
+    /// The request was accepted but takes a long time to process
+    Acknowledge,
+    /// The unit is busy with a long-running request
+    ServerDeviceBusy,
+    /// The unit detected a parity error in its memory
+    MemoryParityError,
+    /// A gateway has no path to the target unit
+    GatewayPathUnavailable,
+    /// The target unit behind a gateway did not respond
+    GatewayTargetDeviceFailedToRespond,
+    /// An exception code not defined by the specification
+    Other(u8),
+}
+
+impl From<u8> for ExceptionCode {
+    fn from(code: u8) -> Self {
+        match code {
+            0x01 => ExceptionCode::IllegalFunction,
+            0x02 => ExceptionCode::IllegalDataAddress,
+            0x03 => ExceptionCode::IllegalDataValue,
+            0x04 => ExceptionCode::ServerDeviceFailure,
+            0x05 => ExceptionCode::Acknowledge,
+            0x06 => ExceptionCode::ServerDeviceBusy,
+            0x08 => ExceptionCode::MemoryParityError,
+            0x0A => ExceptionCode::GatewayPathUnavailable,
+            0x0B => ExceptionCode::GatewayTargetDeviceFailedToRespond,
+            code => ExceptionCode::Other(code),
+        }
+    }
+}
+
+impl From<ExceptionCode> for u8 {
+    fn from(code: ExceptionCode) -> Self {
+        match code {
+            ExceptionCode::IllegalFunction => 0x01,
+            ExceptionCode::IllegalDataAddress => 0x02,
+            ExceptionCode::IllegalDataValue => 0x03,
+            ExceptionCode::ServerDeviceFailure => 0x04,
+            ExceptionCode::Acknowledge => 0x05,
+            ExceptionCode::ServerDeviceBusy => 0x06,
+            ExceptionCode::MemoryParityError => 0x08,
+            ExceptionCode::GatewayPathUnavailable => 0x0A,
+            ExceptionCode::GatewayTargetDeviceFailedToRespond => 0x0B,
+            ExceptionCode::Other(code) => code,
+        }
+    }
+}
+
+impl std::fmt::Display for ExceptionCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExceptionCode::IllegalFunction => write!(f, "illegal function"),
+            ExceptionCode::IllegalDataAddress => write!(f, "illegal data address"),
+            ExceptionCode::IllegalDataValue => write!(f, "illegal data value"),
+            ExceptionCode::ServerDeviceFailure => write!(f, "server device failure"),
+            ExceptionCode::Acknowledge => write!(f, "acknowledge"),
+            ExceptionCode::ServerDeviceBusy => write!(f, "server device busy"),
+            ExceptionCode::MemoryParityError => write!(f, "memory parity error"),
+            ExceptionCode::GatewayPathUnavailable => write!(f, "gateway path unavailable"),
+            ExceptionCode::GatewayTargetDeviceFailedToRespond => {
+                write!(f, "gateway target device failed to respond")
+            }
+            ExceptionCode::Other(code) => write!(f, "exception code {:#04x}", code),
+        }
+    }
+}
+
+/// An exception response to a request.
+///
+/// Returned inside the `Other` error of the [`Client`] requests, and
+/// retrieved with `get_ref` and `downcast_ref`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Exception {
+    /// The function code of the rejected request
+    pub function: u8,
+    /// The reason the request was rejected
+    pub code: ExceptionCode,
+}
+
+impl std::fmt::Display for Exception {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "function {:#04x} failed with exception: {}",
+            self.function, self.code
+        )
+    }
+}
+
+impl std::error::Error for Exception {}
+
+impl From<Exception> for io::Error {
+    fn from(exception: Exception) -> Self {
+        io::Error::other(exception)
+    }
+}
+
+/// Which objects Read Device Identification returns.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ReadDeviceIdCode {
+    /// The mandatory objects: vendor name, product code and revision
+    Basic = 1,
+    /// The basic objects and the optional standard objects
+    Regular = 2,
+    /// The regular objects and private objects
+    Extended = 3,
+    /// The one object with the requested id
+    Specific = 4,
+}
+
+/// The response to Read Device Identification.
+///
+/// Object 0 is the vendor name, 1 the product code, 2 the revision, 3 the
+/// vendor URL, 4 the product name, 5 the model name and 6 the user
+/// application name. Objects from 0x80 on are private to the device.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceIdentification {
+    /// The identification level and access types the device supports
+    pub conformity_level: u8,
+    /// Whether more objects are available than fit in the response
+    pub more_follows: bool,
+    /// The object to continue with in another request, if more follow
+    pub next_object_id: u8,
+    /// The objects in the response as their ids and values
+    pub objects: Vec<(u8, Vec<u8>)>,
+}
+
+impl DeviceIdentification {
+    /// Returns the value of an object as text, if it is in the response.
+    pub fn object(&self, id: u8) -> Option<String> {
+        self.objects
+            .iter()
+            .find(|(object_id, _)| *object_id == id)
+            .map(|(_, value)| String::from_utf8_lossy(value).into_owned())
+    }
+}
+
+/// The requests a Modbus master sends to its units.
+///
+/// Implementations only provide [`transact`](Self::transact), which sends a
+/// request to a unit and receives its response. The other methods encode
+/// the requests of the standard function codes and decode their responses.
+///
+/// Units are addressed from 1 to 247. Writes can be broadcast to every unit
+/// with the address 0 ([`BROADCAST`]), in which case no unit responds and
+/// the method returns as soon as the request was sent. Reads cannot be
+/// broadcast.
+///
+/// Quantities beyond the limits of the specification, and responses that do
+/// not match the request, are reported as errors of kind `InvalidInput` and
+/// `InvalidData`. Exception responses are reported as an [`Exception`].
+pub trait Client {
+    /// Sends a request PDU, the function code followed by its data, to a
+    /// unit and returns the response PDU.
+    ///
+    /// # Returns
+    ///
+    /// Returns the response as received, which may be an exception
+    /// response, or `None` for a request sent to [`BROADCAST`]. Fails with
+    /// `TimedOut` if the unit does not respond.
+    fn transact(&mut self, unit: u8, request: &[u8]) -> io::Result<Option<Vec<u8>>>;
+
+    /// Reads `quantity` coils from 1 to 2000, starting at `address`.
+    fn read_coils(&mut self, unit: u8, address: u16, quantity: u16) -> io::Result<Vec<bool>> {
+        read_bits(self, unit, READ_COILS, address, quantity)
+    }
+
+    /// Reads `quantity` discrete inputs from 1 to 2000, starting at
+    /// `address`.
+    fn read_discrete_inputs(
+        &mut self,
+        unit: u8,
+        address: u16,
+        quantity: u16,
+    ) -> io::Result<Vec<bool>> {
+        read_bits(self, unit, READ_DISCRETE_INPUTS, address, quantity)
+    }
+
+    /// Reads `quantity` holding registers from 1 to 125, starting at
+    /// `address`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use serialport::mock::MockPort;
+    /// use serialport::modbus::{Client, rtu};
+    ///
+    /// let port = MockPort::new(serialport::new("mock", 9600).timeout(Duration::from_millis(100)))
+    ///     .expect_write([0x01, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x74, 0x17])
+    ///     .respond(
+    ///         [0x01, 0x03, 0x06, 0x02, 0x2B, 0x00, 0x00, 0x00, 0x64, 0x05, 0x7A],
+    ///         Duration::from_millis(5),
+    ///     );
+    ///
+    /// let mut master = rtu::Master::new(Box::new(port));
+    /// assert_eq!(master.read_holding_registers(1, 0x006B, 3)?, [0x022B, 0x0000, 0x0064]);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn read_holding_registers(
+        &mut self,
+        unit: u8,
+        address: u16,
+        quantity: u16,
+    ) -> io::Result<Vec<u16>> {
+        read_registers(self, unit, READ_HOLDING_REGISTERS, address, quantity)
+    }
+
+    /// Reads `quantity` input registers from 1 to 125, starting at
+    /// `address`.
+    fn read_input_registers(
+        &mut self,
+        unit: u8,
+        address: u16,
+        quantity: u16,
+    ) -> io::Result<Vec<u16>> {
+        read_registers(self, unit, READ_INPUT_REGISTERS, address, quantity)
+    }
+
+    /// Sets the coil at `address` on or off.
+    fn write_single_coil(&mut self, unit: u8, address: u16, value: bool) -> io::Result<()> {
+        let value: u16 = if value { 0xFF00 } else { 0x0000 };
+        let request = pdu(WRITE_SINGLE_COIL, &[address, value], &[]);
+
+        write_echo(self, unit, &request)
+    }
+
+    /// Writes `value` to the holding register at `address`.
+    fn write_single_register(&mut self, unit: u8, address: u16, value: u16) -> io::Result<()> {
+        let request = pdu(WRITE_SINGLE_REGISTER, &[address, value], &[]);
+
+        write_echo(self, unit, &request)
+    }
+
+    /// Writes from 1 to 1968 coils, starting at `address`.
+    fn write_multiple_coils(&mut self, unit: u8, address: u16, values: &[bool]) -> io::Result<()> {
+        let quantity = check_quantity(values.len(), 1968)?;
+        let request = pdu(
+            WRITE_MULTIPLE_COILS,
+            &[address, quantity],
+            &pack_bits(values),
+        );
+
+        write_multiple(self, unit, &request)
+    }
+
+    /// Writes from 1 to 123 holding registers, starting at `address`.
+    fn write_multiple_registers(
+        &mut self,
+        unit: u8,
+        address: u16,
+        values: &[u16],
+    ) -> io::Result<()> {
+        let quantity = check_quantity(values.len(), 123)?;
+        let request = pdu(
+            WRITE_MULTIPLE_REGISTERS,
+            &[address, quantity],
+            &pack_registers(values),
+        );
+
+        write_multiple(self, unit, &request)
+    }
+
+    /// Writes from 1 to 121 holding registers starting at `write_address`,
+    /// then reads from 1 to 125 holding registers starting at
+    /// `read_address`, in one transaction.
+    fn read_write_multiple_registers(
+        &mut self,
+        unit: u8,
+        read_address: u16,
+        read_quantity: u16,
+        write_address: u16,
+        values: &[u16],
+    ) -> io::Result<Vec<u16>> {
+        check_quantity(read_quantity.into(), 125)?;
+        let write_quantity = check_quantity(values.len(), 121)?;
+        let request = pdu(
+            READ_WRITE_MULTIPLE_REGISTERS,
+            &[read_address, read_quantity, write_address, write_quantity],
+            &pack_registers(values),
+        );
+
+        let response = read(self, unit, &request)?;
+        unpack_registers(&response, read_quantity)
+    }
+
+    /// Reads the identification objects of a unit, starting at
+    /// `object_id`.
+    ///
+    /// A device may return fewer objects than requested if they do not fit
+    /// into one response. It then sets `more_follows`, and the remaining
+    /// objects are read with another request starting at `next_object_id`.
+    fn read_device_identification(
+        &mut self,
+        unit: u8,
+        code: ReadDeviceIdCode,
+        object_id: u8,
+    ) -> io::Result<DeviceIdentification> {
+        let request = [
+            ENCAPSULATED_INTERFACE_TRANSPORT,
+            MEI_READ_DEVICE_ID,
+            code as u8,
+            object_id,
+        ];
+
+        let response = read(self, unit, &request)?;
+        parse_device_identification(&response)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Checks a quantity against the limit of its function code.
+fn check_quantity(quantity: usize, max: u16) -> io::Result<u16> {
+    match u16::try_from(quantity) {
+        Ok(quantity @ 1..) if quantity <= max => Ok(quantity),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("quantity {} is not between 1 and {}", quantity, max),
+        )),
+    }
+}
+
+/// Builds a request PDU from its 16-bit fields and the values to write.
+fn pdu(function: u8, fields: &[u16], values: &[u8]) -> Vec<u8> {
+    let mut request = Vec::with_capacity(2 + fields.len() * 2 + values.len());
+    request.push(function);
+    request.extend(fields.iter().flat_map(|field| field.to_be_bytes()));
+
+    if !values.is_empty() {
+        request.push(values.len() as u8);
+        request.extend_from_slice(values);
+    }
+
+    request
+}
+
+/// Packs coil values into bytes, least significant bit first.
+pub(crate) fn pack_bits(values: &[bool]) -> Vec<u8> {
+    values
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (i, &bit)| byte | ((bit as u8) << i))
+        })
+        .collect()
+}
+
+pub(crate) fn pack_registers(values: &[u16]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+/// Unpacks coil values packed by [`pack_bits`].
+fn unpack_bits(data: &[u8], quantity: u16) -> Vec<bool> {
+    (0..quantity as usize)
+        .map(|i| data[i / 8] & (1 << (i % 8)) != 0)
+        .collect()
+}
+
+fn unpack_values(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+/// Sends a request and checks that the response has the same function code.
+fn call<C: Client + ?Sized>(
+    client: &mut C,
+    unit: u8,
+    request: &[u8],
+) -> io::Result<Option<Vec<u8>>> {
+    if unit > MAX_UNIT {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unit {} is not between 0 and {}", unit, MAX_UNIT),
+        ));
+    }
+
+    let function = request[0];
+
+    let Some(response) = client.transact(unit, request)? else {
+        return Ok(None);
+    };
+
+    match response.first() {
+        Some(&code) if code == function => Ok(Some(response)),
+        Some(&code) if code == function | 0x80 => Err(Exception {
+            function,
+            code: ExceptionCode::from(
+                *response
+                    .get(1)
+                    .ok_or_else(|| invalid_data("exception response without an exception code"))?,
+            ),
+        }
+        .into()),
+        _ => Err(invalid_data("response has a different function code")),
+    }
+}
+
+/// Sends a request that needs a response, so it cannot be broadcast.
+fn read<C: Client + ?Sized>(client: &mut C, unit: u8, request: &[u8]) -> io::Result<Vec<u8>> {
+    if unit == BROADCAST {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "reads cannot be broadcast",
+        ));
+    }
+
+    call(client, unit, request)?.ok_or_else(|| invalid_data("missing response"))
+}
+
+/// Returns the data of a response that starts with a byte count.
+fn counted_data(response: &[u8], expected: usize) -> io::Result<&[u8]> {
+    match response {
+        [_, count, data @ ..] if *count as usize == expected && data.len() == expected => Ok(data),
+        _ => Err(invalid_data("response has the wrong length")),
+    }
+}
+
+fn read_bits<C: Client + ?Sized>(
+    client: &mut C,
+    unit: u8,
+    function: u8,
+    address: u16,
+    quantity: u16,
+) -> io::Result<Vec<bool>> {
+    check_quantity(quantity.into(), 2000)?;
+
+    let response = read(client, unit, &pdu(function, &[address, quantity], &[]))?;
+    let data = counted_data(&response, (quantity as usize).div_ceil(8))?;
+
+    Ok(unpack_bits(data, quantity))
+}
+
+fn read_registers<C: Client + ?Sized>(
+    client: &mut C,
+    unit: u8,
+    function: u8,
+    address: u16,
+    quantity: u16,
+) -> io::Result<Vec<u16>> {
+    check_quantity(quantity.into(), 125)?;
+
+    let response = read(client, unit, &pdu(function, &[address, quantity], &[]))?;
+    unpack_registers(&response, quantity)
+}
+
+fn unpack_registers(response: &[u8], quantity: u16) -> io::Result<Vec<u16>> {
+    let data = counted_data(response, quantity as usize * 2)?;
+
+    Ok(unpack_values(data))
+}
+
+/// Sends a write whose response repeats the request.
+fn write_echo<C: Client + ?Sized>(client: &mut C, unit: u8, request: &[u8]) -> io::Result<()> {
+    match call(client, unit, request)? {
+        Some(response) if response != request => {
+            Err(invalid_data("response does not repeat the request"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Sends a write whose response repeats the address and quantity.
+fn write_multiple<C: Client + ?Sized>(client: &mut C, unit: u8, request: &[u8]) -> io::Result<()> {
+    match call(client, unit, request)? {
+        Some(response) if response != request[..5] => {
+            Err(invalid_data("response does not match the request"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn parse_device_identification(response: &[u8]) -> io::Result<DeviceIdentification> {
+    let [
+        _,
+        MEI_READ_DEVICE_ID,
+        _,
+        conformity_level,
+        more_follows,
+        next_object_id,
+        count,
+        objects @ ..,
+    ] = response
+    else {
+        return Err(invalid_data("malformed device identification"));
+    };
+
+    let mut identification = DeviceIdentification {
+        conformity_level: *conformity_level,
+        more_follows: *more_follows == 0xFF,
+        next_object_id: *next_object_id,
+        objects: Vec::with_capacity(*count as usize),
+    };
+
+    let mut rest = objects;
+
+    for _ in 0..*count {
+        let [id, len, tail @ ..] = rest else {
+            return Err(invalid_data("malformed device identification"));
+        };
+
+        if tail.len() < *len as usize {
+            return Err(invalid_data("malformed device identification"));
+        }
+
+        let (value, tail) = tail.split_at(*len as usize);
+        identification.objects.push((*id, value.to_vec()));
+        rest = tail;
+    }
+
+    if !rest.is_empty() {
+        return Err(invalid_data("malformed device identification"));
+    }
+
+    Ok(identification)
+}
+
+/// The data a Modbus slave exposes to its master.
+///
+/// A [`rtu::Slave`] decodes the requests it receives and calls these methods
+/// with the addresses and quantities of the request, which have already been
+/// checked against the limits of the specification. Returning an
+/// [`ExceptionCode`] sends it as an exception response. The methods that are
+/// not implemented return [`ExceptionCode::IllegalFunction`].
+///
+/// Write Single Coil and Write Multiple Coils both call
+/// [`write_coils`](Self::write_coils), and Write Single Register, Write
+/// Multiple Registers and the write of Read/Write Multiple Registers call
+/// [`write_registers`](Self::write_registers).
+pub trait RegisterMap {
+    /// Returns `quantity` coils starting at `address`.
+    fn read_coils(&mut self, address: u16, quantity: u16) -> Result<Vec<bool>, ExceptionCode> {
+        let _ = (address, quantity);
+        Err(ExceptionCode::IllegalFunction)
+    }
+
+    /// Returns `quantity` discrete inputs starting at `address`.
+    fn read_discrete_inputs(
+        &mut self,
+        address: u16,
+        quantity: u16,
+    ) -> Result<Vec<bool>, ExceptionCode> {
+        let _ = (address, quantity);
+        Err(ExceptionCode::IllegalFunction)
+    }
+
+    /// Returns `quantity` holding registers starting at `address`.
+    fn read_holding_registers(
+        &mut self,
+        address: u16,
+        quantity: u16,
+    ) -> Result<Vec<u16>, ExceptionCode> {
+        let _ = (address, quantity);
+        Err(ExceptionCode::IllegalFunction)
+    }
+
+    /// Returns `quantity` input registers starting at `address`.
+    fn read_input_registers(
+        &mut self,
+        address: u16,
+        quantity: u16,
+    ) -> Result<Vec<u16>, ExceptionCode> {
+        let _ = (address, quantity);
+        Err(ExceptionCode::IllegalFunction)
+    }
+
+    /// Sets the coils starting at `address`.
+    fn write_coils(&mut self, address: u16, values: &[bool]) -> Result<(), ExceptionCode> {
+        let _ = (address, values);
+        Err(ExceptionCode::IllegalFunction)
+    }
+
+    /// Writes the holding registers starting at `address`.
+    fn write_registers(&mut self, address: u16, values: &[u16]) -> Result<(), ExceptionCode> {
+        let _ = (address, values);
+        Err(ExceptionCode::IllegalFunction)
+    }
+}
+
+impl<M: RegisterMap + ?Sized> RegisterMap for &mut M {
+    fn read_coils(&mut self, address: u16, quantity: u16) -> Result<Vec<bool>, ExceptionCode> {
+        (**self).read_coils(address, quantity)
+    }
+
+    fn read_discrete_inputs(
+        &mut self,
+        address: u16,
+        quantity: u16,
+    ) -> Result<Vec<bool>, ExceptionCode> {
+        (**self).read_discrete_inputs(address, quantity)
+    }
+
+    fn read_holding_registers(
+        &mut self,
+        address: u16,
+        quantity: u16,
+    ) -> Result<Vec<u16>, ExceptionCode> {
+        (**self).read_holding_registers(address, quantity)
+    }
+
+    fn read_input_registers(
+        &mut self,
+        address: u16,
+        quantity: u16,
+    ) -> Result<Vec<u16>, ExceptionCode> {
+        (**self).read_input_registers(address, quantity)
+    }
+
+    fn write_coils(&mut self, address: u16, values: &[bool]) -> Result<(), ExceptionCode> {
+        (**self).write_coils(address, values)
+    }
+
+    fn write_registers(&mut self, address: u16, values: &[u16]) -> Result<(), ExceptionCode> {
+        (**self).write_registers(address, values)
+    }
+}
+
+/// A [`RegisterMap`] that keeps its data in memory.
+///
+/// Each table starts at address 0 and has the length of its vector.
+/// Requests beyond the end of a table are answered with
+/// [`ExceptionCode::IllegalDataAddress`].
+///
+/// # Examples
+///
+/// ```rust
+/// use serialport::modbus::{ExceptionCode, RegisterBank, RegisterMap};
+///
+/// let mut bank = RegisterBank {
+///     holding_registers: vec![0; 8],
+///     ..Default::default()
+/// };
+///
+/// bank.write_registers(2, &[0x1234, 0x5678])?;
+/// assert_eq!(bank.read_holding_registers(1, 3)?, [0x0000, 0x1234, 0x5678]);
+/// assert_eq!(bank.read_holding_registers(7, 2), Err(ExceptionCode::IllegalDataAddress));
+/// # Ok::<(), ExceptionCode>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegisterBank {
+    /// The coils, read and written by the master
+    pub coils: Vec<bool>,
+    /// The discrete inputs, read by the master
+    pub discrete_inputs: Vec<bool>,
+    /// The holding registers, read and written by the master
+    pub holding_registers: Vec<u16>,
+    /// The input registers, read by the master
+    pub input_registers: Vec<u16>,
+}
+
+/// Returns the range of a table that a request covers.
+fn table_range<T>(
+    table: &[T],
+    address: u16,
+    quantity: usize,
+) -> Result<std::ops::Range<usize>, ExceptionCode> {
+    let start = address as usize;
+
+    match start.checked_add(quantity) {
+        Some(end) if end <= table.len() => Ok(start..end),
+        _ => Err(ExceptionCode::IllegalDataAddress),
+    }
+}
+
+impl RegisterMap for RegisterBank {
+    fn read_coils(&mut self, address: u16, quantity: u16) -> Result<Vec<bool>, ExceptionCode> {
+        Ok(self.coils[table_range(&self.coils, address, quantity.into())?].to_vec())
+    }
+
+    fn read_discrete_inputs(
+        &mut self,
+        address: u16,
+        quantity: u16,
+    ) -> Result<Vec<bool>, ExceptionCode> {
+        let range = table_range(&self.discrete_inputs, address, quantity.into())?;
+        Ok(self.discrete_inputs[range].to_vec())
+    }
+
+    fn read_holding_registers(
+        &mut self,
+        address: u16,
+        quantity: u16,
+    ) -> Result<Vec<u16>, ExceptionCode> {
+        let range = table_range(&self.holding_registers, address, quantity.into())?;
+        Ok(self.holding_registers[range].to_vec())
+    }
+
+    fn read_input_registers(
+        &mut self,
+        address: u16,
+        quantity: u16,
+    ) -> Result<Vec<u16>, ExceptionCode> {
+        let range = table_range(&self.input_registers, address, quantity.into())?;
+        Ok(self.input_registers[range].to_vec())
+    }
+
+    fn write_coils(&mut self, address: u16, values: &[bool]) -> Result<(), ExceptionCode> {
+        let range = table_range(&self.coils, address, values.len())?;
+        self.coils[range].copy_from_slice(values);
+        Ok(())
+    }
+
+    fn write_registers(&mut self, address: u16, values: &[u16]) -> Result<(), ExceptionCode> {
+        let range = table_range(&self.holding_registers, address, values.len())?;
+        self.holding_registers[range].copy_from_slice(values);
+        Ok(())
+    }
+}
+
+/// Reads the 16-bit field at `index` of a request PDU, after the function
+/// code.
+fn field(request: &[u8], index: usize) -> u16 {
+    u16::from_be_bytes([request[1 + 2 * index], request[2 + 2 * index]])
+}
+
+/// Checks a requested quantity and that the addresses it covers exist.
+fn check_request(address: u16, quantity: u16, max: u16) -> Result<(), ExceptionCode> {
+    if quantity == 0 || quantity > max {
+        return Err(ExceptionCode::IllegalDataValue);
+    }
+
+    if address as u32 + quantity as u32 > 0x10000 {
+        return Err(ExceptionCode::IllegalDataAddress);
+    }
+
+    Ok(())
+}
+
+/// Returns the values of a write request that follow its byte count.
+fn write_values(request: &[u8], offset: usize, expected: usize) -> Result<&[u8], ExceptionCode> {
+    match &request[offset..] {
+        [count, values @ ..] if *count as usize == expected && values.len() == expected => {
+            Ok(values)
+        }
+        _ => Err(ExceptionCode::IllegalDataValue),
+    }
+}
+
+fn counted_response(function: u8, data: Vec<u8>) -> Vec<u8> {
+    let mut response = Vec::with_capacity(2 + data.len());
+    response.push(function);
+    response.push(data.len() as u8);
+    response.extend(data);
+    response
+}
+
+/// Processes a request PDU with a register map and returns the response PDU.
+pub(crate) fn process<M: RegisterMap + ?Sized>(map: &mut M, request: &[u8]) -> Vec<u8> {
+    let function = request[0];
+
+    match dispatch(map, request) {
+        Ok(response) => response,
+        Err(code) => vec![function | 0x80, code.into()],
+    }
+}
+
+fn dispatch<M: RegisterMap + ?Sized>(
+    map: &mut M,
+    request: &[u8],
+) -> Result<Vec<u8>, ExceptionCode> {
+    let function = request[0];
+
+    let valid_length = match function {
+        READ_COILS..=WRITE_SINGLE_REGISTER => request.len() == 5,
+        WRITE_MULTIPLE_COILS | WRITE_MULTIPLE_REGISTERS => request.len() >= 6,
+        READ_WRITE_MULTIPLE_REGISTERS => request.len() >= 10,
+        _ => return Err(ExceptionCode::IllegalFunction),
+    };
+
+    if !valid_length {
+        return Err(ExceptionCode::IllegalDataValue);
+    }
+
+    let address = field(request, 0);
+    let quantity = field(request, 1);
+
+    match function {
+        READ_COILS | READ_DISCRETE_INPUTS => {
+            check_request(address, quantity, 2000)?;
+
+            let values = match function {
+                READ_COILS => map.read_coils(address, quantity)?,
+                _ => map.read_discrete_inputs(address, quantity)?,
+            };
+
+            if values.len() != quantity as usize {
+                return Err(ExceptionCode::ServerDeviceFailure);
+            }
+
+            Ok(counted_response(function, pack_bits(&values)))
+        }
+        READ_HOLDING_REGISTERS | READ_INPUT_REGISTERS => {
+            check_request(address, quantity, 125)?;
+
+            let values = match function {
+                READ_HOLDING_REGISTERS => map.read_holding_registers(address, quantity)?,
+                _ => map.read_input_registers(address, quantity)?,
+            };
+
+            if values.len() != quantity as usize {
+                return Err(ExceptionCode::ServerDeviceFailure);
+            }
+
+            Ok(counted_response(function, pack_registers(&values)))
+        }
+        WRITE_SINGLE_COIL => {
+            let value = match quantity {
+                0xFF00 => true,
+                0x0000 => false,
+                _ => return Err(ExceptionCode::IllegalDataValue),
+            };
+
+            map.write_coils(address, &[value])?;
+            Ok(request.to_vec())
+        }
+        WRITE_SINGLE_REGISTER => {
+            map.write_registers(address, &[quantity])?;
+            Ok(request.to_vec())
+        }
+        WRITE_MULTIPLE_COILS => {
+            check_request(address, quantity, 1968)?;
+            let data = write_values(request, 5, (quantity as usize).div_ceil(8))?;
+
+            map.write_coils(address, &unpack_bits(data, quantity))?;
+            Ok(request[..5].to_vec())
+        }
+        WRITE_MULTIPLE_REGISTERS => {
+            check_request(address, quantity, 123)?;
+            let data = write_values(request, 5, quantity as usize * 2)?;
+
+            map.write_registers(address, &unpack_values(data))?;
+            Ok(request[..5].to_vec())
+        }
+        _ => {
+            let write_address = field(request, 2);
+            let write_quantity = field(request, 3);
+
+            check_request(address, quantity, 125)?;
+            check_request(write_address, write_quantity, 121)?;
+            let data = write_values(request, 9, write_quantity as usize * 2)?;
+
+            // The write happens before the read
+            map.write_registers(write_address, &unpack_values(data))?;
+            let values = map.read_holding_registers(address, quantity)?;
+
+            if values.len() != quantity as usize {
+                return Err(ExceptionCode::ServerDeviceFailure);
+            }
+
+            Ok(counted_response(function, pack_registers(&values)))
+        }
+    }
+}
+
+/// Polls a slave until `stop` is set, reopening its port whenever the device
+/// is lost.
+fn run_slave<S>(
+    slave: &mut S,
+    stop: &AtomicBool,
+    retry_delay: Duration,
+    port: fn(&mut S) -> &mut dyn SerialPort,
+    poll: fn(&mut S, Duration) -> io::Result<bool>,
+) -> io::Result<()> {
+    while !stop.load(Ordering::Relaxed) {
+        if !port(slave).is_open() {
+            match port(slave).open() {
+                Ok(()) => {}
+                Err(e) if is_device_lost(&e) => {
+                    thread::sleep(retry_delay);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        match poll(slave, POLL_INTERVAL) {
+            Ok(_) => {}
+            Err(e) if is_device_lost(&e) => {
+                let _ = port(slave).close();
+                thread::sleep(retry_delay);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bank() -> RegisterBank {
+        RegisterBank {
+            coils: vec![false; 64],
+            discrete_inputs: vec![false; 64],
+            holding_registers: vec![0; 128],
+            input_registers: vec![0; 16],
+        }
+    }
+
+    /// A client that answers every request with the same response.
+    struct Reply(Vec<u8>);
+
+    impl Client for Reply {
+        fn transact(&mut self, _unit: u8, _request: &[u8]) -> io::Result<Option<Vec<u8>>> {
+            Ok(Some(self.0.clone()))
+        }
+    }
+
+    fn exception(error: &io::Error) -> Option<Exception> {
+        error.get_ref()?.downcast_ref::<Exception>().copied()
+    }
+
+    // The request and response examples of the Modbus application protocol
+    // specification
+
+    #[test]
+    fn process_read_coils() {
+        let mut bank = bank();
+        bank.coils[19..38].copy_from_slice(&unpack_bits(&[0xCD, 0x6B, 0x05], 19));
+
+        let response = process(&mut bank, &[0x01, 0x00, 0x13, 0x00, 0x13]);
+        assert_eq!(response, [0x01, 0x03, 0xCD, 0x6B, 0x05]);
+    }
+
+    #[test]
+    fn process_read_holding_registers() {
+        let mut bank = bank();
+        bank.holding_registers[0x6B..0x6E].copy_from_slice(&[0x022B, 0x0000, 0x0064]);
+
+        let response = process(&mut bank, &[0x03, 0x00, 0x6B, 0x00, 0x03]);
+        assert_eq!(response, [0x03, 0x06, 0x02, 0x2B, 0x00, 0x00, 0x00, 0x64]);
+    }
+
+    #[test]
+    fn process_write_single_coil() {
+        let mut bank = RegisterBank {
+            coils: vec![false; 256],
+            ..Default::default()
+        };
+
+        let request = [0x05, 0x00, 0xAC, 0xFF, 0x00];
+        assert_eq!(process(&mut bank, &request), request);
+        assert!(bank.coils[0xAC]);
+    }
+
+    #[test]
+    fn process_write_multiple_coils() {
+        let mut bank = bank();
+
+        let request = [0x0F, 0x00, 0x13, 0x00, 0x0A, 0x02, 0xCD, 0x01];
+        assert_eq!(process(&mut bank, &request), request[..5]);
+        assert_eq!(
+            bank.coils[19..29],
+            [
+                true, false, true, true, false, false, true, true, true, false
+            ]
+        );
+        assert!(!bank.coils[29]);
+    }
+
+    #[test]
+    fn process_write_multiple_registers() {
+        let mut bank = bank();
+
+        let request = [0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0A, 0x01, 0x02];
+        assert_eq!(process(&mut bank, &request), request[..5]);
+        assert_eq!(
+            bank.holding_registers[..4],
+            [0x0000, 0x000A, 0x0102, 0x0000]
+        );
+    }
+
+    #[test]
+    fn process_read_write_multiple_registers() {
+        let mut bank = bank();
+        bank.holding_registers[3..9].copy_from_slice(&[0x00FE, 0x0ACD, 0x0001, 0x0003, 0x000D, 0]);
+
+        let request = [
+            0x17, 0x00, 0x03, 0x00, 0x06, 0x00, 0x0E, 0x00, 0x03, 0x06, 0x00, 0xFF, 0x00, 0xFF,
+            0x00, 0xFF,
+        ];
+        let response = process(&mut bank, &request);
+        assert_eq!(response[..2], [0x17, 0x0C]);
+        assert_eq!(
+            unpack_values(&response[2..]),
+            [0x00FE, 0x0ACD, 0x0001, 0x0003, 0x000D, 0x0000]
+        );
+        assert_eq!(bank.holding_registers[14..17], [0x00FF, 0x00FF, 0x00FF]);
+    }
+
+    #[test]
+    fn process_illegal_function() {
+        assert_eq!(process(&mut bank(), &[0x07]), [0x87, 0x01]);
+        assert_eq!(
+            process(&mut bank(), &[0x2B, 0x0E, 0x01, 0x00]),
+            [0xAB, 0x01]
+        );
+    }
+
+    #[test]
+    fn process_illegal_data_value() {
+        // A quantity of zero, and over the limit of the function
+        assert_eq!(
+            process(&mut bank(), &[0x03, 0x00, 0x00, 0x00, 0x00]),
+            [0x83, 0x03]
+        );
+        assert_eq!(
+            process(&mut bank(), &[0x03, 0x00, 0x00, 0x00, 0x7E]),
+            [0x83, 0x03]
+        );
+
+        // A coil value other than 0xFF00 or 0x0000
+        assert_eq!(
+            process(&mut bank(), &[0x05, 0x00, 0x01, 0x00, 0x01]),
+            [0x85, 0x03]
+        );
+
+        // A byte count that does not match the quantity
+        let request = [0x10, 0x00, 0x01, 0x00, 0x02, 0x02, 0x00, 0x0A];
+        assert_eq!(process(&mut bank(), &request), [0x90, 0x03]);
+        let request = [0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0A];
+        assert_eq!(process(&mut bank(), &request), [0x90, 0x03]);
+    }
+
+    #[test]
+    fn process_truncated_requests() {
+        assert_eq!(
+            process(&mut bank(), &[0x03, 0x00, 0x6B, 0x00]),
+            [0x83, 0x03]
+        );
+        assert_eq!(process(&mut bank(), &[0x03]), [0x83, 0x03]);
+        assert_eq!(
+            process(&mut bank(), &[0x0F, 0x00, 0x13, 0x00, 0x0A]),
+            [0x8F, 0x03]
+        );
+        assert_eq!(process(&mut bank(), &[0x17, 0x00, 0x03]), [0x97, 0x03]);
+    }
+
+    #[test]
+    fn process_illegal_data_address() {
+        assert_eq!(
+            process(&mut bank(), &[0x03, 0x00, 0x7F, 0x00, 0x02]),
+            [0x83, 0x02]
+        );
+        assert_eq!(
+            process(&mut bank(), &[0x01, 0xFF, 0xFF, 0x00, 0x02]),
+            [0x81, 0x02]
+        );
+        assert_eq!(
+            process(&mut bank(), &[0x06, 0x00, 0x80, 0x00, 0x01]),
+            [0x86, 0x02]
+        );
+    }
+
+    #[test]
+    fn call_exception_response() {
+        let error = Reply(vec![0x83, 0x02])
+            .read_holding_registers(1, 0x6B, 3)
+            .unwrap_err();
+
+        assert_eq!(
+            exception(&error),
+            Some(Exception {
+                function: 0x03,
+                code: ExceptionCode::IllegalDataAddress,
+            })
+        );
+    }
+
+    #[test]
+    fn call_exception_response_without_code() {
+        let error = Reply(vec![0x83])
+            .read_holding_registers(1, 0x6B, 3)
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(exception(&error), None);
+    }
+
+    #[test]
+    fn call_mismatched_responses() {
+        // Another function code
+        let error = Reply(vec![0x04, 0x02, 0x00, 0x00])
+            .read_holding_registers(1, 0, 1)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // Fewer registers than requested
+        let error = Reply(vec![0x03, 0x02, 0x00, 0x00])
+            .read_holding_registers(1, 0, 2)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // A write response that does not repeat the request
+        let error = Reply(vec![0x06, 0x00, 0x01, 0x00, 0x04])
+            .write_single_register(1, 1, 3)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn exception_codes() {
+        for code in 0..=u8::MAX {
+            assert_eq!(u8::from(ExceptionCode::from(code)), code);
+        }
+
+        assert_eq!(ExceptionCode::from(0x01), ExceptionCode::IllegalFunction);
+        assert_eq!(
+            ExceptionCode::from(0x0B),
+            ExceptionCode::GatewayTargetDeviceFailedToRespond
+        );
+        assert_eq!(ExceptionCode::from(0x09), ExceptionCode::Other(0x09));
+    }
+}
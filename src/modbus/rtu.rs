@@ -0,0 +1,872 @@
+//! Modbus RTU framing.
+//!
+//! An RTU frame is the unit address, the PDU and a CRC-16 of both, sent low
+//! byte first. Frames are separated by at least 3.5 character times of
+//! silence, which are derived from the current settings of the port.
+
+use std::io;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    SerialPort,
+    config::{ClearBuffer, Parity, StopBits},
+};
+
+use super::{BROADCAST, Client, ENCAPSULATED_INTERFACE_TRANSPORT, MEI_READ_DEVICE_ID, RegisterMap};
+
+/// The longest RTU frame, including the address and the CRC.
+const MAX_FRAME_LENGTH: usize = 256;
+
+/// Computes the Modbus CRC-16 of `data`.
+///
+/// # Examples
+///
+/// ```rust
+/// use serialport::modbus::rtu::crc16;
+///
+/// // The CRC is sent low byte first
+/// let crc = crc16(&[0x01, 0x03, 0x00, 0x6B, 0x00, 0x03]);
+/// assert_eq!(crc.to_le_bytes(), [0x74, 0x17]);
+/// ```
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ u16::from(byte), |crc, _| match crc & 1 {
+            0 => crc >> 1,
+            _ => (crc >> 1) ^ 0xA001,
+        })
+    })
+}
+
+/// Returns the time it takes to send one character with the current
+/// settings of the port, including its start, parity and stop bits.
+pub fn character_time(port: &dyn SerialPort) -> io::Result<Duration> {
+    let baud_rate = port.baud_rate()?;
+
+    if baud_rate == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "baud rate is zero",
+        ));
+    }
+
+    let parity = match port.parity()? {
+        Parity::None => 0,
+        _ => 1,
+    };
+
+    // Counted in half bits for 1.5 stop bits
+    let half_bits = 2 * (1 + port.data_bits()? as u64 + parity)
+        + match port.stop_bits()? {
+            StopBits::One => 2,
+            StopBits::OnePointFive => 3,
+            StopBits::Two => 4,
+        };
+
+    Ok(Duration::from_nanos(
+        half_bits * 1_000_000_000 / (2 * u64::from(baud_rate)),
+    ))
+}
+
+/// Returns the silent interval that ends a frame: 3.5 character times, or
+/// 1.75 ms above 19200 baud as recommended by the specification.
+pub fn frame_gap(port: &dyn SerialPort) -> io::Result<Duration> {
+    if port.baud_rate()? > 19200 {
+        return Ok(Duration::from_micros(1750));
+    }
+
+    Ok(character_time(port)? * 7 / 2)
+}
+
+/// Appends the CRC to a frame.
+fn seal(mut frame: Vec<u8>) -> Vec<u8> {
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame
+}
+
+/// Checks the CRC of a frame and returns its unit address and PDU.
+fn open(frame: &[u8]) -> io::Result<(u8, &[u8])> {
+    let [unit, pdu @ .., crc_low, crc_high] = frame else {
+        return Err(super::invalid_data("frame is too short"));
+    };
+
+    if pdu.is_empty() {
+        return Err(super::invalid_data("frame is too short"));
+    }
+
+    if crc16(&frame[..frame.len() - 2]) != u16::from_le_bytes([*crc_low, *crc_high]) {
+        return Err(super::invalid_data("CRC mismatch"));
+    }
+
+    Ok((*unit, pdu))
+}
+
+/// The length of a frame, as far as it can be told from its first bytes.
+#[derive(Debug, PartialEq, Eq)]
+enum FrameLength {
+    /// The frame has this many bytes
+    Known(usize),
+    /// The length is known once the frame has this many bytes
+    AtLeast(usize),
+    /// The frame ends with the next silent interval
+    Unknown,
+}
+
+/// Tells the length of a response frame from its first bytes.
+fn response_length(frame: &[u8]) -> FrameLength {
+    let Some(&function) = frame.get(1) else {
+        return FrameLength::AtLeast(2);
+    };
+
+    if function & 0x80 != 0 {
+        return FrameLength::Known(5);
+    }
+
+    match function {
+        0x01..=0x04 | 0x17 => match frame.get(2) {
+            Some(&count) => FrameLength::Known(3 + count as usize + 2),
+            None => FrameLength::AtLeast(3),
+        },
+        0x05 | 0x06 | 0x0F | 0x10 => FrameLength::Known(8),
+        ENCAPSULATED_INTERFACE_TRANSPORT => device_identification_length(frame),
+        _ => FrameLength::Unknown,
+    }
+}
+
+/// Walks the objects of a Read Device Identification response.
+fn device_identification_length(frame: &[u8]) -> FrameLength {
+    match frame.get(2) {
+        Some(&MEI_READ_DEVICE_ID) => {}
+        Some(_) => return FrameLength::Unknown,
+        None => return FrameLength::AtLeast(3),
+    }
+
+    let Some(&count) = frame.get(7) else {
+        return FrameLength::AtLeast(8);
+    };
+
+    let mut length = 8;
+
+    for _ in 0..count {
+        match frame.get(length + 1) {
+            Some(&object_length) => length += 2 + object_length as usize,
+            None => return FrameLength::AtLeast(length + 2),
+        }
+    }
+
+    FrameLength::Known(length + 2)
+}
+
+/// Tells the length of a request frame from its first bytes.
+fn request_length(frame: &[u8]) -> FrameLength {
+    let Some(&function) = frame.get(1) else {
+        return FrameLength::AtLeast(2);
+    };
+
+    match function {
+        0x01..=0x06 => FrameLength::Known(8),
+        0x0F | 0x10 => match frame.get(6) {
+            Some(&count) => FrameLength::Known(7 + count as usize + 2),
+            None => FrameLength::AtLeast(7),
+        },
+        0x17 => match frame.get(10) {
+            Some(&count) => FrameLength::Known(11 + count as usize + 2),
+            None => FrameLength::AtLeast(11),
+        },
+        ENCAPSULATED_INTERFACE_TRANSPORT => match frame.get(2) {
+            Some(&MEI_READ_DEVICE_ID) => FrameLength::Known(7),
+            Some(_) => FrameLength::Unknown,
+            None => FrameLength::AtLeast(3),
+        },
+        _ => FrameLength::Unknown,
+    }
+}
+
+/// Receives one frame. Waits for its first byte until `deadline`, and ends
+/// it once `length` says it is complete or the line is silent for `gap`.
+fn receive(
+    port: &mut dyn SerialPort,
+    deadline: Instant,
+    gap: Duration,
+    length: fn(&[u8]) -> FrameLength,
+) -> io::Result<Vec<u8>> {
+    let mut frame = Vec::with_capacity(MAX_FRAME_LENGTH);
+    let mut buffer = [0u8; MAX_FRAME_LENGTH];
+    let mut current = None;
+
+    loop {
+        let wanted = match length(&frame) {
+            FrameLength::Known(len) | FrameLength::AtLeast(len) => {
+                usize::min(len, MAX_FRAME_LENGTH).saturating_sub(frame.len())
+            }
+            FrameLength::Unknown => MAX_FRAME_LENGTH - frame.len(),
+        };
+
+        if wanted == 0 {
+            return Ok(frame);
+        }
+
+        // The timeout changes once per frame, not with every read
+        let timeout = match frame.is_empty() {
+            true => deadline.saturating_duration_since(Instant::now()),
+            false => gap,
+        };
+        if current != Some(timeout) {
+            port.set_read_timeout(timeout)?;
+            current = Some(timeout);
+        }
+
+        match port.read(&mut buffer[..wanted]) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => frame.extend_from_slice(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            // The line went silent before the frame was complete
+            Err(e) if e.kind() == io::ErrorKind::TimedOut && !frame.is_empty() => {
+                return match length(&frame) {
+                    FrameLength::Unknown => Ok(frame),
+                    _ => Err(super::invalid_data("incomplete frame")),
+                };
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// A Modbus RTU master, which sends requests to the units on a bus and
+/// receives their responses.
+///
+/// The requests are the methods of the [`Client`] trait. Before each request
+/// the master waits for the bus to be silent for 3.5 characters, and it
+/// discards any data left over from earlier frames. It then waits for the
+/// response for the configured [timeout](Self::set_timeout). The silent
+/// interval is derived from the settings of the port at the time of the
+/// request, so the port can be reconfigured, closed or reopened between
+/// requests.
+///
+/// Responses are recognized by their length and end early if the line goes
+/// silent for the [frame gap](Self::frame_gap) in the middle of a frame.
+/// USB adapters deliver received data in bursts, which can look like a
+/// silent interval at low latencies; [`set_frame_gap`](Self::set_frame_gap)
+/// allows a longer interval for them.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use serialport::mock::MockPort;
+/// use serialport::modbus::{BROADCAST, Client, rtu};
+///
+/// let port = MockPort::new(serialport::new("mock", 19200).timeout(Duration::from_millis(100)))
+///     // Unit 17 sets coil 0xAC
+///     .expect_write([0x11, 0x05, 0x00, 0xAC, 0xFF, 0x00, 0x4E, 0x8B])
+///     .respond([0x11, 0x05, 0x00, 0xAC, 0xFF, 0x00, 0x4E, 0x8B], Duration::from_millis(5))
+///     // Every unit sets register 1, without responding
+///     .expect_write([0x00, 0x06, 0x00, 0x01, 0x00, 0x03, 0x99, 0xDA]);
+/// let handle = port.handle();
+///
+/// let mut master = rtu::Master::new(Box::new(port));
+/// master.set_timeout(Duration::from_millis(500));
+///
+/// master.write_single_coil(17, 0x00AC, true)?;
+/// master.write_single_register(BROADCAST, 0x0001, 0x0003)?;
+/// assert!(handle.is_done());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Master {
+    port: Box<dyn SerialPort>,
+    timeout: Duration,
+    broadcast_delay: Duration,
+    frame_gap: Option<Duration>,
+    /// When the bus is silent long enough for the next request
+    idle_at: Instant,
+}
+
+impl Master {
+    /// Creates a master on the port, with a response timeout of one second
+    /// and a delay of 100 ms after broadcasts.
+    pub fn new(port: Box<dyn SerialPort>) -> Self {
+        Self {
+            port,
+            timeout: Duration::from_secs(1),
+            broadcast_delay: Duration::from_millis(100),
+            frame_gap: None,
+            idle_at: Instant::now(),
+        }
+    }
+
+    /// Returns a reference to the port.
+    pub fn get_ref(&self) -> &dyn SerialPort {
+        &*self.port
+    }
+
+    /// Returns a mutable reference to the port.
+    pub fn get_mut(&mut self) -> &mut dyn SerialPort {
+        &mut *self.port
+    }
+
+    /// Returns the port.
+    pub fn into_inner(self) -> Box<dyn SerialPort> {
+        self.port
+    }
+
+    /// Returns how long to wait for a response after sending a request.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets how long to wait for a response, counted from the end of the
+    /// request. It applies to the requests sent from now on.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns how long the master waits after a broadcast before sending
+    /// the next request.
+    pub fn broadcast_delay(&self) -> Duration {
+        self.broadcast_delay
+    }
+
+    /// Sets how long to wait after a broadcast, which gives the units time to
+    /// process it before the next request.
+    pub fn set_broadcast_delay(&mut self, delay: Duration) {
+        self.broadcast_delay = delay;
+    }
+
+    /// Returns the silent interval that separates frames, which is derived
+    /// from the settings of the port unless it was set explicitly.
+    pub fn frame_gap(&self) -> io::Result<Duration> {
+        match self.frame_gap {
+            Some(gap) => Ok(gap),
+            None => frame_gap(&*self.port),
+        }
+    }
+
+    /// Sets the silent interval that separates frames, or `None` to derive
+    /// it from the settings of the port.
+    pub fn set_frame_gap(&mut self, gap: Option<Duration>) {
+        self.frame_gap = gap;
+    }
+
+    fn exchange(&mut self, unit: u8, request: &[u8], gap: Duration) -> io::Result<Option<Vec<u8>>> {
+        thread::sleep(self.idle_at.saturating_duration_since(Instant::now()));
+
+        // Whatever arrived since the last response belongs to no request
+        self.port.clear(ClearBuffer::Input)?;
+
+        let mut frame = Vec::with_capacity(request.len() + 3);
+        frame.push(unit);
+        frame.extend_from_slice(request);
+        let frame = seal(frame);
+
+        self.port.write_all(&frame)?;
+        self.port.flush()?;
+
+        // The driver may still be sending the request
+        let sent = Instant::now() + character_time(&*self.port)? * frame.len() as u32;
+
+        if unit == BROADCAST {
+            self.idle_at = sent + self.broadcast_delay;
+            return Ok(None);
+        }
+
+        let result = receive(&mut *self.port, sent + self.timeout, gap, response_length);
+        self.idle_at = Instant::now() + gap;
+
+        let response = match result {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("no response from unit {}", unit),
+                ));
+            }
+            result => result?,
+        };
+
+        match open(&response)? {
+            (address, pdu) if address == unit => Ok(Some(pdu.to_vec())),
+            (address, _) => Err(super::invalid_data(&format!(
+                "response from unit {} instead of {}",
+                address, unit
+            ))),
+        }
+    }
+}
+
+impl Client for Master {
+    /// Sends a request and receives the response. The read timeout of the
+    /// port is restored afterwards.
+    fn transact(&mut self, unit: u8, request: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        if request.is_empty() || request.len() > MAX_FRAME_LENGTH - 3 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "request must have between 1 and 253 bytes",
+            ));
+        }
+
+        let gap = self.frame_gap()?;
+        let read_timeout = self.port.read_timeout();
+
+        let result = self.exchange(unit, request, gap);
+        self.port.set_read_timeout(read_timeout)?;
+
+        result
+    }
+}
+
+/// A Modbus RTU slave, which answers the requests of a master on behalf of
+/// one unit.
+///
+/// The slave decodes the requests addressed to its unit and answers them
+/// from a [`RegisterMap`]. Requests to other units are ignored, and requests
+/// to [`BROADCAST`] are processed without a response. Frames with a wrong
+/// CRC are discarded, as the specification requires, and the master times
+/// out.
+///
+/// [`poll`](Self::poll) answers one request, and [`run`](Self::run) answers
+/// requests until it is told to stop, reopening the port whenever it is
+/// closed or the device disappears.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(unix)] {
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::time::Duration;
+/// use serialport::modbus::{BROADCAST, Client, ExceptionCode, Exception, RegisterBank, rtu};
+///
+/// let (master_port, slave_port) = serialport::pair()?;
+///
+/// let bank = RegisterBank {
+///     coils: vec![false; 16],
+///     holding_registers: vec![0; 16],
+///     input_registers: vec![100, 200, 300],
+///     ..Default::default()
+/// };
+/// let mut slave = rtu::Slave::new(slave_port, 17, bank);
+///
+/// let mut master = rtu::Master::new(master_port);
+/// master.set_timeout(Duration::from_millis(200));
+///
+/// let stop = AtomicBool::new(false);
+/// std::thread::scope(|s| {
+///     s.spawn(|| slave.run(&stop));
+///
+///     master.write_multiple_registers(17, 4, &[0x1234, 0x5678])?;
+///     master.write_single_coil(BROADCAST, 3, true)?;
+///     assert_eq!(master.read_input_registers(17, 1, 2)?, [200, 300]);
+///     assert_eq!(master.read_coils(17, 2, 3)?, [false, true, false]);
+///
+///     // Addresses beyond the bank are rejected
+///     let error = master.read_input_registers(17, 2, 2).unwrap_err();
+///     let exception = error.get_ref().and_then(|e| e.downcast_ref::<Exception>());
+///     assert_eq!(exception.map(|e| e.code), Some(ExceptionCode::IllegalDataAddress));
+///
+///     // Requests to other units go unanswered
+///     let error = master.read_coils(18, 0, 1).unwrap_err();
+///     assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+///
+///     stop.store(true, Ordering::Relaxed);
+///     Ok::<(), std::io::Error>(())
+/// })?;
+///
+/// assert_eq!(slave.map().holding_registers[4..6], [0x1234, 0x5678]);
+/// # }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Slave<M> {
+    port: Box<dyn SerialPort>,
+    unit: u8,
+    map: M,
+    frame_gap: Option<Duration>,
+    retry_delay: Duration,
+}
+
+impl<M: RegisterMap> Slave<M> {
+    /// Creates a slave that answers the requests to `unit` from `map`.
+    pub fn new(port: Box<dyn SerialPort>, unit: u8, map: M) -> Self {
+        Self {
+            port,
+            unit,
+            map,
+            frame_gap: None,
+            retry_delay: Duration::from_millis(100),
+        }
+    }
+
+    /// Returns a reference to the port.
+    pub fn get_ref(&self) -> &dyn SerialPort {
+        &*self.port
+    }
+
+    /// Returns a mutable reference to the port.
+    pub fn get_mut(&mut self) -> &mut dyn SerialPort {
+        &mut *self.port
+    }
+
+    /// Returns the port and the register map.
+    pub fn into_inner(self) -> (Box<dyn SerialPort>, M) {
+        (self.port, self.map)
+    }
+
+    /// Returns a reference to the register map.
+    pub fn map(&self) -> &M {
+        &self.map
+    }
+
+    /// Returns a mutable reference to the register map.
+    pub fn map_mut(&mut self) -> &mut M {
+        &mut self.map
+    }
+
+    /// Returns the unit the slave answers for.
+    pub fn unit(&self) -> u8 {
+        self.unit
+    }
+
+    /// Sets the unit the slave answers for.
+    pub fn set_unit(&mut self, unit: u8) {
+        self.unit = unit;
+    }
+
+    /// Returns the silent interval that separates frames, which is derived
+    /// from the settings of the port unless it was set explicitly.
+    pub fn frame_gap(&self) -> io::Result<Duration> {
+        match self.frame_gap {
+            Some(gap) => Ok(gap),
+            None => frame_gap(&*self.port),
+        }
+    }
+
+    /// Sets the silent interval that separates frames, or `None` to derive
+    /// it from the settings of the port.
+    pub fn set_frame_gap(&mut self, gap: Option<Duration>) {
+        self.frame_gap = gap;
+    }
+
+    /// Returns how long [`run`](Self::run) waits before reopening the port.
+    pub fn retry_delay(&self) -> Duration {
+        self.retry_delay
+    }
+
+    /// Sets how long [`run`](Self::run) waits between attempts to reopen the
+    /// port.
+    pub fn set_retry_delay(&mut self, delay: Duration) {
+        self.retry_delay = delay;
+    }
+
+    /// Waits up to `timeout` for a request and answers it.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if a request to the unit or a broadcast was processed,
+    /// and `false` if no request arrived in time or the frame was ignored.
+    /// The read timeout of the port is restored afterwards.
+    pub fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        let gap = self.frame_gap()?;
+        let read_timeout = self.port.read_timeout();
+
+        let result = self.serve(timeout, gap);
+        let restored = self.port.set_read_timeout(read_timeout);
+
+        let processed = result?;
+        restored?;
+
+        Ok(processed)
+    }
+
+    /// Answers requests until `stop` is set.
+    ///
+    /// The port is opened if it is closed. When the device disappears, the
+    /// port is closed and reopened every [`retry_delay`](Self::retry_delay)
+    /// until the device is back. `stop` is checked at least every 100 ms.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` once `stop` is set, or any error that does not mean
+    /// the device is gone.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::time::{Duration, Instant};
+    /// use serialport::mock::{MockCall, MockPort};
+    /// use serialport::modbus::{RegisterBank, rtu};
+    ///
+    /// let port = MockPort::new(serialport::new("mock", 9600).timeout(Duration::from_millis(100)))
+    ///     .respond([0x01, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x74, 0x17], Duration::from_millis(5))
+    ///     .expect_write([0x01, 0x03, 0x06, 0x02, 0x2B, 0x00, 0x00, 0x00, 0x64, 0x05, 0x7A]);
+    /// let handle = port.handle();
+    ///
+    /// let mut bank = RegisterBank { holding_registers: vec![0; 0x70], ..Default::default() };
+    /// bank.holding_registers[0x6B..0x6E].copy_from_slice(&[0x022B, 0x0000, 0x0064]);
+    ///
+    /// let mut slave = rtu::Slave::new(Box::new(port), 1, bank);
+    /// slave.set_retry_delay(Duration::from_millis(10));
+    ///
+    /// // The adapter is gone when the slave starts
+    /// handle.unplug();
+    ///
+    /// let stop = AtomicBool::new(false);
+    /// std::thread::scope(|s| {
+    ///     let slave = s.spawn(|| slave.run(&stop));
+    ///
+    ///     std::thread::sleep(Duration::from_millis(50));
+    ///     handle.plug();
+    ///
+    ///     let deadline = Instant::now() + Duration::from_secs(5);
+    ///     while !handle.is_done() && Instant::now() < deadline {
+    ///         std::thread::sleep(Duration::from_millis(10));
+    ///     }
+    ///
+    ///     stop.store(true, Ordering::Relaxed);
+    ///     slave.join().unwrap()
+    /// })?;
+    ///
+    /// // The slave closed the port and reopened it once the adapter was back
+    /// let mut calls = handle.calls();
+    /// calls.retain(|call| matches!(call, MockCall::Open | MockCall::Close));
+    /// assert_eq!(calls, [MockCall::Close, MockCall::Open]);
+    /// assert!(handle.is_done());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn run(&mut self, stop: &AtomicBool) -> io::Result<()> {
+        let retry_delay = self.retry_delay;
+        super::run_slave(self, stop, retry_delay, Self::get_mut, Self::poll)
+    }
+
+    fn serve(&mut self, timeout: Duration, gap: Duration) -> io::Result<bool> {
+        let frame = match receive(
+            &mut *self.port,
+            Instant::now() + timeout,
+            gap,
+            request_length,
+        ) {
+            Ok(frame) => frame,
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(false),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                self.skip(gap)?;
+                return Ok(false);
+            }
+            Err(e) => return Err(e),
+        };
+
+        let Ok((unit, request)) = open(&frame) else {
+            // Whatever follows belongs to the broken frame
+            self.skip(gap)?;
+            return Ok(false);
+        };
+
+        if unit != self.unit && unit != BROADCAST {
+            return Ok(false);
+        }
+
+        let response = super::process(&mut self.map, request);
+
+        if unit == BROADCAST {
+            return Ok(true);
+        }
+
+        let mut frame = Vec::with_capacity(response.len() + 3);
+        frame.push(unit);
+        frame.extend(response);
+        let frame = seal(frame);
+
+        thread::sleep(gap);
+        self.port.write_all(&frame)?;
+        self.port.flush()?;
+
+        Ok(true)
+    }
+
+    /// Discards input until the line is silent for `gap`.
+    fn skip(&mut self, gap: Duration) -> io::Result<()> {
+        let mut buffer = [0u8; MAX_FRAME_LENGTH];
+        self.port.set_read_timeout(gap)?;
+
+        loop {
+            match self.port.read(&mut buffer) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference frames from the Modbus specifications, with their CRCs.
+    const READ_REQUEST: [u8; 8] = [0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD];
+    const SPEC_REQUEST: [u8; 8] = [0x11, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x76, 0x87];
+    const SPEC_RESPONSE: [u8; 11] = [
+        0x11, 0x03, 0x06, 0xAE, 0x41, 0x56, 0x52, 0x43, 0x40, 0x49, 0xAD,
+    ];
+    const EXCEPTION_RESPONSE: [u8; 5] = [0x01, 0x83, 0x02, 0xC0, 0xF1];
+
+    /// Feeds a frame to a length function one byte at a time, as it arrives.
+    fn length_of(frame: &[u8], length: fn(&[u8]) -> FrameLength) -> FrameLength {
+        for end in 0..frame.len() {
+            match length(&frame[..end]) {
+                FrameLength::AtLeast(at_least) => assert!(at_least > end),
+                known => return known,
+            }
+        }
+
+        length(frame)
+    }
+
+    #[test]
+    fn crc16_reference_frames() {
+        // The example of the serial line specification: 0x1241, low byte first
+        assert_eq!(crc16(&[0x02, 0x07]), 0x1241);
+
+        for frame in [
+            &READ_REQUEST[..],
+            &SPEC_REQUEST,
+            &SPEC_RESPONSE,
+            &EXCEPTION_RESPONSE,
+        ] {
+            let (data, crc) = frame.split_at(frame.len() - 2);
+            assert_eq!(crc16(data).to_le_bytes(), crc);
+        }
+    }
+
+    #[test]
+    fn crc16_of_frame_with_crc_is_zero() {
+        assert_eq!(crc16(&READ_REQUEST), 0);
+        assert_eq!(crc16(&SPEC_RESPONSE), 0);
+    }
+
+    #[test]
+    fn crc16_empty() {
+        assert_eq!(crc16(&[]), 0xFFFF);
+    }
+
+    #[test]
+    fn seal_appends_crc_low_byte_first() {
+        assert_eq!(seal(READ_REQUEST[..6].to_vec()), READ_REQUEST);
+    }
+
+    #[test]
+    fn open_reference_frames() {
+        assert_eq!(open(&SPEC_REQUEST).unwrap(), (0x11, &SPEC_REQUEST[1..6]));
+        assert_eq!(open(&SPEC_RESPONSE).unwrap(), (0x11, &SPEC_RESPONSE[1..9]));
+        assert_eq!(
+            open(&EXCEPTION_RESPONSE).unwrap(),
+            (0x01, &EXCEPTION_RESPONSE[1..3])
+        );
+    }
+
+    #[test]
+    fn open_corrupt_frames() {
+        let mut frame = SPEC_RESPONSE;
+        frame[4] ^= 0x01;
+        assert_eq!(open(&frame).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // The CRC in the wrong byte order
+        let mut frame = READ_REQUEST;
+        frame.swap(6, 7);
+        assert_eq!(open(&frame).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn open_truncated_frames() {
+        for end in 0..SPEC_RESPONSE.len() {
+            assert_eq!(
+                open(&SPEC_RESPONSE[..end]).unwrap_err().kind(),
+                io::ErrorKind::InvalidData,
+                "{} bytes",
+                end
+            );
+        }
+
+        // An address and a CRC without a PDU
+        let frame = seal(vec![0x11]);
+        assert_eq!(open(&frame).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn response_length_of_reference_frames() {
+        assert_eq!(
+            length_of(&SPEC_RESPONSE, response_length),
+            FrameLength::Known(11)
+        );
+        assert_eq!(
+            length_of(&EXCEPTION_RESPONSE, response_length),
+            FrameLength::Known(5)
+        );
+
+        // Write responses echo the address and the value or quantity
+        let frame = seal(vec![0x11, 0x06, 0x00, 0x01, 0x00, 0x03]);
+        assert_eq!(length_of(&frame, response_length), FrameLength::Known(8));
+        let frame = seal(vec![0x11, 0x0F, 0x00, 0x13, 0x00, 0x0A]);
+        assert_eq!(length_of(&frame, response_length), FrameLength::Known(8));
+    }
+
+    #[test]
+    fn response_length_of_partial_frames() {
+        assert_eq!(response_length(&[]), FrameLength::AtLeast(2));
+        assert_eq!(response_length(&[0x11]), FrameLength::AtLeast(2));
+        assert_eq!(response_length(&[0x11, 0x03]), FrameLength::AtLeast(3));
+        assert_eq!(response_length(&[0x11, 0x03, 0x06]), FrameLength::Known(11));
+    }
+
+    #[test]
+    fn response_length_of_device_identification() {
+        // Two objects of 3 and 1 bytes
+        let frame = seal(vec![
+            0x01, 0x2B, 0x0E, 0x01, 0x01, 0x00, 0x00, 0x02, 0x00, 0x03, b'A', b'C', b'M', 0x01,
+            0x01, b'X',
+        ]);
+        assert_eq!(length_of(&frame, response_length), FrameLength::Known(18));
+    }
+
+    #[test]
+    fn response_length_of_unknown_function() {
+        assert_eq!(response_length(&[0x11, 0x07]), FrameLength::Unknown);
+        assert_eq!(response_length(&[0x11, 0x2B, 0x0D]), FrameLength::Unknown);
+    }
+
+    #[test]
+    fn request_length_of_reference_frames() {
+        assert_eq!(
+            length_of(&READ_REQUEST, request_length),
+            FrameLength::Known(8)
+        );
+        assert_eq!(
+            length_of(&SPEC_REQUEST, request_length),
+            FrameLength::Known(8)
+        );
+
+        let frame = seal(vec![0x11, 0x0F, 0x00, 0x13, 0x00, 0x0A, 0x02, 0xCD, 0x01]);
+        assert_eq!(frame[9..], [0xBF, 0x0B]);
+        assert_eq!(length_of(&frame, request_length), FrameLength::Known(11));
+
+        let frame = seal(vec![
+            0x11, 0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0A, 0x01, 0x02,
+        ]);
+        assert_eq!(frame[11..], [0xC6, 0xF0]);
+        assert_eq!(length_of(&frame, request_length), FrameLength::Known(13));
+
+        let frame = seal(vec![
+            0x11, 0x17, 0x00, 0x03, 0x00, 0x06, 0x00, 0x0E, 0x00, 0x03, 0x06, 0x00, 0xFF, 0x00,
+            0xFF, 0x00, 0xFF,
+        ]);
+        assert_eq!(length_of(&frame, request_length), FrameLength::Known(19));
+
+        let frame = seal(vec![0x11, 0x2B, 0x0E, 0x01, 0x00]);
+        assert_eq!(length_of(&frame, request_length), FrameLength::Known(7));
+    }
+
+    #[test]
+    fn request_length_of_unknown_function() {
+        assert_eq!(request_length(&[0x11, 0x08]), FrameLength::Unknown);
+    }
+}
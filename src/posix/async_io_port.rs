@@ -0,0 +1,428 @@
+use std::io;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+
+use async_io::{Async, Timer};
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::{
+    SerialPort, SerialPortBuilder,
+    communication::Communication,
+    config::{ClearBuffer, DataBits, FlowControl, LineEvents, Parity, Rs485Config, StopBits},
+    private,
+};
+
+use super::tty::{EVENT_POLL_INTERVAL, TTYPort, read_nonblocking, write_nonblocking};
+
+/// The descriptor registered with the async-io reactor. It is owned by the
+/// `TTYPort`, which outlives the registration.
+struct Registration(RawFd);
+
+impl AsFd for Registration {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.0) }
+    }
+}
+
+/// A serial port for use with async-io based executors such as smol.
+///
+/// The port implements the `futures-io` [`AsyncRead`] and [`AsyncWrite`]
+/// traits on top of the non-blocking tty descriptor, so it works with any
+/// executor. It is also a [`SerialPort`]: settings are read and changed
+/// through the same methods as on a blocking port, and it is closed and
+/// reopened through [`Communication`]. Opening the port registers it with the
+/// async-io reactor, and closing it removes it again. Closing it through
+/// [`AsyncWrite::poll_close`] first waits for pending output to be sent.
+///
+/// The timeout of the port only applies to the blocking `Read` and `Write`
+/// implementations. Async reads and writes wait until they can make progress;
+/// use a timer such as `async_io::Timer` to bound them.
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::{AsyncReadExt, AsyncWriteExt};
+/// use serialport::{AsyncIoSerialPort, SerialPort, SerialPortBuilder};
+/// use serialport::communication::Communication;
+///
+/// # async_io::block_on(async {
+/// let (mut master, mut slave) = AsyncIoSerialPort::pair(SerialPortBuilder::new().baud_rate(115200))?;
+///
+/// master.write_all(b"ping").await?;
+///
+/// let mut buffer = [0u8; 4];
+/// slave.read_exact(&mut buffer).await?;
+/// assert_eq!(&buffer, b"ping");
+///
+/// // Settings work as on a blocking port
+/// slave.set_baud_rate(9600)?;
+///
+/// // Closing the port as a writer closes the device, which can then be
+/// // reopened through `Communication`
+/// AsyncWriteExt::close(&mut slave).await?;
+/// assert!(!slave.is_open());
+/// slave.open()?;
+/// assert_eq!(slave.baud_rate()?, 9600);
+/// # Ok::<(), std::io::Error>(())
+/// # })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// A setting the device rejects leaves the port open and registered, with
+/// its previous settings:
+///
+/// ```rust
+/// use futures_lite::{AsyncReadExt, AsyncWriteExt};
+/// use serialport::{AsyncIoSerialPort, SerialPort, SerialPortBuilder};
+/// use serialport::communication::Communication;
+///
+/// # async_io::block_on(async {
+/// let (mut master, mut slave) = AsyncIoSerialPort::pair(SerialPortBuilder::new().baud_rate(115200))?;
+///
+/// assert!(slave.set_baud_rate(0).is_err());
+/// assert!(slave.is_open());
+/// assert_eq!(slave.baud_rate()?, 115200);
+///
+/// // A file opened now does not take over the descriptor of the port
+/// let _file = std::fs::File::open("/dev/null")?;
+///
+/// master.write_all(b"pong").await?;
+///
+/// let mut buffer = [0u8; 4];
+/// slave.read_exact(&mut buffer).await?;
+/// assert_eq!(&buffer, b"pong");
+/// # Ok::<(), std::io::Error>(())
+/// # })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct AsyncIoSerialPort {
+    port: TTYPort,
+    io: Option<Async<Registration>>,
+    /// Paces the checks for pending output while closing
+    drain: Option<Timer>,
+}
+
+impl AsyncIoSerialPort {
+    /// Creates a port with the given settings, opening it if the builder has
+    /// a path.
+    pub fn new(builder: SerialPortBuilder) -> io::Result<Self> {
+        Self::from_port(TTYPort::new(builder)?)
+    }
+
+    /// Creates a pair of connected pseudo-terminal ports.
+    ///
+    /// See [`SerialPortBuilder::build_pair`] for details.
+    pub fn pair(builder: SerialPortBuilder) -> io::Result<(Self, Self)> {
+        let (master, slave) = TTYPort::pair(builder)?;
+
+        Ok((Self::from_port(master)?, Self::from_port(slave)?))
+    }
+
+    fn from_port(port: TTYPort) -> io::Result<Self> {
+        let mut serialport = Self {
+            port,
+            io: None,
+            drain: None,
+        };
+
+        if serialport.port.is_open() {
+            serialport.register()?;
+        }
+
+        Ok(serialport)
+    }
+
+    fn register(&mut self) -> io::Result<()> {
+        self.io = Some(Async::new(Registration(self.port.as_raw_fd()))?);
+        Ok(())
+    }
+
+    fn io(&self) -> io::Result<&Async<Registration>> {
+        self.io
+            .as_ref()
+            .filter(|_| self.port.is_open())
+            .ok_or_else(|| io::ErrorKind::NotConnected.into())
+    }
+
+    /// Changes a setting of the inner port. Should the port have lost its
+    /// descriptor, it also leaves the reactor, before the number is reused.
+    fn configure(&mut self, change: impl FnOnce(&mut TTYPort) -> io::Result<()>) -> io::Result<()> {
+        let result = change(&mut self.port);
+
+        if !self.port.is_open() {
+            self.io = None;
+            self.drain = None;
+        }
+
+        result
+    }
+}
+
+impl Communication for AsyncIoSerialPort {
+    fn is_open(&self) -> bool {
+        self.port.is_open()
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        self.port.open()?;
+
+        if let Err(e) = self.register() {
+            let _ = self.port.close();
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        // The descriptor must leave the reactor before it is closed
+        self.io = None;
+        self.drain = None;
+        self.port.close()
+    }
+}
+
+impl SerialPort for AsyncIoSerialPort {
+    /// Returns a blocking handle to the same device.
+    fn try_clone(&self) -> io::Result<Box<dyn SerialPort>> {
+        self.port.try_clone()
+    }
+
+    fn path(&self) -> Option<String> {
+        self.port.path()
+    }
+
+    fn baud_rate(&self) -> io::Result<u32> {
+        self.port.baud_rate()
+    }
+
+    fn data_bits(&self) -> io::Result<DataBits> {
+        self.port.data_bits()
+    }
+
+    fn flow_control(&self) -> io::Result<FlowControl> {
+        self.port.flow_control()
+    }
+
+    fn parity(&self) -> io::Result<Parity> {
+        self.port.parity()
+    }
+
+    fn stop_bits(&self) -> io::Result<StopBits> {
+        self.port.stop_bits()
+    }
+
+    fn timeout(&self) -> Duration {
+        self.port.timeout()
+    }
+
+    fn read_timeout(&self) -> Duration {
+        self.port.read_timeout()
+    }
+
+    fn write_timeout(&self) -> Duration {
+        self.port.write_timeout()
+    }
+
+    fn inter_byte_timeout(&self) -> Duration {
+        self.port.inter_byte_timeout()
+    }
+
+    fn rs485(&self) -> Rs485Config {
+        self.port.rs485()
+    }
+
+    fn bytes_to_read(&self) -> io::Result<u32> {
+        self.port.bytes_to_read()
+    }
+
+    fn bytes_to_write(&self) -> io::Result<u32> {
+        self.port.bytes_to_write()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> io::Result<()> {
+        self.configure(|port| port.set_baud_rate(baud_rate))
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> io::Result<()> {
+        self.configure(|port| port.set_data_bits(data_bits))
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> io::Result<()> {
+        self.configure(|port| port.set_flow_control(flow_control))
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> io::Result<()> {
+        self.configure(|port| port.set_parity(parity))
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> io::Result<()> {
+        self.configure(|port| port.set_stop_bits(stop_bits))
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.port.set_timeout(timeout)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.port.set_read_timeout(timeout)
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.port.set_write_timeout(timeout)
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.port.set_inter_byte_timeout(timeout)
+    }
+
+    fn set_rs485(&mut self, rs485: Rs485Config) -> io::Result<()> {
+        self.configure(|port| port.set_rs485(rs485))
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> io::Result<()> {
+        self.port.clear(buffer_to_clear)
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> io::Result<()> {
+        self.port.write_request_to_send(level)
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> io::Result<()> {
+        self.port.write_data_terminal_ready(level)
+    }
+
+    fn read_clear_to_send(&self) -> io::Result<bool> {
+        self.port.read_clear_to_send()
+    }
+
+    fn read_data_set_ready(&self) -> io::Result<bool> {
+        self.port.read_data_set_ready()
+    }
+
+    fn read_ring_indicator(&self) -> io::Result<bool> {
+        self.port.read_ring_indicator()
+    }
+
+    fn read_carrier_detect(&self) -> io::Result<bool> {
+        self.port.read_carrier_detect()
+    }
+
+    fn set_break(&mut self) -> io::Result<()> {
+        self.port.set_break()
+    }
+
+    fn clear_break(&mut self) -> io::Result<()> {
+        self.port.clear_break()
+    }
+
+    fn send_break(&mut self, duration: Duration) -> io::Result<()> {
+        self.port.send_break(duration)
+    }
+
+    fn wait_for_event(&mut self, mask: LineEvents, timeout: Duration) -> io::Result<LineEvents> {
+        self.port.wait_for_event(mask, timeout)
+    }
+}
+
+impl private::Private for AsyncIoSerialPort {
+    fn set_raw_path<'a>(&mut self, path: std::borrow::Cow<'a, str>) -> io::Result<()> {
+        self.port.set_raw_path(path)
+    }
+}
+
+impl io::Read for AsyncIoSerialPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.port.read(buf)
+    }
+}
+
+impl io::Write for AsyncIoSerialPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.port.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
+
+impl AsRawFd for AsyncIoSerialPort {
+    fn as_raw_fd(&self) -> RawFd {
+        self.port.as_raw_fd()
+    }
+}
+
+impl AsFd for AsyncIoSerialPort {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.port.as_fd()
+    }
+}
+
+impl AsyncRead for AsyncIoSerialPort {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let io = self.io()?;
+
+        loop {
+            match read_nonblocking(io.as_fd().as_raw_fd(), buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                result => return Poll::Ready(result),
+            }
+
+            ready!(io.poll_readable(cx))?;
+        }
+    }
+}
+
+impl AsyncWrite for AsyncIoSerialPort {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let io = self.io()?;
+
+        loop {
+            match write_nonblocking(io.as_fd().as_raw_fd(), buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                result => return Poll::Ready(result),
+            }
+
+            ready!(io.poll_writable(cx))?;
+        }
+    }
+
+    /// Data is handed to the driver as soon as it is written, so there is
+    /// nothing to flush. Use `bytes_to_write` to find out whether it has
+    /// been transmitted.
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.io()?;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Waits for pending output to be transmitted, then closes the port.
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        if !this.port.is_open() {
+            return Poll::Ready(Ok(()));
+        }
+
+        // The driver does not signal when its output buffer drains
+        while this.port.bytes_to_write()? > 0 {
+            let timer = this
+                .drain
+                .get_or_insert_with(|| Timer::after(EVENT_POLL_INTERVAL));
+            ready!(Pin::new(timer).poll(cx));
+            this.drain = None;
+        }
+
+        Poll::Ready(this.close())
+    }
+}
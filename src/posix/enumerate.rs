@@ -0,0 +1,248 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{SerialPortInfo, SerialPortType, UsbPortInfo};
+
+const SYSFS_ROOT: &str = "/sys";
+
+/// Reads a sysfs attribute, returning `None` when it is missing or empty.
+fn read_attribute(dir: &Path, name: &str) -> Option<String> {
+    let value = fs::read_to_string(dir.join(name)).ok()?;
+    let value = value.trim();
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Reads a sysfs attribute holding a hexadecimal number such as `idVendor`.
+fn read_hex_attribute<T: TryFrom<u32>>(dir: &Path, name: &str) -> Option<T> {
+    let value = u32::from_str_radix(&read_attribute(dir, name)?, 16).ok()?;
+    T::try_from(value).ok()
+}
+
+/// Returns the name of the bus a sysfs device directory belongs to, e.g. `usb` or `pci`.
+fn subsystem(dir: &Path) -> Option<String> {
+    let link = fs::read_link(dir.join("subsystem")).ok()?;
+    Some(link.file_name()?.to_string_lossy().into_owned())
+}
+
+/// Walks from the tty's device up through its parents until the bus that
+/// exposes it is found.
+///
+/// USB serial adapters are nested as `usb device / interface / [usb-serial port]`,
+/// so the interface number is picked up on the way to the USB device itself.
+fn port_type(sysfs: &Path, device: &Path) -> SerialPortType {
+    let mut interface = None;
+
+    for dir in device.ancestors().take_while(|dir| dir.starts_with(sysfs)) {
+        if interface.is_none() {
+            interface = read_hex_attribute::<u8>(dir, "bInterfaceNumber");
+        }
+
+        match subsystem(dir).as_deref() {
+            Some("usb") => {
+                let vid = read_hex_attribute(dir, "idVendor");
+                let pid = read_hex_attribute(dir, "idProduct");
+
+                if let (Some(vid), Some(pid)) = (vid, pid) {
+                    return SerialPortType::UsbPort(UsbPortInfo {
+                        vid,
+                        pid,
+                        serial_number: read_attribute(dir, "serial"),
+                        manufacturer: read_attribute(dir, "manufacturer"),
+                        product: read_attribute(dir, "product"),
+                        interface,
+                    });
+                }
+            }
+            Some("pci") => return SerialPortType::PciPort,
+            Some("bluetooth") => return SerialPortType::BluetoothPort,
+            _ => (),
+        }
+    }
+
+    SerialPortType::Unknown
+}
+
+/// Inspects a single `/sys/class/tty` entry, returning `None` for ttys that
+/// are not serial ports.
+fn probe_tty(sysfs: &Path, tty: &Path, name: &str) -> Option<SerialPortInfo> {
+    let port_name = format!("/dev/{}", name);
+
+    // RFCOMM ttys are created by the Bluetooth stack without a parent device
+    if name.starts_with("rfcomm") {
+        return Some(SerialPortInfo {
+            port_name,
+            port_type: SerialPortType::BluetoothPort,
+        });
+    }
+
+    // Virtual consoles and pseudo-terminals have no backing device
+    let device: PathBuf = fs::canonicalize(tty.join("device")).ok()?;
+
+    // The 8250 driver registers a fixed number of legacy ttySx ports whether
+    // or not a UART is present; the missing ones report PORT_UNKNOWN (0)
+    if read_attribute(tty, "type").as_deref() == Some("0") {
+        return None;
+    }
+
+    Some(SerialPortInfo {
+        port_name,
+        port_type: port_type(sysfs, &device),
+    })
+}
+
+/// Enumerates serial ports below the given sysfs mount point.
+fn available_ports_in(sysfs: &Path) -> io::Result<Vec<SerialPortInfo>> {
+    let sysfs = fs::canonicalize(sysfs)?;
+    let mut ports = Vec::new();
+
+    for entry in fs::read_dir(sysfs.join("class").join("tty"))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if let Some(port) = probe_tty(&sysfs, &entry.path(), &name) {
+            ports.push(port);
+        }
+    }
+
+    ports.sort_by(|a, b| a.port_name.cmp(&b.port_name));
+
+    Ok(ports)
+}
+
+pub(crate) fn available_ports() -> io::Result<Vec<SerialPortInfo>> {
+    available_ports_in(Path::new(SYSFS_ROOT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// Creates a device directory with the given attributes, linked to its bus.
+    fn device(sysfs: &Path, path: &str, bus: &str, attributes: &[(&str, &str)]) -> PathBuf {
+        let dir = sysfs.join("devices").join(path);
+        fs::create_dir_all(&dir).unwrap();
+        symlink(sysfs.join("bus").join(bus), dir.join("subsystem")).unwrap();
+
+        for (name, value) in attributes {
+            fs::write(dir.join(name), format!("{}\n", value)).unwrap();
+        }
+
+        dir
+    }
+
+    /// Creates a tty below its parent device, or a virtual one without a
+    /// parent, and links it into `class/tty`.
+    fn tty(sysfs: &Path, parent: Option<&Path>, name: &str, attributes: &[(&str, &str)]) {
+        let dir = match parent {
+            Some(parent) => parent.join("tty").join(name),
+            None => sysfs.join("devices/virtual/tty").join(name),
+        };
+        fs::create_dir_all(&dir).unwrap();
+
+        if let Some(parent) = parent {
+            symlink(parent, dir.join("device")).unwrap();
+        }
+
+        for (name, value) in attributes {
+            fs::write(dir.join(name), format!("{}\n", value)).unwrap();
+        }
+
+        let class = sysfs.join("class/tty");
+        fs::create_dir_all(&class).unwrap();
+        symlink(&dir, class.join(name)).unwrap();
+    }
+
+    #[test]
+    fn available_ports_in_fake_sysfs() {
+        let root = tempfile::tempdir().unwrap();
+        let sysfs = root.path();
+
+        // An FTDI adapter: USB device, interface, usb-serial port, tty
+        device(
+            sysfs,
+            "pci0000:00/0000:00:14.0/usb1/1-2",
+            "usb",
+            &[
+                ("idVendor", "0403"),
+                ("idProduct", "6001"),
+                ("serial", "A50285BI"),
+                ("manufacturer", "FTDI"),
+                ("product", "FT232R USB UART"),
+            ],
+        );
+        device(
+            sysfs,
+            "pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.1",
+            "usb",
+            &[("bInterfaceNumber", "01")],
+        );
+        let port = device(
+            sysfs,
+            "pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.1/ttyUSB0",
+            "usb-serial",
+            &[],
+        );
+        tty(sysfs, Some(&port), "ttyUSB0", &[]);
+
+        // A UART on a PCI card
+        let pci = device(
+            sysfs,
+            "pci0000:00/0000:00:16.3",
+            "pci",
+            &[("vendor", "0x8086")],
+        );
+        tty(sysfs, Some(&pci), "ttyS4", &[("type", "4")]);
+
+        // A legacy 8250 port without a UART behind it
+        let platform = device(sysfs, "platform/serial8250", "platform", &[]);
+        tty(sysfs, Some(&platform), "ttyS1", &[("type", "0")]);
+
+        // A Bluetooth RFCOMM tty and a virtual console
+        tty(sysfs, None, "rfcomm0", &[]);
+        tty(sysfs, None, "tty0", &[]);
+
+        let ports = available_ports_in(sysfs).unwrap();
+
+        assert_eq!(
+            ports,
+            [
+                SerialPortInfo {
+                    port_name: "/dev/rfcomm0".into(),
+                    port_type: SerialPortType::BluetoothPort,
+                },
+                SerialPortInfo {
+                    port_name: "/dev/ttyS4".into(),
+                    port_type: SerialPortType::PciPort,
+                },
+                SerialPortInfo {
+                    port_name: "/dev/ttyUSB0".into(),
+                    port_type: SerialPortType::UsbPort(UsbPortInfo {
+                        vid: 0x0403,
+                        pid: 0x6001,
+                        serial_number: Some("A50285BI".into()),
+                        manufacturer: Some("FTDI".into()),
+                        product: Some("FT232R USB UART".into()),
+                        interface: Some(1),
+                    }),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn available_ports_in_missing_class() {
+        let root = tempfile::tempdir().unwrap();
+
+        assert_eq!(
+            available_ports_in(root.path()).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use std::io;
+use std::os::unix::io::RawFd;
+
+use crate::config::LineEvents;
+
+use super::tty::libc_result;
+
+/// Interrupt counters maintained by the serial driver, as returned by `TIOCGICOUNT`.
+///
+/// Mirrors `struct serial_icounter_struct` from `<linux/serial.h>`. Each
+/// counter only ever increases, so comparing two snapshots reveals every
+/// transition in between, however short.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(super) struct SerialIcounter {
+    pub cts: libc::c_int,
+    pub dsr: libc::c_int,
+    pub rng: libc::c_int,
+    pub dcd: libc::c_int,
+    pub rx: libc::c_int,
+    pub tx: libc::c_int,
+    pub frame: libc::c_int,
+    pub overrun: libc::c_int,
+    pub parity: libc::c_int,
+    pub brk: libc::c_int,
+    pub buf_overrun: libc::c_int,
+    pub reserved: [libc::c_int; 9],
+}
+
+impl SerialIcounter {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn get(fd: RawFd) -> io::Result<Self> {
+        let mut icount = Self::default();
+
+        libc_result(unsafe { libc::ioctl(fd, libc::TIOCGICOUNT, &mut icount) })?;
+
+        Ok(icount)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn get(_fd: RawFd) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "line event counters not implemented for platform",
+        ))
+    }
+
+    fn counters(&mut self) -> [(LineEvents, &mut libc::c_int); 8] {
+        [
+            (LineEvents::CLEAR_TO_SEND, &mut self.cts),
+            (LineEvents::DATA_SET_READY, &mut self.dsr),
+            (LineEvents::RING_INDICATOR, &mut self.rng),
+            (LineEvents::CARRIER_DETECT, &mut self.dcd),
+            (LineEvents::BREAK, &mut self.brk),
+            (LineEvents::FRAMING_ERROR, &mut self.frame),
+            (LineEvents::PARITY_ERROR, &mut self.parity),
+            (LineEvents::OVERRUN, &mut self.overrun),
+        ]
+    }
+
+    /// Returns the events whose counters differ between `self` and `later`.
+    pub fn changes(&self, later: &Self) -> LineEvents {
+        let (mut earlier, mut later) = (*self, *later);
+
+        earlier
+            .counters()
+            .into_iter()
+            .zip(later.counters())
+            .filter(|((_, a), (_, b))| a != b)
+            .fold(LineEvents::empty(), |events, ((event, _), _)| {
+                events | event
+            })
+    }
+
+    /// Catches up with `later` for the given events only, so events that
+    /// were not reported yet are still seen by the next comparison.
+    pub fn consume(&mut self, later: &Self, events: LineEvents) {
+        let mut later = *later;
+
+        for ((event, counter), (_, value)) in self.counters().into_iter().zip(later.counters()) {
+            if events.contains(event) {
+                *counter = *value;
+            }
+        }
+    }
+
+    /// Counts an event on an emulated line.
+    pub fn record(&mut self, events: LineEvents) {
+        for (event, counter) in self.counters() {
+            if events.contains(event) {
+                *counter = counter.wrapping_add(1);
+            }
+        }
+    }
+}
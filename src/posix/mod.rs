@@ -0,0 +1,29 @@
+pub use tty::*;
+
+#[cfg(feature = "async-io")]
+pub use async_io_port::AsyncIoSerialPort;
+#[cfg(feature = "tokio")]
+pub use tokio_port::AsyncSerialPort;
+
+#[cfg(target_os = "linux")]
+pub(super) use enumerate::available_ports;
+
+#[cfg(feature = "async-io")]
+mod async_io_port;
+#[cfg(target_os = "linux")]
+mod enumerate;
+mod icount;
+mod null_modem;
+mod rs485;
+mod termios;
+#[cfg(feature = "tokio")]
+mod tokio_port;
+mod tty;
+
+#[cfg(not(target_os = "linux"))]
+pub(super) fn available_ports() -> std::io::Result<Vec<crate::SerialPortInfo>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "available_ports() not implemented for platform",
+    ))
+}
@@ -0,0 +1,138 @@
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Instant;
+
+use crate::config::LineEvents;
+
+use super::icount::SerialIcounter;
+
+#[derive(Debug, Default)]
+struct Lines {
+    /// The `TIOCM_RTS | TIOCM_DTR` bits driven by each end
+    outputs: [libc::c_int; 2],
+    /// Interrupt counters as seen by each end
+    counters: [SerialIcounter; 2],
+}
+
+/// Modem control lines shared by the two ends of a pseudo-terminal pair.
+///
+/// Pseudo-terminals have no modem lines, so `TIOCMGET` and friends fail on
+/// them. Ports created with `TTYPort::pair` emulate a null-modem cable
+/// instead: each end's RTS drives the other end's CTS, and DTR drives the
+/// other end's DSR and CD. RI is never asserted.
+#[derive(Debug, Default)]
+struct Cable {
+    lines: Mutex<Lines>,
+    changed: Condvar,
+}
+
+impl Cable {
+    fn lock(&self) -> MutexGuard<'_, Lines> {
+        self.lines.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// One end of an emulated null-modem cable.
+#[derive(Debug, Clone)]
+pub(super) struct NullModem {
+    cable: Arc<Cable>,
+    end: usize,
+}
+
+impl NullModem {
+    /// Creates both ends of a cable. Like a real tty after `open`, both ends
+    /// start with RTS and DTR asserted.
+    pub fn pair() -> (Self, Self) {
+        let cable = Arc::new(Cable::default());
+        cable.lock().outputs = [libc::TIOCM_RTS | libc::TIOCM_DTR; 2];
+
+        (
+            Self {
+                cable: Arc::clone(&cable),
+                end: 0,
+            },
+            Self { cable, end: 1 },
+        )
+    }
+
+    /// Returns the line state in `TIOCMGET` format as seen from this end.
+    pub fn get(&self) -> libc::c_int {
+        let lines = self.cable.lock();
+        let own = lines.outputs[self.end];
+        let peer = lines.outputs[1 - self.end];
+
+        let mut bits = own;
+        if peer & libc::TIOCM_RTS != 0 {
+            bits |= libc::TIOCM_CTS;
+        }
+        if peer & libc::TIOCM_DTR != 0 {
+            bits |= libc::TIOCM_DSR | libc::TIOCM_CAR;
+        }
+        bits
+    }
+
+    /// Asserts or clears output lines, in the manner of `TIOCMBIS`/`TIOCMBIC`.
+    pub fn set(&self, bits: libc::c_int, level: bool) {
+        let mut lines = self.cable.lock();
+        let before = lines.outputs[self.end];
+
+        if level {
+            lines.outputs[self.end] |= bits & (libc::TIOCM_RTS | libc::TIOCM_DTR);
+        } else {
+            lines.outputs[self.end] &= !bits;
+        }
+
+        let changed = before ^ lines.outputs[self.end];
+        let mut events = LineEvents::empty();
+        if changed & libc::TIOCM_RTS != 0 {
+            events |= LineEvents::CLEAR_TO_SEND;
+        }
+        if changed & libc::TIOCM_DTR != 0 {
+            events |= LineEvents::DATA_SET_READY | LineEvents::CARRIER_DETECT;
+        }
+
+        if !events.is_empty() {
+            lines.counters[1 - self.end].record(events);
+            self.cable.changed.notify_all();
+        }
+    }
+
+    /// Starts a break condition, which the other end counts as a break.
+    pub fn set_break(&self) {
+        let mut lines = self.cable.lock();
+        lines.counters[1 - self.end].record(LineEvents::BREAK);
+        self.cable.changed.notify_all();
+    }
+
+    /// Returns the interrupt counters as seen from this end.
+    pub fn icount(&self) -> SerialIcounter {
+        self.cable.lock().counters[self.end]
+    }
+
+    /// Blocks until the counters seen from this end differ from `seen`, or
+    /// until `deadline` passes.
+    pub fn wait(&self, seen: &SerialIcounter, deadline: Option<Instant>) {
+        let mut lines = self.cable.lock();
+
+        while lines.counters[self.end] == *seen {
+            lines = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return;
+                    }
+
+                    self.cable
+                        .changed
+                        .wait_timeout(lines, deadline - now)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self
+                    .cable
+                    .changed
+                    .wait(lines)
+                    .unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::io;
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+use crate::config::Rs485Config;
+
+#[cfg(target_os = "linux")]
+use super::tty::libc_result;
+
+// Flags of `struct serial_rs485` from `<linux/serial.h>`
+const SER_RS485_ENABLED: u32 = 1 << 0;
+const SER_RS485_RTS_ON_SEND: u32 = 1 << 1;
+const SER_RS485_RTS_AFTER_SEND: u32 = 1 << 2;
+const SER_RS485_RX_DURING_TX: u32 = 1 << 4;
+
+/// RS-485 settings as passed to `TIOCSRS485`.
+///
+/// Mirrors `struct serial_rs485` from `<linux/serial.h>`.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct SerialRs485 {
+    flags: u32,
+    delay_rts_before_send: u32,
+    delay_rts_after_send: u32,
+    padding: [u32; 5],
+}
+
+/// Converts a delay to the whole milliseconds used by the kernel, rounding up.
+fn milliseconds(delay: Duration) -> u32 {
+    u32::try_from(delay.as_nanos().div_ceil(1_000_000)).unwrap_or(u32::MAX)
+}
+
+impl SerialRs485 {
+    pub fn new(config: &Rs485Config) -> Self {
+        let mut flags = 0;
+
+        for (set, flag) in [
+            (config.enabled, SER_RS485_ENABLED),
+            (config.rts_on_send, SER_RS485_RTS_ON_SEND),
+            (config.rts_after_send, SER_RS485_RTS_AFTER_SEND),
+            (config.rx_during_tx, SER_RS485_RX_DURING_TX),
+        ] {
+            if set {
+                flags |= flag;
+            }
+        }
+
+        Self {
+            flags,
+            delay_rts_before_send: milliseconds(config.delay_before_send),
+            delay_rts_after_send: milliseconds(config.delay_after_send),
+            ..Self::default()
+        }
+    }
+
+    /// Hands the settings to the driver. Drivers without RS-485 support fail
+    /// with `ENOTTY`.
+    #[cfg(target_os = "linux")]
+    pub fn set(&self, fd: RawFd) -> io::Result<()> {
+        libc_result(unsafe { libc::ioctl(fd, libc::TIOCSRS485, self) })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn set(&self, _fd: RawFd) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "RS-485 ioctls not implemented for platform",
+        ))
+    }
+
+    /// Returns `true` if `error` means that the port has no RS-485 support
+    /// in the driver, so RTS has to be switched in software.
+    pub fn is_unsupported(error: &io::Error) -> bool {
+        error.kind() == io::ErrorKind::Unsupported || error.raw_os_error() == Some(libc::ENOTTY)
+    }
+}
@@ -0,0 +1,337 @@
+use std::io;
+use std::os::unix::io::RawFd;
+
+use crate::SerialPortBuilder;
+use crate::config::{DataBits, FlowControl, Parity, StopBits};
+
+use super::tty::libc_result;
+
+/// Baud rates that can be expressed with the standard `Bxxxx` speed constants.
+///
+/// Linux sets arbitrary rates through `termios2` instead.
+#[cfg(not(target_os = "linux"))]
+const BAUD_RATES: &[(u32, libc::speed_t)] = &[
+    (50, libc::B50),
+    (75, libc::B75),
+    (110, libc::B110),
+    (134, libc::B134),
+    (150, libc::B150),
+    (200, libc::B200),
+    (300, libc::B300),
+    (600, libc::B600),
+    (1200, libc::B1200),
+    (1800, libc::B1800),
+    (2400, libc::B2400),
+    (4800, libc::B4800),
+    (9600, libc::B9600),
+    (19200, libc::B19200),
+    (38400, libc::B38400),
+    (57600, libc::B57600),
+    (115200, libc::B115200),
+    (230400, libc::B230400),
+    #[cfg(target_os = "android")]
+    (460800, libc::B460800),
+    #[cfg(target_os = "android")]
+    (500000, libc::B500000),
+    #[cfg(target_os = "android")]
+    (576000, libc::B576000),
+    #[cfg(target_os = "android")]
+    (921600, libc::B921600),
+    #[cfg(target_os = "android")]
+    (1000000, libc::B1000000),
+    #[cfg(target_os = "android")]
+    (1152000, libc::B1152000),
+    #[cfg(target_os = "android")]
+    (1500000, libc::B1500000),
+    #[cfg(target_os = "android")]
+    (2000000, libc::B2000000),
+    #[cfg(target_os = "android")]
+    (2500000, libc::B2500000),
+    #[cfg(target_os = "android")]
+    (3000000, libc::B3000000),
+    #[cfg(target_os = "android")]
+    (3500000, libc::B3500000),
+    #[cfg(target_os = "android")]
+    (4000000, libc::B4000000),
+];
+
+/// How far, in percent, the rate applied by the driver may be from the
+/// requested one. UARTs tolerate a mismatch of a few percent, and the kernel
+/// itself treats rates within 2% of a `Bxxxx` constant as equal to it.
+#[cfg(target_os = "linux")]
+const BAUD_RATE_TOLERANCE: u64 = 2;
+
+/// The kernel's `struct termios2` on Linux, which carries the line speed as a
+/// plain number; `struct termios` elsewhere.
+#[cfg(target_os = "linux")]
+type Termios = libc::termios2;
+#[cfg(not(target_os = "linux"))]
+type Termios = libc::termios;
+
+#[must_use]
+pub(super) struct PosixTermios {
+    pub(super) inner: Termios,
+}
+
+impl PosixTermios {
+    #[cfg(target_os = "linux")]
+    pub fn get(fd: RawFd) -> io::Result<Self> {
+        let mut termios: Termios = unsafe { std::mem::zeroed() };
+
+        libc_result(unsafe { libc::ioctl(fd, libc::TCGETS2, &mut termios) })?;
+
+        Ok(Self { inner: termios })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn get(fd: RawFd) -> io::Result<Self> {
+        let mut termios: Termios = unsafe { std::mem::zeroed() };
+
+        libc_result(unsafe { libc::tcgetattr(fd, &mut termios) })?;
+
+        Ok(Self { inner: termios })
+    }
+
+    pub fn update(&mut self, builder: &SerialPortBuilder) -> io::Result<&mut Self> {
+        self.raw()
+            .baud_rate(builder.baud_rate)?
+            .data_bits(builder.data_bits)
+            .stop_bits(builder.stop_bits)?
+            .parity(builder.parity)?
+            .flow_control(builder.flow_control);
+        Ok(self)
+    }
+
+    /// Disables all line discipline processing and lets `poll` drive the
+    /// timeouts, so `VMIN` and `VTIME` are both zero.
+    pub fn raw(&mut self) -> &mut Self {
+        // Same as cfmakeraw, which does not accept a termios2
+        self.inner.c_iflag &= !(libc::IGNBRK
+            | libc::BRKINT
+            | libc::PARMRK
+            | libc::ISTRIP
+            | libc::INLCR
+            | libc::IGNCR
+            | libc::ICRNL
+            | libc::IXON);
+        self.inner.c_oflag &= !libc::OPOST;
+        self.inner.c_lflag &=
+            !(libc::ECHO | libc::ECHONL | libc::ICANON | libc::ISIG | libc::IEXTEN);
+        self.inner.c_cflag &= !(libc::CSIZE | libc::PARENB);
+        self.inner.c_cflag |= libc::CS8 | libc::CREAD | libc::CLOCAL;
+        self.inner.c_cc[libc::VMIN] = 0;
+        self.inner.c_cc[libc::VTIME] = 0;
+        self
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn baud_rate(&mut self, baud_rate: u32) -> io::Result<&mut Self> {
+        // A speed of zero means "hang up" rather than a baud rate
+        if baud_rate == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unsupported baud rate: 0",
+            ));
+        }
+
+        // Clearing CIBAUD makes the input speed follow the output speed
+        self.inner.c_cflag &= !(libc::CBAUD | libc::CIBAUD);
+        self.inner.c_cflag |= libc::BOTHER;
+        self.inner.c_ispeed = baud_rate;
+        self.inner.c_ospeed = baud_rate;
+        Ok(self)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn baud_rate(&mut self, baud_rate: u32) -> io::Result<&mut Self> {
+        let speed = BAUD_RATES
+            .iter()
+            .find(|&&(rate, _)| rate == baud_rate)
+            .map(|&(_, speed)| speed)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported baud rate: {}", baud_rate),
+                )
+            })?;
+
+        libc_result(unsafe { libc::cfsetspeed(&mut self.inner, speed) })?;
+        Ok(self)
+    }
+
+    pub fn data_bits(&mut self, data_bits: DataBits) -> &mut Self {
+        self.inner.c_cflag &= !libc::CSIZE;
+        self.inner.c_cflag |= match data_bits {
+            DataBits::Five => libc::CS5,
+            DataBits::Six => libc::CS6,
+            DataBits::Seven => libc::CS7,
+            DataBits::Eight => libc::CS8,
+        };
+        self
+    }
+
+    pub fn stop_bits(&mut self, stop_bits: StopBits) -> io::Result<&mut Self> {
+        match stop_bits {
+            StopBits::One => self.inner.c_cflag &= !libc::CSTOPB,
+            StopBits::Two => self.inner.c_cflag |= libc::CSTOPB,
+            StopBits::OnePointFive => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "1.5 stop bits are not supported by termios",
+                ));
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn parity(&mut self, parity: Parity) -> io::Result<&mut Self> {
+        self.inner.c_cflag &= !(libc::PARENB | libc::PARODD);
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            self.inner.c_cflag &= !libc::CMSPAR;
+        }
+        self.inner.c_iflag &= !(libc::INPCK | libc::IGNPAR);
+
+        match parity {
+            Parity::None => {
+                self.inner.c_iflag |= libc::IGNPAR;
+            }
+            Parity::Odd => {
+                self.inner.c_cflag |= libc::PARENB | libc::PARODD;
+                self.inner.c_iflag |= libc::INPCK;
+            }
+            Parity::Even => {
+                self.inner.c_cflag |= libc::PARENB;
+                self.inner.c_iflag |= libc::INPCK;
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Parity::Mark => {
+                self.inner.c_cflag |= libc::PARENB | libc::PARODD | libc::CMSPAR;
+                self.inner.c_iflag |= libc::INPCK;
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Parity::Space => {
+                self.inner.c_cflag |= libc::PARENB | libc::CMSPAR;
+                self.inner.c_iflag |= libc::INPCK;
+            }
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            Parity::Mark | Parity::Space => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "mark and space parity are not supported on this platform",
+                ));
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn flow_control(&mut self, flow_control: FlowControl) -> &mut Self {
+        match flow_control {
+            FlowControl::None => {
+                self.inner.c_cflag &= !libc::CRTSCTS;
+                self.inner.c_iflag &= !(libc::IXON | libc::IXOFF);
+            }
+            FlowControl::Software => {
+                self.inner.c_cflag &= !libc::CRTSCTS;
+                self.inner.c_iflag |= libc::IXON | libc::IXOFF;
+            }
+            FlowControl::Hardware => {
+                self.inner.c_cflag |= libc::CRTSCTS;
+                self.inner.c_iflag &= !(libc::IXON | libc::IXOFF);
+            }
+        }
+        self
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn get_baud_rate(&self) -> io::Result<u32> {
+        Ok(self.inner.c_ospeed)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn get_baud_rate(&self) -> io::Result<u32> {
+        let speed = unsafe { libc::cfgetospeed(&self.inner) };
+
+        BAUD_RATES
+            .iter()
+            .find(|&&(_, s)| s == speed)
+            .map(|&(rate, _)| rate)
+            .ok_or_else(|| io::ErrorKind::InvalidData.into())
+    }
+
+    pub fn get_data_bits(&self) -> io::Result<DataBits> {
+        match self.inner.c_cflag & libc::CSIZE {
+            libc::CS5 => Ok(DataBits::Five),
+            libc::CS6 => Ok(DataBits::Six),
+            libc::CS7 => Ok(DataBits::Seven),
+            libc::CS8 => Ok(DataBits::Eight),
+            _ => Err(io::ErrorKind::InvalidData.into()),
+        }
+    }
+
+    pub fn get_flow_control(&self) -> FlowControl {
+        if self.inner.c_cflag & libc::CRTSCTS != 0 {
+            FlowControl::Hardware
+        } else if self.inner.c_iflag & (libc::IXON | libc::IXOFF) != 0 {
+            FlowControl::Software
+        } else {
+            FlowControl::None
+        }
+    }
+
+    pub fn get_parity(&self) -> Parity {
+        let cflag = self.inner.c_cflag;
+
+        if cflag & libc::PARENB == 0 {
+            return Parity::None;
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if cflag & libc::CMSPAR != 0 {
+            return match cflag & libc::PARODD {
+                0 => Parity::Space,
+                _ => Parity::Mark,
+            };
+        }
+
+        match cflag & libc::PARODD {
+            0 => Parity::Even,
+            _ => Parity::Odd,
+        }
+    }
+
+    pub fn get_stop_bits(&self) -> StopBits {
+        match self.inner.c_cflag & libc::CSTOPB {
+            0 => StopBits::One,
+            _ => StopBits::Two,
+        }
+    }
+
+    /// Applies the settings, failing with `InvalidInput` if the driver
+    /// cannot get close enough to the requested baud rate.
+    #[cfg(target_os = "linux")]
+    pub fn set(&mut self, fd: RawFd) -> io::Result<()> {
+        libc_result(unsafe { libc::ioctl(fd, libc::TCSETS2, &self.inner) })?;
+
+        // Drivers apply the closest rate their divisors allow and report it back
+        let requested = u64::from(self.inner.c_ospeed);
+        let actual = u64::from(Self::get(fd)?.inner.c_ospeed);
+
+        if requested.abs_diff(actual) * 100 > requested * BAUD_RATE_TOLERANCE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "unsupported baud rate: {} (the closest the device supports is {})",
+                    requested, actual
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn set(&mut self, fd: RawFd) -> io::Result<()> {
+        libc_result(unsafe { libc::tcsetattr(fd, libc::TCSANOW, &self.inner) })
+    }
+}
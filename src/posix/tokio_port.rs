@@ -0,0 +1,419 @@
+use std::io;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+
+use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{
+    SerialPort, SerialPortBuilder,
+    communication::Communication,
+    config::{ClearBuffer, DataBits, FlowControl, LineEvents, Parity, Rs485Config, StopBits},
+};
+
+use super::tty::{EVENT_POLL_INTERVAL, TTYPort, read_nonblocking, write_nonblocking};
+
+/// The descriptor registered with the tokio reactor. It is owned by the
+/// `TTYPort`, which outlives the registration.
+struct Registration(RawFd);
+
+impl AsRawFd for Registration {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+/// A serial port for use with tokio.
+///
+/// The port implements [`AsyncRead`] and [`AsyncWrite`] on top of the
+/// non-blocking tty descriptor, so reads and writes wait in the tokio reactor
+/// instead of blocking a thread. Its settings are read and changed through
+/// async counterparts of the [`SerialPort`] methods, and it can be closed and
+/// reopened through [`Communication`] like a blocking port.
+///
+/// The timeout of the builder is not used: wrap reads and writes in
+/// `tokio::time::timeout` instead. Ports must be created and opened from
+/// within a tokio runtime.
+///
+/// RS-485 settings are applied as on a blocking port, but RTS is only
+/// switched around async writes by drivers with RS-485 support.
+///
+/// # Examples
+///
+/// ```rust
+/// use tokio::io::{AsyncReadExt, AsyncWriteExt};
+/// use serialport::{AsyncSerialPort, SerialPortBuilder};
+///
+/// # tokio::runtime::Builder::new_current_thread().enable_all().build()?.block_on(async {
+/// let (mut master, mut slave) = AsyncSerialPort::pair(SerialPortBuilder::new().baud_rate(115200))?;
+///
+/// master.write_all(b"ping").await?;
+///
+/// let mut buffer = [0u8; 4];
+/// slave.read_exact(&mut buffer).await?;
+/// assert_eq!(&buffer, b"ping");
+/// assert_eq!(slave.baud_rate().await?, 115200);
+/// # Ok::<(), std::io::Error>(())
+/// # })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// A setting the device rejects leaves the port open and registered, with
+/// its previous settings:
+///
+/// ```rust
+/// use tokio::io::{AsyncReadExt, AsyncWriteExt};
+/// use serialport::{AsyncSerialPort, SerialPortBuilder};
+/// use serialport::communication::Communication;
+///
+/// # tokio::runtime::Builder::new_current_thread().enable_all().build()?.block_on(async {
+/// let (mut master, mut slave) = AsyncSerialPort::pair(SerialPortBuilder::new().baud_rate(115200))?;
+///
+/// assert!(slave.set_baud_rate(0).await.is_err());
+/// assert!(slave.is_open());
+/// assert_eq!(slave.baud_rate().await?, 115200);
+///
+/// // A file opened now does not take over the descriptor of the port
+/// let _file = std::fs::File::open("/dev/null")?;
+///
+/// master.write_all(b"pong").await?;
+///
+/// let mut buffer = [0u8; 4];
+/// slave.read_exact(&mut buffer).await?;
+/// assert_eq!(&buffer, b"pong");
+/// # Ok::<(), std::io::Error>(())
+/// # })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct AsyncSerialPort {
+    port: TTYPort,
+    io: Option<AsyncFd<Registration>>,
+}
+
+impl AsyncSerialPort {
+    /// Creates a port with the given settings, opening it if the builder has
+    /// a path.
+    pub fn new(builder: SerialPortBuilder) -> io::Result<Self> {
+        Self::from_port(TTYPort::new(builder)?)
+    }
+
+    /// Creates a pair of connected pseudo-terminal ports.
+    ///
+    /// See [`SerialPortBuilder::build_pair`] for details.
+    pub fn pair(builder: SerialPortBuilder) -> io::Result<(Self, Self)> {
+        let (master, slave) = TTYPort::pair(builder)?;
+
+        Ok((Self::from_port(master)?, Self::from_port(slave)?))
+    }
+
+    fn from_port(port: TTYPort) -> io::Result<Self> {
+        let mut serialport = Self { port, io: None };
+
+        if serialport.port.is_open() {
+            serialport.register()?;
+        }
+
+        Ok(serialport)
+    }
+
+    fn register(&mut self) -> io::Result<()> {
+        self.io = Some(AsyncFd::new(Registration(self.port.as_raw_fd()))?);
+        Ok(())
+    }
+
+    fn io(&self) -> io::Result<&AsyncFd<Registration>> {
+        self.io
+            .as_ref()
+            .filter(|_| self.port.is_open())
+            .ok_or_else(|| io::ErrorKind::NotConnected.into())
+    }
+
+    /// Changes a setting of the inner port. Should the port have lost its
+    /// descriptor, it also leaves the reactor, before the number is reused.
+    fn configure(&mut self, change: impl FnOnce(&mut TTYPort) -> io::Result<()>) -> io::Result<()> {
+        let result = change(&mut self.port);
+
+        if !self.port.is_open() {
+            self.io = None;
+        }
+
+        result
+    }
+
+    /// Returns the path of the port.
+    pub async fn path(&self) -> Option<String> {
+        self.port.path()
+    }
+
+    /// Gets the current baud rate. See [`SerialPort::baud_rate`].
+    pub async fn baud_rate(&self) -> io::Result<u32> {
+        self.port.baud_rate()
+    }
+
+    /// Gets the current data bits setting. See [`SerialPort::data_bits`].
+    pub async fn data_bits(&self) -> io::Result<DataBits> {
+        self.port.data_bits()
+    }
+
+    /// Gets the current flow control setting. See [`SerialPort::flow_control`].
+    pub async fn flow_control(&self) -> io::Result<FlowControl> {
+        self.port.flow_control()
+    }
+
+    /// Gets the current parity setting. See [`SerialPort::parity`].
+    pub async fn parity(&self) -> io::Result<Parity> {
+        self.port.parity()
+    }
+
+    /// Gets the current stop bits setting. See [`SerialPort::stop_bits`].
+    pub async fn stop_bits(&self) -> io::Result<StopBits> {
+        self.port.stop_bits()
+    }
+
+    /// Gets the current RS-485 settings. See [`SerialPort::rs485`].
+    pub async fn rs485(&self) -> Rs485Config {
+        self.port.rs485()
+    }
+
+    /// Gets the number of bytes available to be read. See [`SerialPort::bytes_to_read`].
+    pub async fn bytes_to_read(&self) -> io::Result<u32> {
+        self.port.bytes_to_read()
+    }
+
+    /// Gets the number of bytes waiting to be transmitted. See [`SerialPort::bytes_to_write`].
+    pub async fn bytes_to_write(&self) -> io::Result<u32> {
+        self.port.bytes_to_write()
+    }
+
+    /// Changes the port path, reopening the port if it was open. See [`SerialPort::set_path`].
+    pub async fn set_path<'a>(&mut self, path: std::borrow::Cow<'a, str>) -> io::Result<()> {
+        let was_open = self.is_open();
+        if was_open {
+            self.close()?;
+        }
+
+        self.port.set_path(path)?;
+
+        if was_open {
+            self.open()?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the baud rate. See [`SerialPort::set_baud_rate`].
+    pub async fn set_baud_rate(&mut self, baud_rate: u32) -> io::Result<()> {
+        self.configure(|port| port.set_baud_rate(baud_rate))
+    }
+
+    /// Sets the number of data bits. See [`SerialPort::set_data_bits`].
+    pub async fn set_data_bits(&mut self, data_bits: DataBits) -> io::Result<()> {
+        self.configure(|port| port.set_data_bits(data_bits))
+    }
+
+    /// Sets the flow control mode. See [`SerialPort::set_flow_control`].
+    pub async fn set_flow_control(&mut self, flow_control: FlowControl) -> io::Result<()> {
+        self.configure(|port| port.set_flow_control(flow_control))
+    }
+
+    /// Sets the parity checking mode. See [`SerialPort::set_parity`].
+    pub async fn set_parity(&mut self, parity: Parity) -> io::Result<()> {
+        self.configure(|port| port.set_parity(parity))
+    }
+
+    /// Sets the number of stop bits. See [`SerialPort::set_stop_bits`].
+    pub async fn set_stop_bits(&mut self, stop_bits: StopBits) -> io::Result<()> {
+        self.configure(|port| port.set_stop_bits(stop_bits))
+    }
+
+    /// Sets the RS-485 settings. See [`SerialPort::set_rs485`].
+    pub async fn set_rs485(&mut self, rs485: Rs485Config) -> io::Result<()> {
+        self.configure(|port| port.set_rs485(rs485))
+    }
+
+    /// Discards buffered data. See [`SerialPort::clear`].
+    pub async fn clear(&self, buffer_to_clear: ClearBuffer) -> io::Result<()> {
+        self.port.clear(buffer_to_clear)
+    }
+
+    /// Sets the state of the RTS line. See [`SerialPort::write_request_to_send`].
+    pub async fn write_request_to_send(&mut self, level: bool) -> io::Result<()> {
+        self.port.write_request_to_send(level)
+    }
+
+    /// Sets the state of the DTR line. See [`SerialPort::write_data_terminal_ready`].
+    pub async fn write_data_terminal_ready(&mut self, level: bool) -> io::Result<()> {
+        self.port.write_data_terminal_ready(level)
+    }
+
+    /// Reads the state of the CTS line. See [`SerialPort::read_clear_to_send`].
+    pub async fn read_clear_to_send(&self) -> io::Result<bool> {
+        self.port.read_clear_to_send()
+    }
+
+    /// Reads the state of the DSR line. See [`SerialPort::read_data_set_ready`].
+    pub async fn read_data_set_ready(&self) -> io::Result<bool> {
+        self.port.read_data_set_ready()
+    }
+
+    /// Reads the state of the RI line. See [`SerialPort::read_ring_indicator`].
+    pub async fn read_ring_indicator(&self) -> io::Result<bool> {
+        self.port.read_ring_indicator()
+    }
+
+    /// Reads the state of the CD line. See [`SerialPort::read_carrier_detect`].
+    pub async fn read_carrier_detect(&self) -> io::Result<bool> {
+        self.port.read_carrier_detect()
+    }
+
+    /// Starts a break condition. See [`SerialPort::set_break`].
+    pub async fn set_break(&mut self) -> io::Result<()> {
+        self.port.set_break()
+    }
+
+    /// Ends a break condition. See [`SerialPort::clear_break`].
+    pub async fn clear_break(&mut self) -> io::Result<()> {
+        self.port.clear_break()
+    }
+
+    /// Transmits a break of the given length once queued data has been sent.
+    /// See [`SerialPort::send_break`].
+    pub async fn send_break(&mut self, duration: Duration) -> io::Result<()> {
+        let duration = if duration.is_zero() {
+            Duration::from_millis(250)
+        } else {
+            duration
+        };
+
+        while self.port.bytes_to_write()? > 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        self.port.set_break()?;
+        tokio::time::sleep(duration).await;
+        self.port.clear_break()
+    }
+
+    /// Waits for a modem line change or line error. See [`SerialPort::wait_for_event`].
+    pub async fn wait_for_event(
+        &mut self,
+        mask: LineEvents,
+        timeout: Duration,
+    ) -> io::Result<LineEvents> {
+        let deadline = Instant::now().checked_add(timeout);
+
+        loop {
+            match self.port.wait_for_event(mask, Duration::ZERO) {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => (),
+                result => return result,
+            }
+
+            let delay = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(io::ErrorKind::TimedOut.into());
+                    }
+
+                    Duration::min(deadline - now, EVENT_POLL_INTERVAL)
+                }
+                None => EVENT_POLL_INTERVAL,
+            };
+
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+impl Communication for AsyncSerialPort {
+    fn is_open(&self) -> bool {
+        self.port.is_open()
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        self.port.open()?;
+
+        if let Err(e) = self.register() {
+            let _ = self.port.close();
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        // The descriptor must leave the reactor before it is closed
+        self.io = None;
+        self.port.close()
+    }
+}
+
+impl AsRawFd for AsyncSerialPort {
+    fn as_raw_fd(&self) -> RawFd {
+        self.port.as_raw_fd()
+    }
+}
+
+impl AsFd for AsyncSerialPort {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.port.as_fd()
+    }
+}
+
+impl AsyncRead for AsyncSerialPort {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let io = self.io()?;
+
+        loop {
+            let mut guard = ready!(io.poll_read_ready(cx))?;
+
+            match guard
+                .try_io(|inner| read_nonblocking(inner.as_raw_fd(), buf.initialize_unfilled()))
+            {
+                Ok(Ok(bytes_read)) => {
+                    buf.advance(bytes_read);
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(e)) => return Poll::Ready(Err(e)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for AsyncSerialPort {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let io = self.io()?;
+
+        loop {
+            let mut guard = ready!(io.poll_write_ready(cx))?;
+
+            match guard.try_io(|inner| write_nonblocking(inner.as_raw_fd(), buf)) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Data is handed to the driver as soon as it is written, so there is
+    /// nothing to flush. Use `bytes_to_write` to find out whether it has
+    /// been transmitted.
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.io()?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
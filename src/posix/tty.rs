@@ -0,0 +1,1004 @@
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::time::{Duration, Instant};
+
+use crate::{
+    SerialPort, SerialPortBuilder,
+    communication::Communication,
+    config::{ClearBuffer, DataBits, FlowControl, LineEvents, Parity, Rs485Config, StopBits},
+    posix::{icount::SerialIcounter, null_modem::NullModem, rs485::SerialRs485, termios},
+    private,
+};
+
+/// How often the interrupt counters are sampled while waiting for line
+/// events with a timeout. The counters never miss a transition, so this only
+/// bounds how late an event can be reported.
+pub(super) const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub(super) fn libc_result(result: libc::c_int) -> io::Result<()> {
+    match result {
+        -1 => Err(std::io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the path of the slave device belonging to a pty master.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn ptsname(fd: RawFd) -> io::Result<String> {
+    let mut name = [0 as libc::c_char; 128];
+
+    match unsafe { libc::ptsname_r(fd, name.as_mut_ptr(), name.len()) } {
+        0 => Ok(unsafe { CStr::from_ptr(name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned()),
+        error => Err(std::io::Error::from_raw_os_error(error)),
+    }
+}
+
+/// Returns the path of the slave device belonging to a pty master.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn ptsname(fd: RawFd) -> io::Result<String> {
+    let name = unsafe { libc::ptsname(fd) };
+
+    if name.is_null() {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(unsafe { CStr::from_ptr(name) }
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Converts a timeout into the millisecond argument expected by `poll`,
+/// rounding sub-millisecond timeouts up so they don't become non-blocking.
+fn poll_timeout(timeout: Duration) -> libc::c_int {
+    let milliseconds =
+        timeout.as_millis() + u128::from(!timeout.subsec_nanos().is_multiple_of(1_000_000));
+    u128::min(milliseconds, libc::c_int::MAX as u128) as libc::c_int
+}
+
+/// Reads whatever data is available without waiting.
+///
+/// With `VMIN` and `VTIME` at zero, a tty returns 0 rather than `EAGAIN` when
+/// there is no data, which readers expecting non-blocking semantics would take
+/// for end of file. That is reported as `WouldBlock` unless the line hung up.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub(super) fn read_nonblocking(fd: RawFd, buf: &mut [u8]) -> io::Result<usize> {
+    match unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) } {
+        -1 => Err(std::io::Error::last_os_error()),
+        0 if !buf.is_empty() => {
+            let mut pollfd = libc::pollfd {
+                fd,
+                events: 0,
+                revents: 0,
+            };
+            libc_result(unsafe { libc::poll(&mut pollfd, 1, 0) })?;
+
+            if pollfd.revents & libc::POLLHUP != 0 {
+                Ok(0)
+            } else {
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+        }
+        bytes_read => Ok(bytes_read as usize),
+    }
+}
+
+/// Writes as much of `buf` as fits in the output buffer without waiting.
+pub(super) fn write_nonblocking(fd: RawFd, buf: &[u8]) -> io::Result<usize> {
+    match unsafe { libc::write(fd, buf.as_ptr().cast(), buf.len()) } {
+        -1 => Err(std::io::Error::last_os_error()),
+        bytes_written => Ok(bytes_written as usize),
+    }
+}
+
+/// Waits until `fd` is ready for `events` or `timeout` elapses.
+fn wait_fd(fd: RawFd, events: libc::c_short, timeout: Duration) -> io::Result<()> {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events,
+        revents: 0,
+    };
+
+    loop {
+        match unsafe { libc::poll(&mut pollfd, 1, poll_timeout(timeout)) } {
+            -1 => {
+                let error = std::io::Error::last_os_error();
+                if error.kind() != io::ErrorKind::Interrupted {
+                    return Err(error);
+                }
+            }
+            0 => return Err(std::io::ErrorKind::TimedOut.into()),
+            _ => break,
+        }
+    }
+
+    if pollfd.revents & libc::POLLNVAL != 0 {
+        return Err(std::io::Error::from_raw_os_error(libc::EBADF));
+    }
+
+    Ok(())
+}
+
+/// A serial port backed by a POSIX terminal device such as `/dev/ttyUSB0`.
+///
+/// The device is opened in exclusive mode and configured in raw mode through
+/// termios. Read and write timeouts are implemented with `poll`, so a timeout
+/// of zero returns `TimedOut` immediately when no data is available.
+///
+/// Getters report what the driver actually applied. Note that Linux
+/// pseudo-terminals always use 8 data bits without parity, whatever was
+/// requested.
+///
+/// # Examples
+///
+/// Any terminal device works, including the slave side of a pseudo-terminal:
+///
+/// ```rust
+/// use std::io::{ErrorKind, Read, Write};
+/// use std::time::Duration;
+/// use serialport::config::StopBits;
+///
+/// # let master = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY) };
+/// # assert!(master >= 0);
+/// # assert_eq!(unsafe { libc::grantpt(master) }, 0);
+/// # assert_eq!(unsafe { libc::unlockpt(master) }, 0);
+/// # let mut name = [0 as libc::c_char; 64];
+/// # assert_eq!(unsafe { libc::ptsname_r(master, name.as_mut_ptr(), name.len()) }, 0);
+/// # let path = unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) }.to_string_lossy().into_owned();
+/// let mut port = serialport::new(path, 9600).build()?;
+/// assert!(port.is_open());
+///
+/// // Nothing was sent yet, so a zero timeout returns immediately
+/// let mut buffer = [0u8; 16];
+/// assert_eq!(port.read(&mut buffer).unwrap_err().kind(), ErrorKind::TimedOut);
+///
+/// // Settings can be changed while the port is open
+/// port.set_baud_rate(115200)?;
+/// port.set_stop_bits(StopBits::Two)?;
+/// assert_eq!(port.baud_rate()?, 115200);
+/// assert_eq!(port.stop_bits()?, StopBits::Two);
+///
+/// // Data written by the other end of the line becomes readable
+/// # assert_eq!(unsafe { libc::write(master, b"ping".as_ptr().cast(), 4) }, 4);
+/// port.set_timeout(Duration::from_millis(500))?;
+/// # std::thread::sleep(Duration::from_millis(50));
+/// assert_eq!(port.bytes_to_read()?, 4);
+/// assert_eq!(port.read(&mut buffer)?, 4);
+/// assert_eq!(&buffer[..4], b"ping");
+///
+/// // The port can be closed and reopened with the same settings
+/// port.close()?;
+/// assert_eq!(port.read(&mut buffer).unwrap_err().kind(), ErrorKind::NotConnected);
+/// port.open()?;
+/// assert_eq!(port.baud_rate()?, 115200);
+/// port.write_all(b"pong")?;
+/// # unsafe { libc::close(master) };
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct TTYPort {
+    is_open: bool,
+    fd: RawFd,
+    builder: SerialPortBuilder,
+    /// Emulated modem lines for ports created by `TTYPort::pair`
+    null_modem: Option<NullModem>,
+    /// Interrupt counters up to which line events have been reported
+    event_baseline: Option<SerialIcounter>,
+    /// How RTS is currently switched for RS-485
+    rs485: Rs485Mode,
+}
+
+/// Who switches RTS around transmissions in RS-485 mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rs485Mode {
+    Off,
+    Kernel,
+    Software,
+}
+
+impl TTYPort {
+    pub fn new(builder: SerialPortBuilder) -> io::Result<Self> {
+        let mut serialport = Self {
+            is_open: false,
+            fd: -1,
+            builder,
+            null_modem: None,
+            event_baseline: None,
+            rs485: Rs485Mode::Off,
+        };
+
+        if serialport.builder.has_device() {
+            serialport.open()?;
+        }
+
+        Ok(serialport)
+    }
+
+    /// Creates a pair of connected pseudo-terminal ports.
+    ///
+    /// Both ends are configured from `builder`; its path is ignored. The first
+    /// port is the pty master, which has no path and cannot be reopened once
+    /// closed. The second port is the slave, which can be closed and reopened
+    /// by its `/dev/pts/N` path for as long as the master is alive.
+    pub fn pair(builder: SerialPortBuilder) -> io::Result<(Self, Self)> {
+        let fd = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC) };
+
+        if fd == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let mut master = Self {
+            is_open: false,
+            fd,
+            builder: builder.path("".into()),
+            null_modem: None,
+            event_baseline: None,
+            rs485: Rs485Mode::Off,
+        };
+
+        libc_result(unsafe { libc::grantpt(master.fd) })?;
+        libc_result(unsafe { libc::unlockpt(master.fd) })?;
+        libc_result(unsafe { libc::fcntl(master.fd, libc::F_SETFL, libc::O_NONBLOCK) })?;
+
+        let path = ptsname(master.fd)?;
+
+        master.reconfigure()?;
+        master.is_open = true;
+
+        let mut slave = Self::new(master.builder.clone().path(path.into()))?;
+
+        let (master_end, slave_end) = NullModem::pair();
+        master.event_baseline = Some(master_end.icount());
+        master.null_modem = Some(master_end);
+        slave.event_baseline = Some(slave_end.icount());
+        slave.null_modem = Some(slave_end);
+
+        Ok((master, slave))
+    }
+
+    pub fn try_clone_native(&self) -> io::Result<Self> {
+        let fd = unsafe { libc::fcntl(self.fd, libc::F_DUPFD_CLOEXEC, 0) };
+
+        if fd == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            is_open: self.is_open,
+            fd,
+            builder: self.builder.clone(),
+            null_modem: self.null_modem.clone(),
+            event_baseline: self.event_baseline,
+            rs485: self.rs485,
+        })
+    }
+
+    fn reconfigure(&mut self) -> io::Result<()> {
+        if self.fd == -1 {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        let result = termios::PosixTermios::get(self.fd)?
+            .update(&self.builder)
+            .and_then(|termios| termios.set(self.fd))
+            .and_then(|_| self.configure_rs485());
+
+        // A port that is already open stays open, so that descriptors
+        // registered elsewhere remain valid; see `update`
+        if let Err(e) = result {
+            if !self.is_open {
+                let _ = self.release();
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Changes the settings and applies them to an open port. A rejected
+    /// setting is undone and the previous settings are applied again.
+    fn update(&mut self, change: impl FnOnce(&mut SerialPortBuilder)) -> io::Result<()> {
+        let previous = self.builder.clone();
+        change(&mut self.builder);
+
+        if self.is_open
+            && let Err(e) = self.reconfigure()
+        {
+            self.builder = previous;
+            let _ = self.reconfigure();
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Applies the RS-485 settings, falling back to switching RTS in
+    /// software when the driver has no RS-485 support.
+    fn configure_rs485(&mut self) -> io::Result<()> {
+        let config = self.builder.rs485;
+        let previous = self.rs485;
+
+        // Drivers put into RS-485 mode by someone else, e.g. from the device
+        // tree, are left alone unless RS-485 is requested
+        self.rs485 = if !config.enabled && previous != Rs485Mode::Kernel {
+            Rs485Mode::Off
+        } else {
+            match SerialRs485::new(&config).set(self.fd) {
+                Ok(()) if config.enabled => Rs485Mode::Kernel,
+                Err(e) if SerialRs485::is_unsupported(&e) && config.enabled => Rs485Mode::Software,
+                Err(e) if !SerialRs485::is_unsupported(&e) => return Err(e),
+                _ => Rs485Mode::Off,
+            }
+        };
+
+        if self.is_open {
+            if previous == Rs485Mode::Software && self.rs485 == Rs485Mode::Off {
+                self.write_modem_lines(libc::TIOCM_RTS, true)?;
+            }
+
+            self.release_bus()?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets RTS to its idle level when RS-485 is switched in software.
+    fn release_bus(&mut self) -> io::Result<()> {
+        match self.rs485 {
+            Rs485Mode::Software => {
+                self.write_modem_lines(libc::TIOCM_RTS, self.builder.rs485.rts_after_send)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads the data that is available, failing with `TimedOut` if there is
+    /// none.
+    fn read_some(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = unsafe { libc::read(self.fd, buf.as_mut_ptr().cast(), buf.len()) };
+
+        match bytes_read {
+            -1 => match std::io::Error::last_os_error() {
+                e if e.kind() == io::ErrorKind::WouldBlock => {
+                    Err(std::io::ErrorKind::TimedOut.into())
+                }
+                e => Err(e),
+            },
+            0 => Err(std::io::ErrorKind::TimedOut.into()),
+            _ => Ok(bytes_read as usize),
+        }
+    }
+
+    /// Writes as much of `buf` as the port accepts within the timeout.
+    fn write_some(&mut self, buf: &[u8]) -> io::Result<usize> {
+        wait_fd(self.fd, libc::POLLOUT, self.builder.write_timeout)?;
+
+        match write_nonblocking(self.fd, buf) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Err(std::io::ErrorKind::TimedOut.into())
+            }
+            result => result,
+        }
+    }
+
+    /// Sends all of `buf` with RTS switched around the transmission.
+    fn write_rs485(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let config = self.builder.rs485;
+
+        self.write_modem_lines(libc::TIOCM_RTS, config.rts_on_send)?;
+        std::thread::sleep(config.delay_before_send);
+
+        let mut result = Ok(());
+        let mut remaining = buf;
+
+        while !remaining.is_empty() {
+            match self.write_some(remaining) {
+                Ok(0) => result = Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => remaining = &remaining[n..],
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => result = Err(e),
+            }
+
+            if result.is_err() {
+                break;
+            }
+        }
+
+        // The bus must not be released before the last stop bit has left
+        let result = result.and_then(|_| libc_result(unsafe { libc::tcdrain(self.fd) }));
+
+        // Whatever was received meanwhile is the echo of our own data
+        if !config.rx_during_tx {
+            let _ = unsafe { libc::tcflush(self.fd, libc::TCIFLUSH) };
+        }
+
+        std::thread::sleep(config.delay_after_send);
+        self.release_bus()?;
+
+        result.map(|_| buf.len())
+    }
+
+    /// Returns the modem line state in `TIOCMGET` format.
+    fn modem_status(&self) -> io::Result<libc::c_int> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        if let Some(null_modem) = &self.null_modem {
+            return Ok(null_modem.get());
+        }
+
+        let mut bits: libc::c_int = 0;
+        libc_result(unsafe { libc::ioctl(self.fd, libc::TIOCMGET, &mut bits) })?;
+
+        Ok(bits)
+    }
+
+    /// Asserts or clears the given `TIOCM_*` output lines.
+    fn write_modem_lines(&mut self, bits: libc::c_int, level: bool) -> io::Result<()> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        if let Some(null_modem) = &self.null_modem {
+            null_modem.set(bits, level);
+            return Ok(());
+        }
+
+        let request = if level {
+            libc::TIOCMBIS
+        } else {
+            libc::TIOCMBIC
+        };
+
+        libc_result(unsafe { libc::ioctl(self.fd, request, &bits) })
+    }
+
+    /// Starts or ends a break condition with `TIOCSBRK` or `TIOCCBRK`.
+    fn write_break(&mut self, level: bool) -> io::Result<()> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        let request = if level {
+            libc::TIOCSBRK
+        } else {
+            libc::TIOCCBRK
+        };
+
+        libc_result(unsafe { libc::ioctl(self.fd, request) })?;
+
+        if let (true, Some(null_modem)) = (level, &self.null_modem) {
+            null_modem.set_break();
+        }
+
+        Ok(())
+    }
+
+    /// Returns the driver's interrupt counters.
+    fn icount(&self) -> io::Result<SerialIcounter> {
+        match &self.null_modem {
+            Some(null_modem) => Ok(null_modem.icount()),
+            None => SerialIcounter::get(self.fd),
+        }
+    }
+
+    /// Blocks until the interrupt counters may have moved past `seen`.
+    fn wait_icount(
+        &self,
+        seen: &SerialIcounter,
+        mask: LineEvents,
+        deadline: Option<Instant>,
+    ) -> io::Result<()> {
+        if let Some(null_modem) = &self.null_modem {
+            null_modem.wait(seen, deadline);
+            return Ok(());
+        }
+
+        // TIOCMIWAIT cannot time out and ignores line errors, so it is only
+        // used for unbounded waits on the modem lines
+        match deadline {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            None if LineEvents::MODEM_LINES.contains(mask) => {
+                let mut lines = 0;
+                if mask.intersects(LineEvents::CLEAR_TO_SEND) {
+                    lines |= libc::TIOCM_CTS;
+                }
+                if mask.intersects(LineEvents::DATA_SET_READY) {
+                    lines |= libc::TIOCM_DSR;
+                }
+                if mask.intersects(LineEvents::RING_INDICATOR) {
+                    lines |= libc::TIOCM_RNG;
+                }
+                if mask.intersects(LineEvents::CARRIER_DETECT) {
+                    lines |= libc::TIOCM_CAR;
+                }
+
+                libc_result(unsafe { libc::ioctl(self.fd, libc::TIOCMIWAIT, lines) })
+            }
+            _ => {
+                let remaining = deadline
+                    .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+                    .unwrap_or(EVENT_POLL_INTERVAL);
+
+                std::thread::sleep(Duration::min(remaining, EVENT_POLL_INTERVAL));
+                Ok(())
+            }
+        }
+    }
+
+    /// Closes the file descriptor regardless of the `is_open` flag, so a
+    /// descriptor that failed configuration during `open` is not leaked.
+    fn release(&mut self) -> io::Result<()> {
+        self.is_open = false;
+        self.event_baseline = None;
+
+        if self.fd != -1 {
+            let fd = std::mem::replace(&mut self.fd, -1);
+            libc_result(unsafe { libc::close(fd) })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Communication for TTYPort {
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        if self.is_open {
+            return Err(std::io::ErrorKind::AlreadyExists.into());
+        }
+
+        // A USB device may have come back under a different path
+        self.builder.resolve_usb()?;
+
+        if self.builder.path.is_empty() {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        }
+
+        let path = CString::new(self.builder.path.as_bytes())
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+
+        let fd = unsafe {
+            libc::open(
+                path.as_ptr(),
+                libc::O_RDWR | libc::O_NOCTTY | libc::O_NONBLOCK | libc::O_CLOEXEC,
+            )
+        };
+
+        if fd == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        self.fd = fd;
+
+        // Mirror the exclusive share mode used on Windows
+        if let Err(e) = libc_result(unsafe { libc::ioctl(self.fd, libc::TIOCEXCL) }) {
+            let _ = self.release();
+            return Err(e);
+        }
+
+        self.reconfigure()?;
+        self.is_open = true;
+
+        // Opening a real tty raises DTR and RTS
+        if let Some(null_modem) = &self.null_modem {
+            null_modem.set(libc::TIOCM_RTS | libc::TIOCM_DTR, true);
+        }
+
+        self.release_bus()?;
+
+        // Drivers without TIOCGICOUNT can still be used for everything else
+        self.event_baseline = self.icount().ok();
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        if !self.is_open {
+            return Ok(());
+        }
+
+        // Closing a real tty drops DTR and RTS (HUPCL)
+        if let Some(null_modem) = &self.null_modem {
+            null_modem.set(libc::TIOCM_RTS | libc::TIOCM_DTR, false);
+        }
+
+        self.release()
+    }
+}
+
+impl SerialPort for TTYPort {
+    fn try_clone(&self) -> io::Result<Box<dyn SerialPort>> {
+        self.try_clone_native()
+            .map(|port| Box::new(port) as Box<dyn SerialPort>)
+    }
+
+    fn path(&self) -> Option<String> {
+        if self.builder.path.is_empty() {
+            None
+        } else {
+            Some(self.builder.path.clone())
+        }
+    }
+
+    fn baud_rate(&self) -> io::Result<u32> {
+        termios::PosixTermios::get(self.fd)?.get_baud_rate()
+    }
+
+    fn data_bits(&self) -> io::Result<DataBits> {
+        termios::PosixTermios::get(self.fd)?.get_data_bits()
+    }
+
+    fn flow_control(&self) -> io::Result<FlowControl> {
+        Ok(termios::PosixTermios::get(self.fd)?.get_flow_control())
+    }
+
+    fn parity(&self) -> io::Result<Parity> {
+        Ok(termios::PosixTermios::get(self.fd)?.get_parity())
+    }
+
+    fn stop_bits(&self) -> io::Result<StopBits> {
+        Ok(termios::PosixTermios::get(self.fd)?.get_stop_bits())
+    }
+
+    fn timeout(&self) -> Duration {
+        self.builder.read_timeout
+    }
+
+    fn read_timeout(&self) -> Duration {
+        self.builder.read_timeout
+    }
+
+    fn write_timeout(&self) -> Duration {
+        self.builder.write_timeout
+    }
+
+    fn inter_byte_timeout(&self) -> Duration {
+        self.builder.inter_byte_timeout
+    }
+
+    fn rs485(&self) -> Rs485Config {
+        self.builder.rs485
+    }
+
+    fn bytes_to_read(&self) -> io::Result<u32> {
+        let mut bytes: libc::c_int = 0;
+
+        libc_result(unsafe { libc::ioctl(self.fd, libc::FIONREAD, &mut bytes) })?;
+
+        Ok(bytes as u32)
+    }
+
+    fn bytes_to_write(&self) -> io::Result<u32> {
+        let mut bytes: libc::c_int = 0;
+
+        libc_result(unsafe { libc::ioctl(self.fd, libc::TIOCOUTQ, &mut bytes) })?;
+
+        Ok(bytes as u32)
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> io::Result<()> {
+        self.update(|builder| builder.baud_rate = baud_rate)
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> io::Result<()> {
+        self.update(|builder| builder.data_bits = data_bits)
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> io::Result<()> {
+        self.update(|builder| builder.flow_control = flow_control)
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> io::Result<()> {
+        self.update(|builder| builder.parity = parity)
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> io::Result<()> {
+        self.update(|builder| builder.stop_bits = stop_bits)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.builder.read_timeout = timeout;
+        self.builder.write_timeout = timeout;
+
+        Ok(())
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.builder.read_timeout = timeout;
+
+        Ok(())
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.builder.write_timeout = timeout;
+
+        Ok(())
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.builder.inter_byte_timeout = timeout;
+
+        Ok(())
+    }
+
+    fn set_rs485(&mut self, rs485: Rs485Config) -> io::Result<()> {
+        self.update(|builder| builder.rs485 = rs485)
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> io::Result<()> {
+        let queue_selector = match buffer_to_clear {
+            ClearBuffer::Input => libc::TCIFLUSH,
+            ClearBuffer::Output => libc::TCOFLUSH,
+            ClearBuffer::All => libc::TCIOFLUSH,
+        };
+
+        libc_result(unsafe { libc::tcflush(self.fd, queue_selector) })
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> io::Result<()> {
+        self.write_modem_lines(libc::TIOCM_RTS, level)
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> io::Result<()> {
+        self.write_modem_lines(libc::TIOCM_DTR, level)
+    }
+
+    fn read_clear_to_send(&self) -> io::Result<bool> {
+        Ok(self.modem_status()? & libc::TIOCM_CTS != 0)
+    }
+
+    fn read_data_set_ready(&self) -> io::Result<bool> {
+        Ok(self.modem_status()? & libc::TIOCM_DSR != 0)
+    }
+
+    fn read_ring_indicator(&self) -> io::Result<bool> {
+        Ok(self.modem_status()? & libc::TIOCM_RNG != 0)
+    }
+
+    fn read_carrier_detect(&self) -> io::Result<bool> {
+        Ok(self.modem_status()? & libc::TIOCM_CAR != 0)
+    }
+
+    fn set_break(&mut self) -> io::Result<()> {
+        self.write_break(true)
+    }
+
+    fn clear_break(&mut self) -> io::Result<()> {
+        self.write_break(false)
+    }
+
+    fn send_break(&mut self, duration: Duration) -> io::Result<()> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        // tcsendbreak only supports the default duration or, on Linux,
+        // multiples of 100ms, which is too coarse for e.g. DMX512 or LIN
+        if duration.is_zero() {
+            libc_result(unsafe { libc::tcsendbreak(self.fd, 0) })?;
+
+            if let Some(null_modem) = &self.null_modem {
+                null_modem.set_break();
+            }
+
+            return Ok(());
+        }
+
+        libc_result(unsafe { libc::tcdrain(self.fd) })?;
+        self.write_break(true)?;
+        std::thread::sleep(duration);
+        self.write_break(false)
+    }
+
+    fn wait_for_event(&mut self, mask: LineEvents, timeout: Duration) -> io::Result<LineEvents> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        let deadline = Instant::now().checked_add(timeout);
+        let mut baseline = match self.event_baseline {
+            Some(baseline) => baseline,
+            None => self.icount()?,
+        };
+        self.event_baseline = Some(baseline);
+
+        loop {
+            let counters = self.icount()?;
+            let events = baseline.changes(&counters) & mask;
+
+            if !events.is_empty() {
+                baseline.consume(&counters, events);
+                self.event_baseline = Some(baseline);
+                return Ok(events);
+            }
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(std::io::ErrorKind::TimedOut.into());
+            }
+
+            self.wait_icount(&counters, mask, deadline)?;
+        }
+    }
+}
+
+impl private::Private for TTYPort {
+    fn set_raw_path<'a>(&mut self, path: std::borrow::Cow<'a, str>) -> io::Result<()> {
+        let path = path.into_owned();
+
+        // A different device is no longer wired to the other end of the pair
+        if path != self.builder.path {
+            self.null_modem = None;
+        }
+
+        self.builder.path = path;
+        self.builder.usb = None;
+        Ok(())
+    }
+}
+
+impl std::io::Read for TTYPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        let read_timeout = self.builder.read_timeout;
+        let deadline = Instant::now().checked_add(read_timeout);
+
+        wait_fd(self.fd, libc::POLLIN, read_timeout)?;
+        let mut bytes_read = self.read_some(buf)?;
+
+        let inter_byte_timeout = self.builder.inter_byte_timeout;
+        if inter_byte_timeout.is_zero() || read_timeout.is_zero() {
+            return Ok(bytes_read);
+        }
+
+        // Keep reading until the line goes idle, the buffer is full or the
+        // read timeout runs out. Data already received is returned even if a
+        // later wait fails.
+        while bytes_read < buf.len() {
+            let idle = match deadline {
+                Some(deadline) => {
+                    inter_byte_timeout.min(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => inter_byte_timeout,
+            };
+
+            if idle.is_zero() || wait_fd(self.fd, libc::POLLIN, idle).is_err() {
+                break;
+            }
+
+            match self.read_some(&mut buf[bytes_read..]) {
+                Ok(n) => bytes_read += n,
+                Err(_) => break,
+            }
+        }
+
+        Ok(bytes_read)
+    }
+}
+
+impl std::io::Write for TTYPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        match self.rs485 {
+            Rs485Mode::Software => self.write_rs485(buf),
+            _ => self.write_some(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        libc_result(unsafe { libc::tcdrain(self.fd) })
+    }
+}
+
+impl AsRawFd for TTYPort {
+    /// Returns the file descriptor of the port, which is -1 while the port
+    /// is closed. Reopening the port gives it a new descriptor.
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl AsFd for TTYPort {
+    /// Borrows the file descriptor of the port.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the port is closed.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        assert!(self.fd != -1, "as_fd() called on a closed port");
+
+        // The descriptor stays open for as long as the port is borrowed
+        unsafe { BorrowedFd::borrow_raw(self.fd) }
+    }
+}
+
+/// Registers the port with a [`mio::Poll`], next to sockets and other
+/// sources.
+///
+/// The descriptor is already non-blocking. Reads and writes still go through
+/// the port's timeout, so with a timeout of zero a drained port returns
+/// `TimedOut` where mio sources usually return `WouldBlock`. Closing the port
+/// removes it from the poll; it must be registered again after reopening.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::io::{Read, Write};
+/// use std::time::Duration;
+/// use mio::{Events, Interest, Poll, Token};
+/// use serialport::{SerialPortBuilder, TTYPort};
+///
+/// let builder = SerialPortBuilder::new().timeout(Duration::from_millis(100));
+/// let (mut master, mut slave) = TTYPort::pair(builder)?;
+///
+/// let mut poll = Poll::new()?;
+/// poll.registry().register(&mut slave, Token(0), Interest::READABLE)?;
+///
+/// master.write_all(b"ping")?;
+///
+/// let mut events = Events::with_capacity(8);
+/// poll.poll(&mut events, Some(Duration::from_secs(1)))?;
+/// assert!(events.iter().any(|event| event.token() == Token(0) && event.is_readable()));
+///
+/// let mut buffer = [0u8; 4];
+/// slave.read_exact(&mut buffer)?;
+/// assert_eq!(&buffer, b"ping");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "mio")]
+impl mio::event::Source for TTYPort {
+    fn register(
+        &mut self,
+        registry: &mio::Registry,
+        token: mio::Token,
+        interests: mio::Interest,
+    ) -> io::Result<()> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        mio::unix::SourceFd(&self.fd).register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &mio::Registry,
+        token: mio::Token,
+        interests: mio::Interest,
+    ) -> io::Result<()> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        mio::unix::SourceFd(&self.fd).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &mio::Registry) -> io::Result<()> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        mio::unix::SourceFd(&self.fd).deregister(registry)
+    }
+}
+
+impl Drop for TTYPort {
+    fn drop(&mut self) {
+        let _ = self.release();
+    }
+}
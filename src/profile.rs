@@ -0,0 +1,555 @@
+//! Declarative port configuration, available with the `serde` feature.
+//!
+//! [`SerialPortBuilder`] can be serialized and deserialized with any serde
+//! format. Every field is optional when deserializing; missing fields keep
+//! the defaults of [`SerialPortBuilder::new`], and unknown fields, such as a
+//! misspelled `baudrate`, are rejected. The fields are:
+//!
+//! - `path`: the device path
+//! - `baud_rate`: the baud rate
+//! - `data_bits`: `"Five"`, `"Six"`, `"Seven"` or `"Eight"`
+//! - `parity`: `"None"`, `"Odd"`, `"Even"`, `"Mark"` or `"Space"`
+//! - `stop_bits`: `"One"`, `"OnePointFive"` or `"Two"`
+//! - `flow_control`: `"None"`, `"Software"` or `"Hardware"`
+//! - `timeout`: sets both the read and the write timeout
+//! - `read_timeout`, `write_timeout` and `inter_byte_timeout`: override
+//!   `timeout` for one direction, or set the inter-byte timeout
+//! - `rs485`: the fields of [`Rs485Config`](crate::config::Rs485Config)
+//!
+//! Durations are written as a number followed by a unit, such as `"500ms"`,
+//! `"1.5s"` or `"2min"`. The units are `ns`, `us`, `ms`, `s`, `min` and `h`,
+//! in lower case; a bare `m` is rejected rather than guessed at.
+//!
+//! A USB device selected with [`SerialPortBuilder::usb_device`] is not part
+//! of a serialized builder. A [`PortProfile`] describes a port the same way
+//! as a builder, but includes the USB device to find it by.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use serialport::SerialPortBuilder;
+//! use serialport::config::Parity;
+//!
+//! let builder: SerialPortBuilder = toml::from_str(r#"
+//!     path = "/dev/ttyUSB0"
+//!     baud_rate = 115200
+//!     parity = "Even"
+//!     timeout = "500ms"
+//! "#).unwrap();
+//!
+//! assert_eq!(
+//!     builder,
+//!     serialport::new("/dev/ttyUSB0", 115200)
+//!         .parity(Parity::Even)
+//!         .timeout(Duration::from_millis(500))
+//! );
+//!
+//! // Serialized builders list both timeouts
+//! let text = toml::to_string(&builder).unwrap();
+//! assert!(text.contains(r#"write_timeout = "500ms""#));
+//! assert_eq!(toml::from_str::<SerialPortBuilder>(&text).unwrap(), builder);
+//!
+//! assert!(toml::from_str::<SerialPortBuilder>("timeout = 500").is_err());
+//! ```
+
+use std::io;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    SerialPort, SerialPortBuilder,
+    config::{DataBits, FlowControl, Parity, Rs485Config, StopBits, UsbFilter},
+};
+
+/// A serial port described by its settings and, optionally, the USB device
+/// it belongs to.
+///
+/// A profile is written like a [`SerialPortBuilder`], with an additional
+/// `usb` table holding a [`UsbFilter`]. When it is present, the port is
+/// looked up with `available_ports` each time it is opened, and `path` is
+/// ignored.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use serialport::config::UsbFilter;
+/// use serialport::profile::PortProfile;
+///
+/// let profile: PortProfile = serde_json::from_str(r#"{
+///     "usb": { "vid": "0403", "pid": "6001", "serial_number": "A5XK3RJT" },
+///     "baud_rate": 19200,
+///     "timeout": "1s"
+/// }"#).unwrap();
+///
+/// assert_eq!(
+///     profile.usb,
+///     Some(UsbFilter::new(0x0403, 0x6001).serial_number("A5XK3RJT"))
+/// );
+/// assert_eq!(
+///     profile.settings,
+///     serialport::new("", 19200).timeout(Duration::from_secs(1))
+/// );
+///
+/// // Find the adapter and open it
+/// # if false {
+/// let port = profile.build()?;
+/// # }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "BuilderConfig")]
+pub struct PortProfile {
+    /// The USB device to use instead of `settings.path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usb: Option<UsbFilter>,
+    /// The settings of the port
+    #[serde(flatten)]
+    pub settings: SerialPortBuilder,
+}
+
+impl PortProfile {
+    /// Creates a profile with the settings of the builder, including the USB
+    /// device selected with [`SerialPortBuilder::usb_device`].
+    pub fn new(mut settings: SerialPortBuilder) -> Self {
+        Self {
+            usb: settings.usb.take(),
+            settings,
+        }
+    }
+
+    /// Selects the port by its USB identifiers instead of its path.
+    #[must_use]
+    pub fn usb(mut self, filter: UsbFilter) -> Self {
+        self.usb = Some(filter);
+        self
+    }
+
+    /// Finds the path of the port.
+    ///
+    /// # Returns
+    ///
+    /// Returns the builder's path if the profile has no USB filter, and
+    /// otherwise the result of [`UsbFilter::find`].
+    pub fn resolve(&self) -> io::Result<String> {
+        match &self.usb {
+            Some(filter) => filter.find(),
+            None => Ok(self.settings.path.clone()),
+        }
+    }
+
+    /// Returns a builder with the settings of the profile. If the profile
+    /// has a USB filter, the port it opens looks the device up each time it
+    /// is opened.
+    pub fn builder(&self) -> SerialPortBuilder {
+        let mut builder = self.settings.clone();
+
+        if self.usb.is_some() {
+            builder.usb = self.usb.clone();
+        }
+
+        builder
+    }
+
+    /// Finds the port and opens it with the settings of the profile.
+    pub fn build(&self) -> io::Result<Box<dyn SerialPort>> {
+        self.builder().build()
+    }
+}
+
+impl From<SerialPortBuilder> for PortProfile {
+    fn from(settings: SerialPortBuilder) -> Self {
+        Self::new(settings)
+    }
+}
+
+/// The deserialized form of a `SerialPortBuilder` and a `PortProfile`,
+/// where every field is optional and `timeout` sets both timeouts. Both are
+/// read through this struct rather than with `flatten`, which would let
+/// misspelled fields through as defaults.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BuilderConfig {
+    #[serde(default)]
+    usb: Option<UsbFilter>,
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    baud_rate: Option<u32>,
+    #[serde(default)]
+    data_bits: Option<DataBits>,
+    #[serde(default)]
+    flow_control: Option<FlowControl>,
+    #[serde(default)]
+    parity: Option<Parity>,
+    #[serde(default)]
+    stop_bits: Option<StopBits>,
+    #[serde(default, deserialize_with = "duration::deserialize_option")]
+    timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "duration::deserialize_option")]
+    read_timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "duration::deserialize_option")]
+    write_timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "duration::deserialize_option")]
+    inter_byte_timeout: Option<Duration>,
+    #[serde(default)]
+    rs485: Option<Rs485Config>,
+}
+
+impl From<BuilderConfig> for PortProfile {
+    fn from(mut config: BuilderConfig) -> Self {
+        Self {
+            usb: config.usb.take(),
+            settings: config.into_builder(),
+        }
+    }
+}
+
+impl TryFrom<BuilderConfig> for SerialPortBuilder {
+    type Error = &'static str;
+
+    fn try_from(config: BuilderConfig) -> Result<Self, Self::Error> {
+        match config.usb {
+            Some(_) => Err("a USB device can only be selected in a `PortProfile`"),
+            None => Ok(config.into_builder()),
+        }
+    }
+}
+
+impl BuilderConfig {
+    /// Fills in the missing settings with the defaults of
+    /// [`SerialPortBuilder::new`], leaving out the USB device.
+    fn into_builder(self) -> SerialPortBuilder {
+        let defaults = SerialPortBuilder::new();
+        let timeout = self.timeout.unwrap_or(defaults.read_timeout);
+
+        SerialPortBuilder {
+            path: self.path.unwrap_or(defaults.path),
+            baud_rate: self.baud_rate.unwrap_or(defaults.baud_rate),
+            data_bits: self.data_bits.unwrap_or(defaults.data_bits),
+            flow_control: self.flow_control.unwrap_or(defaults.flow_control),
+            parity: self.parity.unwrap_or(defaults.parity),
+            stop_bits: self.stop_bits.unwrap_or(defaults.stop_bits),
+            read_timeout: self.read_timeout.unwrap_or(timeout),
+            write_timeout: self.write_timeout.unwrap_or(timeout),
+            inter_byte_timeout: self
+                .inter_byte_timeout
+                .unwrap_or(defaults.inter_byte_timeout),
+            rs485: self.rs485.unwrap_or(defaults.rs485),
+            usb: None,
+        }
+    }
+}
+
+/// Serializes USB vendor and product IDs as hexadecimal strings such as
+/// `"0403"`, and accepts either such a string, optionally prefixed with
+/// `0x`, or a number.
+pub(crate) mod usb_id {
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    pub fn serialize<S: Serializer>(id: &u16, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{:04x}", id))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u16, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Id {
+            Number(u16),
+            Hex(String),
+        }
+
+        match Id::deserialize(deserializer)? {
+            Id::Number(id) => Ok(id),
+            Id::Hex(id) => {
+                let digits = id
+                    .strip_prefix("0x")
+                    .or_else(|| id.strip_prefix("0X"))
+                    .unwrap_or(&id);
+
+                let invalid = || {
+                    de::Error::invalid_value(
+                        de::Unexpected::Str(&id),
+                        &"a 16-bit hexadecimal USB ID",
+                    )
+                };
+
+                // `from_str_radix` would also take a sign
+                if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err(invalid());
+                }
+
+                u16::from_str_radix(digits, 16).map_err(|_| invalid())
+            }
+        }
+    }
+}
+
+/// Serializes durations as strings such as `"500ms"`, for use with
+/// `#[serde(with = "...")]`.
+pub(crate) mod duration {
+    use std::fmt;
+    use std::time::Duration;
+
+    use serde::{Deserializer, Serializer, de};
+
+    const UNITS: [(&str, u128); 6] = [
+        ("h", 3_600_000_000_000),
+        ("min", 60_000_000_000),
+        ("s", 1_000_000_000),
+        ("ms", 1_000_000),
+        ("us", 1_000),
+        ("ns", 1),
+    ];
+
+    /// Formats a duration in the largest unit that represents it exactly.
+    struct Format(Duration);
+
+    impl fmt::Display for Format {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let nanos = self.0.as_nanos();
+
+            if nanos == 0 {
+                return f.write_str("0s");
+            }
+
+            let (unit, scale) = UNITS
+                .into_iter()
+                .find(|(_, scale)| nanos.is_multiple_of(*scale))
+                .unwrap_or(("ns", 1));
+
+            write!(f, "{}{}", nanos / scale, unit)
+        }
+    }
+
+    fn parse(s: &str) -> Option<Duration> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let (number, unit) = (&s[..split], s[split..].trim_start());
+
+        let scale = match unit {
+            "h" => 3_600_000_000_000,
+            "min" => 60_000_000_000,
+            "s" => 1_000_000_000,
+            "ms" => 1_000_000,
+            "us" | "µs" => 1_000,
+            "ns" => 1,
+            _ => return None,
+        };
+
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+
+        if whole.is_empty() && fraction.is_empty() {
+            return None;
+        }
+
+        let mut nanos = if whole.is_empty() {
+            0
+        } else {
+            whole.parse::<u128>().ok()?.checked_mul(scale)?
+        };
+
+        // Digits beyond nanosecond precision are dropped
+        let mut place = scale;
+        for digit in fraction.chars() {
+            let digit = digit.to_digit(10)? as u128;
+            place /= 10;
+            nanos = nanos.checked_add(digit * place)?;
+        }
+
+        let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+        Some(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+    }
+
+    struct Visitor;
+
+    impl de::Visitor<'_> for Visitor {
+        type Value = Duration;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a duration such as \"500ms\" or \"2s\"")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Duration, E> {
+            parse(v).ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))
+        }
+    }
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&Format(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        deserializer.deserialize_str(Visitor)
+    }
+
+    pub fn deserialize_option<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        deserialize(deserializer).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde::de::{IntoDeserializer, value};
+
+    use super::{PortProfile, duration, usb_id};
+    use crate::{SerialPortBuilder, config::UsbFilter};
+
+    fn parse_duration(s: &str) -> Result<Duration, value::Error> {
+        duration::deserialize(s.into_deserializer())
+    }
+
+    fn parse_usb_id(s: &str) -> Result<u16, value::Error> {
+        usb_id::deserialize(s.into_deserializer())
+    }
+
+    #[test]
+    fn duration_units() {
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("2min").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("20us").unwrap(), Duration::from_micros(20));
+        assert_eq!(parse_duration("20µs").unwrap(), Duration::from_micros(20));
+        assert_eq!(parse_duration("7ns").unwrap(), Duration::from_nanos(7));
+        assert_eq!(
+            parse_duration(" 10 ms ").unwrap(),
+            Duration::from_millis(10)
+        );
+        assert_eq!(parse_duration(".25s").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("0s").unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn duration_fraction_beyond_nanoseconds() {
+        assert_eq!(
+            parse_duration("1.0000000019s").unwrap(),
+            Duration::new(1, 1)
+        );
+    }
+
+    #[test]
+    fn duration_empty() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("   ").is_err());
+        assert!(parse_duration("ms").is_err());
+        assert!(parse_duration(".ms").is_err());
+    }
+
+    #[test]
+    fn duration_missing_unit() {
+        assert!(parse_duration("500").is_err());
+        assert!(parse_duration("1.5").is_err());
+    }
+
+    #[test]
+    fn duration_unknown_unit() {
+        // A bare `m` could mean minutes or milliseconds
+        assert!(parse_duration("5m").is_err());
+        assert!(parse_duration("5sec").is_err());
+        assert!(parse_duration("-5s").is_err());
+        assert!(parse_duration("1.2.3s").is_err());
+    }
+
+    #[test]
+    fn duration_mixed_case() {
+        assert!(parse_duration("500MS").is_err());
+        assert!(parse_duration("2Min").is_err());
+        assert!(parse_duration("1S").is_err());
+    }
+
+    #[test]
+    fn duration_overflow() {
+        assert_eq!(
+            parse_duration("18446744073709551615s").unwrap(),
+            Duration::from_secs(u64::MAX)
+        );
+        assert!(parse_duration("18446744073709551616s").is_err());
+        assert!(parse_duration("99999999999999999999999999999999999999999h").is_err());
+        assert!(parse_duration("999999999999999999999999999999999999999999ns").is_err());
+    }
+
+    #[test]
+    fn duration_fraction_overflow() {
+        assert!(parse_duration("340282366920938463463374607431.999999999s").is_err());
+        assert!(parse_duration("18446744073709551615.999999999s").is_ok());
+    }
+
+    #[test]
+    fn builder_unknown_field() {
+        let err = toml::from_str::<SerialPortBuilder>("baudrate = 115200").unwrap_err();
+        assert!(err.to_string().contains("unknown field `baudrate`"));
+
+        let err = toml::from_str::<SerialPortBuilder>("[rs485]\nenable = true").unwrap_err();
+        assert!(err.to_string().contains("unknown field `enable`"));
+    }
+
+    #[test]
+    fn builder_usb_device() {
+        let text = "baud_rate = 115200\n[usb]\nvid = \"0403\"\npid = \"6001\"";
+        assert!(toml::from_str::<SerialPortBuilder>(text).is_err());
+
+        let profile = toml::from_str::<PortProfile>(text).unwrap();
+        assert_eq!(profile.usb, Some(UsbFilter::new(0x0403, 0x6001)));
+        assert_eq!(profile.settings, crate::new("", 115200));
+    }
+
+    #[test]
+    fn profile_unknown_field() {
+        let err = toml::from_str::<PortProfile>("baudrate = 115200").unwrap_err();
+        assert!(err.to_string().contains("unknown field `baudrate`"));
+
+        let err =
+            toml::from_str::<PortProfile>("[usb]\nvid = 1\npid = 2\nserial = \"A\"").unwrap_err();
+        assert!(err.to_string().contains("unknown field `serial`"));
+    }
+
+    #[test]
+    fn usb_id_hex_and_decimal() {
+        assert_eq!(parse_usb_id("0403").unwrap(), 0x0403);
+        assert_eq!(parse_usb_id("0x0403").unwrap(), 0x0403);
+        assert_eq!(parse_usb_id("0X0403").unwrap(), 0x0403);
+        assert_eq!(parse_usb_id("ffff").unwrap(), 0xFFFF);
+
+        let number: Result<u16, value::Error> = usb_id::deserialize(1027u16.into_deserializer());
+        assert_eq!(number.unwrap(), 1027);
+    }
+
+    #[test]
+    fn usb_id_mixed_case() {
+        assert_eq!(parse_usb_id("10C4").unwrap(), 0x10C4);
+        assert_eq!(parse_usb_id("0x10c4").unwrap(), 0x10C4);
+        assert_eq!(parse_usb_id("0xEa60").unwrap(), 0xEA60);
+    }
+
+    #[test]
+    fn usb_id_empty() {
+        assert!(parse_usb_id("").is_err());
+        assert!(parse_usb_id("0x").is_err());
+    }
+
+    #[test]
+    fn usb_id_overflow() {
+        assert!(parse_usb_id("10000").is_err());
+        assert!(parse_usb_id("0x1ffff").is_err());
+
+        let number: Result<u16, value::Error> = usb_id::deserialize(65536u32.into_deserializer());
+        assert!(number.is_err());
+    }
+
+    #[test]
+    fn usb_id_invalid() {
+        assert!(parse_usb_id("0x04g3").is_err());
+        assert!(parse_usb_id("+403").is_err());
+        assert!(parse_usb_id("0x+403").is_err());
+        assert!(parse_usb_id(" 0403").is_err());
+    }
+}
@@ -0,0 +1,548 @@
+//! A serial port that reopens its device after losing it.
+//!
+//! USB serial adapters disappear when they are unplugged, reset or
+//! re-enumerated, and come back later, often under a different path. A
+//! [`ReconnectingPort`] wraps any `SerialPort` and, when an operation fails
+//! in a way that shows the device is gone, closes the port, waits, reopens it
+//! and retries the operation. Opening the port looks the device up again, so
+//! a port selected with [`SerialPortBuilder::usb_device`] follows the adapter
+//! to its new path.
+//!
+//! [`SerialPortBuilder::usb_device`]: crate::SerialPortBuilder::usb_device
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use std::io::Read;
+//! use serialport::SerialPortBuilder;
+//! use serialport::reconnect::{ConnectionState, ReconnectingPort};
+//!
+//! let port = SerialPortBuilder::new()
+//!     .usb_device(0x0403, 0x6001)
+//!     .baud_rate(115200)
+//!     .build()?;
+//!
+//! let mut port = ReconnectingPort::new(port).on_state_change(|state| match state {
+//!     ConnectionState::Reconnecting => eprintln!("Device lost, reconnecting"),
+//!     ConnectionState::Connected => eprintln!("Device connected"),
+//!     ConnectionState::Closed => eprintln!("Port closed"),
+//! });
+//!
+//! // Reads wait for the adapter to come back if it is unplugged
+//! let mut buffer = [0u8; 64];
+//! let n = port.read(&mut buffer)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::io;
+use std::time::Duration;
+
+use crate::{
+    SerialPort,
+    communication::Communication,
+    config::{ClearBuffer, DataBits, FlowControl, LineEvents, Parity, Rs485Config, StopBits},
+    private,
+};
+
+/// The connection state reported by a [`ReconnectingPort`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The port is open
+    Connected,
+    /// The device was lost and the port is trying to reopen it
+    Reconnecting,
+    /// The port was closed, or reconnecting gave up
+    Closed,
+}
+
+/// How a [`ReconnectingPort`] waits between attempts to reopen its device.
+///
+/// The first attempt is made after `initial_delay`, and the delay doubles
+/// after each failed attempt up to `max_delay`.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use serialport::reconnect::Backoff;
+///
+/// let backoff = Backoff {
+///     max_attempts: Some(10),
+///     ..Backoff::default()
+/// };
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Backoff {
+    /// Time to wait before the first attempt
+    pub initial_delay: Duration,
+    /// Longest time to wait between attempts
+    pub max_delay: Duration,
+    /// Number of attempts before giving up, or `None` to keep trying
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    /// Returns a backoff that starts at 100 ms, waits at most 5 s between
+    /// attempts and never gives up.
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            max_attempts: None,
+        }
+    }
+}
+
+/// A serial port that transparently reopens its device when it is lost.
+///
+/// Reads, writes and settings changes that fail because the device has gone
+/// away close the port, reopen it with the [`Backoff`] and are then retried.
+/// Opening the port reapplies all of its settings, and the wrapper restores
+/// the RTS and DTR levels that were last set. Errors are only returned once
+/// reconnecting gives up, after which the port stays closed until it is
+/// opened again. Methods that take `&self`, such as `bytes_to_read`, return
+/// their errors without reconnecting.
+///
+/// The device counts as lost when an operation fails with an error such as
+/// `BrokenPipe`, `NotConnected` or `EIO`. Linux reports a hung-up tty as end
+/// of file, which reads report as `TimedOut`, so a timed out read also checks
+/// whether the port still responds.
+///
+/// Connection state changes are reported to the callback set with
+/// [`on_state_change`](Self::on_state_change), which can forward them to a
+/// channel.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::io::Write;
+/// use std::sync::mpsc;
+/// use std::time::Duration;
+/// use serialport::SerialPort;
+/// use serialport::mock::{MockCall, MockPort};
+/// use serialport::reconnect::{Backoff, ConnectionState, ReconnectingPort};
+///
+/// let mock = MockPort::new(serialport::new("mock", 9600));
+/// let handle = mock.handle();
+///
+/// let (states, changes) = mpsc::channel();
+/// let mut port = ReconnectingPort::new(Box::new(mock))
+///     .backoff(Backoff {
+///         initial_delay: Duration::from_millis(10),
+///         ..Backoff::default()
+///     })
+///     .on_state_change(move |state| {
+///         let _ = states.send(state);
+///     });
+///
+/// port.write_data_terminal_ready(true)?;
+///
+/// // The device disappears for a moment
+/// handle.unplug();
+/// let replug = handle.clone();
+/// let replug = std::thread::spawn(move || {
+///     std::thread::sleep(Duration::from_millis(50));
+///     replug.plug();
+/// });
+///
+/// // The write waits for the device to come back
+/// port.write_all(b"ping")?;
+/// replug.join().unwrap();
+///
+/// assert_eq!(handle.written(), b"ping");
+/// assert_eq!(
+///     changes.try_iter().collect::<Vec<_>>(),
+///     [ConnectionState::Reconnecting, ConnectionState::Connected]
+/// );
+///
+/// // DTR was restored after reopening the port
+/// assert_eq!(
+///     handle.calls().last(),
+///     Some(&MockCall::WriteDataTerminalReady(true))
+/// );
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct ReconnectingPort {
+    port: Box<dyn SerialPort>,
+    backoff: Backoff,
+    state: ConnectionState,
+    /// RTS level to restore after reconnecting
+    request_to_send: Option<bool>,
+    /// DTR level to restore after reconnecting
+    data_terminal_ready: Option<bool>,
+    on_state_change: Option<Box<dyn FnMut(ConnectionState) + Send>>,
+}
+
+impl ReconnectingPort {
+    /// Wraps a port. The port starts out connected if it is open.
+    pub fn new(port: Box<dyn SerialPort>) -> Self {
+        Self {
+            state: match port.is_open() {
+                true => ConnectionState::Connected,
+                false => ConnectionState::Closed,
+            },
+            port,
+            backoff: Backoff::default(),
+            request_to_send: None,
+            data_terminal_ready: None,
+            on_state_change: None,
+        }
+    }
+
+    /// Sets how to wait between attempts to reopen the device.
+    #[must_use]
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets a callback that is called with the new state whenever the
+    /// connection state changes.
+    ///
+    /// The callback runs on the thread that uses the port, in the middle of
+    /// the operation that changed the state, so it should return quickly.
+    #[must_use]
+    pub fn on_state_change(
+        mut self,
+        callback: impl FnMut(ConnectionState) + Send + 'static,
+    ) -> Self {
+        self.on_state_change = Some(Box::new(callback));
+        self
+    }
+
+    /// Returns the current connection state.
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// Returns the wrapped port.
+    pub fn into_inner(self) -> Box<dyn SerialPort> {
+        self.port
+    }
+
+    /// Closes the port and reopens it, waiting between attempts as
+    /// configured by the [`Backoff`].
+    ///
+    /// This also opens a port that is closed, for example to wait for a
+    /// device that is not attached yet.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` once the port is open again, or the error of the last
+    /// attempt if reconnecting gave up, in which case the port is closed.
+    pub fn reconnect(&mut self) -> io::Result<()> {
+        self.set_state(ConnectionState::Reconnecting);
+        let _ = self.port.close();
+
+        let mut delay = self.backoff.initial_delay;
+        let mut attempts = 0;
+
+        loop {
+            std::thread::sleep(delay);
+            attempts += 1;
+
+            match self.port.open().and_then(|_| self.restore_lines()) {
+                Ok(()) => {
+                    self.set_state(ConnectionState::Connected);
+                    return Ok(());
+                }
+                Err(e) => {
+                    let _ = self.port.close();
+
+                    if self
+                        .backoff
+                        .max_attempts
+                        .is_some_and(|max_attempts| attempts >= max_attempts)
+                    {
+                        self.set_state(ConnectionState::Closed);
+                        return Err(e);
+                    }
+
+                    delay = Duration::min(delay.saturating_mul(2), self.backoff.max_delay);
+                }
+            }
+        }
+    }
+
+    fn set_state(&mut self, state: ConnectionState) {
+        if self.state != state {
+            self.state = state;
+
+            if let Some(callback) = &mut self.on_state_change {
+                callback(state);
+            }
+        }
+    }
+
+    fn restore_lines(&mut self) -> io::Result<()> {
+        if let Some(level) = self.request_to_send {
+            self.port.write_request_to_send(level)?;
+        }
+
+        if let Some(level) = self.data_terminal_ready {
+            self.port.write_data_terminal_ready(level)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether `error` shows that the device is gone.
+    fn is_lost(&self, error: &io::Error) -> bool {
+        if self.state != ConnectionState::Connected {
+            return false;
+        }
+
+        if error.kind() == io::ErrorKind::TimedOut {
+            return self.port.bytes_to_read().is_err_and(|e| is_device_lost(&e));
+        }
+
+        is_device_lost(error)
+    }
+
+    /// Runs `operation`, reconnecting and retrying it while it fails because
+    /// the device is lost.
+    fn retry<T>(
+        &mut self,
+        mut operation: impl FnMut(&mut dyn SerialPort) -> io::Result<T>,
+    ) -> io::Result<T> {
+        loop {
+            match operation(&mut *self.port) {
+                Err(e) if self.is_lost(&e) => self.reconnect()?,
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Returns whether an I/O error means that the device has disappeared.
+pub(crate) fn is_device_lost(error: &io::Error) -> bool {
+    use io::ErrorKind::*;
+
+    if matches!(
+        error.kind(),
+        BrokenPipe | NotConnected | NotFound | ConnectionReset | ConnectionAborted | UnexpectedEof
+    ) {
+        return true;
+    }
+
+    #[cfg(unix)]
+    return matches!(
+        error.raw_os_error(),
+        Some(libc::EIO | libc::ENXIO | libc::ENODEV | libc::EBADF)
+    );
+
+    #[cfg(windows)]
+    {
+        use winapi::shared::winerror::{
+            ERROR_ACCESS_DENIED, ERROR_BAD_COMMAND, ERROR_DEVICE_NOT_CONNECTED,
+            ERROR_FILE_NOT_FOUND, ERROR_GEN_FAILURE, ERROR_OPERATION_ABORTED,
+        };
+
+        return matches!(
+            error.raw_os_error().map(|code| code as u32),
+            Some(
+                ERROR_ACCESS_DENIED
+                    | ERROR_BAD_COMMAND
+                    | ERROR_DEVICE_NOT_CONNECTED
+                    | ERROR_FILE_NOT_FOUND
+                    | ERROR_GEN_FAILURE
+                    | ERROR_OPERATION_ABORTED
+            )
+        );
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    false
+}
+
+impl Communication for ReconnectingPort {
+    fn is_open(&self) -> bool {
+        self.port.is_open()
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        self.port.open()?;
+
+        if let Err(e) = self.restore_lines() {
+            let _ = self.port.close();
+            return Err(e);
+        }
+
+        self.set_state(ConnectionState::Connected);
+        Ok(())
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.set_state(ConnectionState::Closed);
+        self.port.close()
+    }
+}
+
+impl SerialPort for ReconnectingPort {
+    /// Returns a handle to the same device that does not reconnect.
+    fn try_clone(&self) -> io::Result<Box<dyn SerialPort>> {
+        self.port.try_clone()
+    }
+
+    fn path(&self) -> Option<String> {
+        self.port.path()
+    }
+
+    fn baud_rate(&self) -> io::Result<u32> {
+        self.port.baud_rate()
+    }
+
+    fn data_bits(&self) -> io::Result<DataBits> {
+        self.port.data_bits()
+    }
+
+    fn flow_control(&self) -> io::Result<FlowControl> {
+        self.port.flow_control()
+    }
+
+    fn parity(&self) -> io::Result<Parity> {
+        self.port.parity()
+    }
+
+    fn stop_bits(&self) -> io::Result<StopBits> {
+        self.port.stop_bits()
+    }
+
+    fn timeout(&self) -> Duration {
+        self.port.timeout()
+    }
+
+    fn read_timeout(&self) -> Duration {
+        self.port.read_timeout()
+    }
+
+    fn write_timeout(&self) -> Duration {
+        self.port.write_timeout()
+    }
+
+    fn inter_byte_timeout(&self) -> Duration {
+        self.port.inter_byte_timeout()
+    }
+
+    fn rs485(&self) -> Rs485Config {
+        self.port.rs485()
+    }
+
+    fn bytes_to_read(&self) -> io::Result<u32> {
+        self.port.bytes_to_read()
+    }
+
+    fn bytes_to_write(&self) -> io::Result<u32> {
+        self.port.bytes_to_write()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> io::Result<()> {
+        self.retry(|port| port.set_baud_rate(baud_rate))
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> io::Result<()> {
+        self.retry(|port| port.set_data_bits(data_bits))
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> io::Result<()> {
+        self.retry(|port| port.set_flow_control(flow_control))
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> io::Result<()> {
+        self.retry(|port| port.set_parity(parity))
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> io::Result<()> {
+        self.retry(|port| port.set_stop_bits(stop_bits))
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.retry(|port| port.set_timeout(timeout))
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.retry(|port| port.set_read_timeout(timeout))
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.retry(|port| port.set_write_timeout(timeout))
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.retry(|port| port.set_inter_byte_timeout(timeout))
+    }
+
+    fn set_rs485(&mut self, rs485: Rs485Config) -> io::Result<()> {
+        self.retry(|port| port.set_rs485(rs485))
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> io::Result<()> {
+        self.port.clear(buffer_to_clear)
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> io::Result<()> {
+        self.retry(|port| port.write_request_to_send(level))?;
+        self.request_to_send = Some(level);
+        Ok(())
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> io::Result<()> {
+        self.retry(|port| port.write_data_terminal_ready(level))?;
+        self.data_terminal_ready = Some(level);
+        Ok(())
+    }
+
+    fn read_clear_to_send(&self) -> io::Result<bool> {
+        self.port.read_clear_to_send()
+    }
+
+    fn read_data_set_ready(&self) -> io::Result<bool> {
+        self.port.read_data_set_ready()
+    }
+
+    fn read_ring_indicator(&self) -> io::Result<bool> {
+        self.port.read_ring_indicator()
+    }
+
+    fn read_carrier_detect(&self) -> io::Result<bool> {
+        self.port.read_carrier_detect()
+    }
+
+    fn set_break(&mut self) -> io::Result<()> {
+        self.retry(|port| port.set_break())
+    }
+
+    fn clear_break(&mut self) -> io::Result<()> {
+        self.retry(|port| port.clear_break())
+    }
+
+    fn send_break(&mut self, duration: Duration) -> io::Result<()> {
+        self.retry(|port| port.send_break(duration))
+    }
+
+    fn wait_for_event(&mut self, mask: LineEvents, timeout: Duration) -> io::Result<LineEvents> {
+        self.retry(|port| port.wait_for_event(mask, timeout))
+    }
+}
+
+impl private::Private for ReconnectingPort {
+    fn set_raw_path<'a>(&mut self, path: std::borrow::Cow<'a, str>) -> io::Result<()> {
+        self.port.set_raw_path(path)
+    }
+}
+
+impl io::Read for ReconnectingPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.retry(|port| port.read(buf))
+    }
+}
+
+impl io::Write for ReconnectingPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.retry(|port| port.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.retry(|port| port.flush())
+    }
+}
@@ -0,0 +1,299 @@
+//! Independent reader and writer halves of a serial port.
+//!
+//! `SerialPort::split` turns a port into a [`ReadHalf`] and a
+//! [`WriteHalf`] that can be moved to different threads. Unlike handles
+//! obtained from `try_clone`, both halves share the open state of the one
+//! underlying port: closing the port through either half makes both return
+//! `NotConnected`, and reopening it through either half makes both usable
+//! again.
+//!
+//! Reads go through a clone of the port's handle, so that a read waiting for
+//! data does not hold up writes. Closing the port waits for a read in
+//! progress to return and closes that handle too, so the device is closed
+//! when `close` returns and can be opened again right away.
+
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{SerialPort, communication::Communication};
+
+struct Shared {
+    /// The split port, used for writing and for opening and closing
+    port: Mutex<Box<dyn SerialPort>>,
+    /// A clone of the port's handle, so that reads do not wait for writes.
+    /// It only exists while the port is open.
+    reader: Mutex<Option<Box<dyn SerialPort>>>,
+    open: AtomicBool,
+}
+
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Shared {
+    fn open(&self) -> io::Result<()> {
+        let mut port = lock(&self.port);
+        port.open()?;
+
+        match port.try_clone() {
+            Ok(reader) => *lock(&self.reader) = Some(reader),
+            Err(e) => {
+                let _ = port.close();
+                return Err(e);
+            }
+        }
+
+        self.open.store(true, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    fn close(&self) -> io::Result<()> {
+        let mut port = lock(&self.port);
+        self.open.store(false, Ordering::SeqCst);
+
+        // Waits for a read in progress, which holds the reader until its
+        // timeout at the latest
+        *lock(&self.reader) = None;
+
+        port.close()
+    }
+}
+
+/// The reading half of a port, created by `split`.
+pub struct ReadHalf {
+    shared: Arc<Shared>,
+}
+
+/// The writing half of a port, created by `split`.
+pub struct WriteHalf {
+    shared: Arc<Shared>,
+}
+
+impl dyn SerialPort {
+    /// Splits the port into halves for reading and writing.
+    ///
+    /// The halves can be used from different threads at the same time: a
+    /// read waiting for data does not hold up writes. They share the open
+    /// state of the port, so closing it through one half makes both fail
+    /// with `NotConnected` until it is reopened. Closing the port waits for
+    /// a read in progress to complete, or time out, first.
+    ///
+    /// The halves can be put back together with [`ReadHalf::unsplit`] to
+    /// access the rest of the port's settings.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the read and write halves, or an error
+    /// if the port handle could not be cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::io::{ErrorKind, Read, Write};
+    /// use std::time::Duration;
+    /// use serialport::communication::Communication;
+    ///
+    /// # #[cfg(unix)] {
+    /// let (mut device, mut port) = serialport::pair()?;
+    /// device.set_timeout(Duration::from_secs(1))?;
+    /// port.set_timeout(Duration::from_secs(1))?;
+    /// let (mut reader, mut writer) = port.split()?;
+    ///
+    /// let echo = std::thread::spawn(move || -> std::io::Result<_> {
+    ///     let mut buffer = [0u8; 4];
+    ///     device.read_exact(&mut buffer)?;
+    ///     device.write_all(&buffer)?;
+    ///     // Closing the other end would hang up the port
+    ///     Ok(device)
+    /// });
+    ///
+    /// writer.write_all(b"ping")?;
+    ///
+    /// let mut buffer = [0u8; 4];
+    /// reader.read_exact(&mut buffer)?;
+    /// assert_eq!(&buffer, b"ping");
+    /// let _device = echo.join().unwrap()?;
+    ///
+    /// // Closing the port through one half closes it for both
+    /// writer.close()?;
+    /// assert_eq!(reader.read(&mut buffer).unwrap_err().kind(), ErrorKind::NotConnected);
+    /// assert_eq!(writer.write(b"ping").unwrap_err().kind(), ErrorKind::NotConnected);
+    ///
+    /// let port = reader.unsplit(writer).unwrap();
+    /// assert!(!port.is_open());
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// A port closed during a read is closed once the read has returned,
+    /// and can be reopened at once:
+    ///
+    /// ```rust
+    /// use std::io::{ErrorKind, Read};
+    /// use std::time::{Duration, Instant};
+    /// use serialport::communication::Communication;
+    ///
+    /// # #[cfg(unix)] {
+    /// let (_device, mut port) = serialport::pair()?;
+    /// port.set_timeout(Duration::from_millis(300))?;
+    /// let (mut reader, mut writer) = port.split()?;
+    ///
+    /// let read = std::thread::spawn(move || reader.read(&mut [0u8; 4]).map(|_| reader));
+    /// std::thread::sleep(Duration::from_millis(50));
+    ///
+    /// let start = Instant::now();
+    /// writer.close()?;
+    /// assert!(start.elapsed() >= Duration::from_millis(200));
+    /// assert_eq!(read.join().unwrap().err().unwrap().kind(), ErrorKind::TimedOut);
+    ///
+    /// writer.open()?;
+    /// assert!(writer.is_open());
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn split(self: Box<Self>) -> io::Result<(ReadHalf, WriteHalf)> {
+        let reader = if self.is_open() {
+            Some(self.try_clone()?)
+        } else {
+            None
+        };
+
+        let shared = Arc::new(Shared {
+            open: AtomicBool::new(self.is_open()),
+            port: Mutex::new(self),
+            reader: Mutex::new(reader),
+        });
+
+        Ok((
+            ReadHalf {
+                shared: Arc::clone(&shared),
+            },
+            WriteHalf { shared },
+        ))
+    }
+}
+
+impl ReadHalf {
+    /// Puts the port back together from its two halves.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ReuniteError`] holding both halves if `write` was not
+    /// split from the same port as `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// let (first, second) = serialport::pair()?;
+    /// let (first_reader, first_writer) = first.split()?;
+    /// let (second_reader, second_writer) = second.split()?;
+    ///
+    /// let err = first_reader.unsplit(second_writer).err().unwrap();
+    /// let (first_reader, second_writer) = (err.0, err.1);
+    ///
+    /// let _first = first_reader.unsplit(first_writer).unwrap();
+    /// let _second = second_reader.unsplit(second_writer).unwrap();
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn unsplit(self, write: WriteHalf) -> Result<Box<dyn SerialPort>, ReuniteError> {
+        if !Arc::ptr_eq(&self.shared, &write.shared) {
+            return Err(ReuniteError(self, write));
+        }
+        drop(write);
+
+        // The halves are the only owners, so this always succeeds
+        let shared = Arc::into_inner(self.shared).expect("halves are the only owners");
+        Ok(shared.port.into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// The error of [`ReadHalf::unsplit`] for halves of different ports,
+/// holding both halves.
+pub struct ReuniteError(pub ReadHalf, pub WriteHalf);
+
+impl fmt::Debug for ReuniteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReuniteError").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for ReuniteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("tried to unsplit halves of different ports")
+    }
+}
+
+impl std::error::Error for ReuniteError {}
+
+impl Communication for ReadHalf {
+    fn is_open(&self) -> bool {
+        self.shared.open.load(Ordering::SeqCst)
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        self.shared.open()
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.shared.close()
+    }
+}
+
+impl Communication for WriteHalf {
+    fn is_open(&self) -> bool {
+        self.shared.open.load(Ordering::SeqCst)
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        self.shared.open()
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.shared.close()
+    }
+}
+
+impl io::Read for ReadHalf {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.is_open() {
+            return Err(io::ErrorKind::NotConnected.into());
+        }
+
+        let mut reader = lock(&self.shared.reader);
+
+        let result = match reader.as_mut() {
+            Some(reader) => reader.read(buf),
+            None => return Err(io::ErrorKind::NotConnected.into()),
+        };
+
+        // The port was closed while this read was in progress
+        if !self.is_open() {
+            *reader = None;
+        }
+
+        result
+    }
+}
+
+impl io::Write for WriteHalf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.is_open() {
+            return Err(io::ErrorKind::NotConnected.into());
+        }
+
+        lock(&self.shared.port).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.is_open() {
+            return Err(io::ErrorKind::NotConnected.into());
+        }
+
+        lock(&self.shared.port).flush()
+    }
+}
@@ -1,11 +1,15 @@
 use std::io;
+use std::time::{Duration, Instant};
 
-use winapi::shared::minwindef::{BOOL, DWORD, LPVOID};
+use winapi::shared::minwindef::{BOOL, DWORD, FALSE, LPVOID, TRUE};
+use winapi::shared::winerror::{ERROR_IO_PENDING, ERROR_OPERATION_ABORTED};
 use winapi::um::{
     commapi, fileapi,
     handleapi::{self, INVALID_HANDLE_VALUE},
+    ioapiset,
+    minwinbase::OVERLAPPED,
     processthreadsapi::GetCurrentProcess,
-    winbase,
+    synchapi, winbase,
     winnt::{
         DUPLICATE_SAME_ACCESS, FILE_ATTRIBUTE_NORMAL, GENERIC_READ, GENERIC_WRITE, HANDLE, MAXDWORD,
     },
@@ -14,11 +18,35 @@ use winapi::um::{
 use crate::{
     SerialPort, SerialPortBuilder,
     communication::Communication,
-    config::{ClearBuffer, DataBits, FlowControl, Parity, StopBits},
+    config::{ClearBuffer, DataBits, FlowControl, LineEvents, Parity, Rs485Config, StopBits},
     private,
     windows::dcb,
 };
 
+// Event mask bits for SetCommMask/WaitCommEvent
+const EV_CTS: DWORD = 0x0008;
+const EV_DSR: DWORD = 0x0010;
+const EV_RLSD: DWORD = 0x0020;
+const EV_BREAK: DWORD = 0x0040;
+const EV_ERR: DWORD = 0x0080;
+const EV_RING: DWORD = 0x0100;
+
+// Line error bits reported by ClearCommError
+const CE_RXOVER: DWORD = 0x0001;
+const CE_OVERRUN: DWORD = 0x0002;
+const CE_RXPARITY: DWORD = 0x0004;
+const CE_FRAME: DWORD = 0x0008;
+const CE_BREAK: DWORD = 0x0010;
+
+/// Converts a timeout into milliseconds for `COMMTIMEOUTS`, rounding
+/// sub-millisecond timeouts up so they don't become non-blocking.
+/// `MAXDWORD` has a special meaning there, so it is never returned.
+fn timeout_milliseconds(timeout: Duration) -> DWORD {
+    let milliseconds =
+        timeout.as_millis() + u128::from(!timeout.subsec_nanos().is_multiple_of(1_000_000));
+    u128::min(milliseconds, MAXDWORD as u128 - 1) as DWORD
+}
+
 pub(super) fn winapi_result(result: BOOL) -> io::Result<()> {
     match result {
         0 => Err(std::io::Error::last_os_error()),
@@ -26,10 +54,77 @@ pub(super) fn winapi_result(result: BOOL) -> io::Result<()> {
     }
 }
 
+/// An `OVERLAPPED` with its own event, for a single operation on a handle
+/// opened with `FILE_FLAG_OVERLAPPED`. It must stay in place until the
+/// operation has completed.
+struct Overlapped(OVERLAPPED);
+
+impl Overlapped {
+    fn new() -> io::Result<Self> {
+        let event =
+            unsafe { synchapi::CreateEventW(std::ptr::null_mut(), TRUE, FALSE, std::ptr::null()) };
+
+        if event.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+        overlapped.hEvent = event;
+
+        Ok(Self(overlapped))
+    }
+
+    /// Waits for the operation started with `started` as its result, for up
+    /// to `timeout` milliseconds or `INFINITE`. Returns the number of bytes
+    /// transferred, or `None` if the operation timed out and was cancelled.
+    fn complete(
+        &mut self,
+        handle: HANDLE,
+        started: BOOL,
+        timeout: DWORD,
+    ) -> io::Result<Option<DWORD>> {
+        if started == 0 {
+            let e = std::io::Error::last_os_error();
+            if e.raw_os_error() != Some(ERROR_IO_PENDING as i32) {
+                return Err(e);
+            }
+        }
+
+        // The operation must be over before `self` can go away, so it is
+        // cancelled rather than abandoned; it may still complete meanwhile
+        if timeout != winbase::INFINITE
+            && unsafe { synchapi::WaitForSingleObject(self.0.hEvent, timeout) }
+                != winbase::WAIT_OBJECT_0
+        {
+            unsafe { ioapiset::CancelIoEx(handle, &mut self.0) };
+        }
+
+        let mut transferred: DWORD = 0;
+        let result =
+            unsafe { ioapiset::GetOverlappedResult(handle, &mut self.0, &mut transferred, TRUE) };
+
+        match winapi_result(result) {
+            Ok(()) => Ok(Some(transferred)),
+            Err(e) if e.raw_os_error() == Some(ERROR_OPERATION_ABORTED as i32) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for Overlapped {
+    fn drop(&mut self) {
+        unsafe { handleapi::CloseHandle(self.0.hEvent) };
+    }
+}
+
 pub struct ComPort {
     is_open: bool,
     handle: HANDLE,
     builder: SerialPortBuilder,
+    /// Modem status against which line changes are detected
+    event_status: DWORD,
+    /// Events seen but not yet returned by `wait_for_event`
+    pending_events: LineEvents,
 }
 
 impl ComPort {
@@ -38,9 +133,11 @@ impl ComPort {
             is_open: false,
             handle: INVALID_HANDLE_VALUE,
             builder,
+            event_status: 0,
+            pending_events: LineEvents::empty(),
         };
 
-        if !serialport.builder.path.is_empty() {
+        if serialport.builder.has_device() {
             serialport.open()?;
         }
 
@@ -71,6 +168,8 @@ impl ComPort {
             is_open: self.is_open,
             handle,
             builder: self.builder.clone(),
+            event_status: self.event_status,
+            pending_events: self.pending_events,
         })
     }
 
@@ -91,19 +190,215 @@ impl ComPort {
             Ok(_) => (),
         };
 
-        let milliseconds =
-            u128::min(self.builder.timeout.as_millis(), MAXDWORD as u128 - 1) as DWORD;
+        self.apply_timeouts()?;
+        self.release_bus()
+    }
+
+    /// Applies the timeouts alone, without touching the DCB, so changing
+    /// them does not disturb the UART in the middle of a transfer.
+    fn apply_timeouts(&mut self) -> io::Result<()> {
+        let read = timeout_milliseconds(self.builder.read_timeout);
+        let inter_byte = timeout_milliseconds(self.builder.inter_byte_timeout);
+
+        let (interval, multiplier) = match (read, inter_byte) {
+            // Return immediately with whatever data is available
+            (0, _) => (MAXDWORD, 0),
+            // Wait for the first byte, then return right away
+            (_, 0) => (MAXDWORD, MAXDWORD),
+            // Wait for the first byte, then until the line goes idle
+            (_, inter_byte) => (inter_byte, 0),
+        };
 
         let mut timeouts = winbase::COMMTIMEOUTS {
-            ReadIntervalTimeout: MAXDWORD,
-            ReadTotalTimeoutMultiplier: 0,
-            ReadTotalTimeoutConstant: milliseconds,
+            ReadIntervalTimeout: interval,
+            ReadTotalTimeoutMultiplier: multiplier,
+            ReadTotalTimeoutConstant: read,
             WriteTotalTimeoutMultiplier: 0,
-            WriteTotalTimeoutConstant: milliseconds,
+            WriteTotalTimeoutConstant: timeout_milliseconds(self.builder.write_timeout),
         };
 
         winapi_result(unsafe { commapi::SetCommTimeouts(self.handle, &mut timeouts) })
     }
+
+    /// Sets RTS to its idle level in RS-485 mode. Windows drivers have no
+    /// RS-485 support, so RTS is always switched in software.
+    fn release_bus(&mut self) -> io::Result<()> {
+        if !self.builder.rs485.enabled {
+            return Ok(());
+        }
+
+        self.escape_comm_function(if self.builder.rs485.rts_after_send {
+            winbase::SETRTS
+        } else {
+            winbase::CLRRTS
+        })
+    }
+
+    fn write_some(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut overlapped = Overlapped::new()?;
+
+        let started = unsafe {
+            fileapi::WriteFile(
+                self.handle,
+                buf.as_ptr() as LPVOID,
+                buf.len() as DWORD,
+                std::ptr::null_mut(),
+                &mut overlapped.0,
+            )
+        };
+
+        let bytes_written = overlapped.complete(self.handle, started, winbase::INFINITE)?;
+        Ok(bytes_written.unwrap_or(0) as usize)
+    }
+
+    /// Sends all of `buf` with RTS switched around the transmission.
+    fn write_rs485(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let config = self.builder.rs485;
+
+        self.escape_comm_function(if config.rts_on_send {
+            winbase::SETRTS
+        } else {
+            winbase::CLRRTS
+        })?;
+        std::thread::sleep(config.delay_before_send);
+
+        let mut result = Ok(());
+        let mut remaining = buf;
+
+        while !remaining.is_empty() {
+            match self.write_some(remaining) {
+                Ok(0) => result = Err(io::ErrorKind::TimedOut.into()),
+                Ok(n) => remaining = &remaining[n..],
+                Err(e) => result = Err(e),
+            }
+
+            if result.is_err() {
+                break;
+            }
+        }
+
+        // The bus must not be released before the last stop bit has left
+        let result =
+            result.and_then(|_| winapi_result(unsafe { fileapi::FlushFileBuffers(self.handle) }));
+
+        // Whatever was received meanwhile is the echo of our own data
+        if !config.rx_during_tx {
+            let _ = unsafe { commapi::PurgeComm(self.handle, winbase::PURGE_RXCLEAR) };
+        }
+
+        std::thread::sleep(config.delay_after_send);
+        self.release_bus()?;
+
+        result.map(|_| buf.len())
+    }
+
+    fn escape_comm_function(&mut self, function: DWORD) -> io::Result<()> {
+        winapi_result(unsafe { commapi::EscapeCommFunction(self.handle, function) })
+    }
+
+    fn modem_status(&self) -> io::Result<DWORD> {
+        let mut status: DWORD = 0;
+
+        winapi_result(unsafe { commapi::GetCommModemStatus(self.handle, &mut status) })?;
+
+        Ok(status)
+    }
+
+    /// Adds line changes and errors that occurred since the last sample to
+    /// the pending events.
+    fn sample_events(&mut self) -> io::Result<()> {
+        let status = self.modem_status()?;
+        let changed = status ^ self.event_status;
+        self.event_status = status;
+
+        let mut errors: DWORD = 0;
+        let mut comstat = winbase::COMSTAT {
+            cbInQue: 0,
+            cbOutQue: 0,
+            BitFields: 0,
+        };
+
+        winapi_result(unsafe { commapi::ClearCommError(self.handle, &mut errors, &mut comstat) })?;
+
+        for (bit, event) in [
+            (winbase::MS_CTS_ON, LineEvents::CLEAR_TO_SEND),
+            (winbase::MS_DSR_ON, LineEvents::DATA_SET_READY),
+            (winbase::MS_RING_ON, LineEvents::RING_INDICATOR),
+            (winbase::MS_RLSD_ON, LineEvents::CARRIER_DETECT),
+        ] {
+            if changed & bit != 0 {
+                self.pending_events |= event;
+            }
+        }
+
+        for (bit, event) in [
+            (CE_BREAK, LineEvents::BREAK),
+            (CE_FRAME, LineEvents::FRAMING_ERROR),
+            (CE_RXPARITY, LineEvents::PARITY_ERROR),
+            (CE_OVERRUN | CE_RXOVER, LineEvents::OVERRUN),
+        ] {
+            if errors & bit != 0 {
+                self.pending_events |= event;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Selects the events `WaitCommEvent` reports.
+    fn set_comm_mask(&mut self, mask: LineEvents) -> io::Result<()> {
+        let mut events = 0;
+        for (event, bit) in [
+            (LineEvents::CLEAR_TO_SEND, EV_CTS),
+            (LineEvents::DATA_SET_READY, EV_DSR),
+            (LineEvents::RING_INDICATOR, EV_RING),
+            (LineEvents::CARRIER_DETECT, EV_RLSD),
+            (LineEvents::BREAK, EV_BREAK),
+            (LineEvents::LINE_ERRORS, EV_ERR),
+        ] {
+            if mask.intersects(event) {
+                events |= bit;
+            }
+        }
+
+        winapi_result(unsafe { commapi::SetCommMask(self.handle, events) })
+    }
+
+    /// Waits in `WaitCommEvent` until one of the selected events occurs or
+    /// the timeout passes, and adds the events to the pending ones. Events
+    /// are taken from `WaitCommEvent` itself, so a pulse on a line that is
+    /// back at its old level by the next sample is still reported.
+    fn wait_comm_event(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        let mut overlapped = Overlapped::new()?;
+        let mut occurred: DWORD = 0;
+
+        let started =
+            unsafe { commapi::WaitCommEvent(self.handle, &mut occurred, &mut overlapped.0) };
+        let timeout = timeout.map_or(winbase::INFINITE, timeout_milliseconds);
+
+        if overlapped
+            .complete(self.handle, started, timeout)?
+            .is_none()
+        {
+            return Ok(());
+        }
+
+        // Which line error `EV_ERR` stands for is read by `ClearCommError`
+        // in the next sample
+        for (bit, event) in [
+            (EV_CTS, LineEvents::CLEAR_TO_SEND),
+            (EV_DSR, LineEvents::DATA_SET_READY),
+            (EV_RING, LineEvents::RING_INDICATOR),
+            (EV_RLSD, LineEvents::CARRIER_DETECT),
+            (EV_BREAK, LineEvents::BREAK),
+        ] {
+            if occurred & bit != 0 {
+                self.pending_events |= event;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Communication for ComPort {
@@ -112,14 +407,17 @@ impl Communication for ComPort {
     }
 
     fn open(&mut self) -> io::Result<()> {
-        if self.builder.path.is_empty() {
-            return Err(std::io::ErrorKind::InvalidInput.into());
-        }
-
         if self.is_open {
             return Err(std::io::ErrorKind::AlreadyExists.into());
         }
 
+        // A USB device may have come back under a different path
+        self.builder.resolve_usb()?;
+
+        if self.builder.path.is_empty() {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        }
+
         let path = if self.builder.path.starts_with(r"\\.\") {
             self.builder.path.clone()
         } else {
@@ -137,7 +435,7 @@ impl Communication for ComPort {
                 0,
                 std::ptr::null_mut(),
                 fileapi::OPEN_EXISTING,
-                FILE_ATTRIBUTE_NORMAL,
+                FILE_ATTRIBUTE_NORMAL | winbase::FILE_FLAG_OVERLAPPED,
                 std::ptr::null_mut(),
             )
         };
@@ -151,6 +449,9 @@ impl Communication for ComPort {
         self.reconfigure()?;
         self.is_open = true;
 
+        self.event_status = self.modem_status().unwrap_or(0);
+        self.pending_events = LineEvents::empty();
+
         Ok(())
     }
 
@@ -231,7 +532,23 @@ impl SerialPort for ComPort {
     }
 
     fn timeout(&self) -> std::time::Duration {
-        self.builder.timeout
+        self.builder.read_timeout
+    }
+
+    fn read_timeout(&self) -> Duration {
+        self.builder.read_timeout
+    }
+
+    fn write_timeout(&self) -> Duration {
+        self.builder.write_timeout
+    }
+
+    fn inter_byte_timeout(&self) -> Duration {
+        self.builder.inter_byte_timeout
+    }
+
+    fn rs485(&self) -> Rs485Config {
+        self.builder.rs485
     }
 
     fn bytes_to_read(&self) -> io::Result<u32> {
@@ -311,7 +628,48 @@ impl SerialPort for ComPort {
     }
 
     fn set_timeout(&mut self, timeout: std::time::Duration) -> io::Result<()> {
-        self.builder.timeout = timeout;
+        self.builder.read_timeout = timeout;
+        self.builder.write_timeout = timeout;
+
+        if self.is_open {
+            self.apply_timeouts()?;
+        }
+
+        Ok(())
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.builder.read_timeout = timeout;
+
+        if self.is_open {
+            self.apply_timeouts()?;
+        }
+
+        Ok(())
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.builder.write_timeout = timeout;
+
+        if self.is_open {
+            self.apply_timeouts()?;
+        }
+
+        Ok(())
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.builder.inter_byte_timeout = timeout;
+
+        if self.is_open {
+            self.apply_timeouts()?;
+        }
+
+        Ok(())
+    }
+
+    fn set_rs485(&mut self, rs485: Rs485Config) -> io::Result<()> {
+        self.builder.rs485 = rs485;
 
         if self.is_open {
             self.reconfigure()?;
@@ -334,11 +692,101 @@ impl SerialPort for ComPort {
 
         winapi_result(unsafe { commapi::PurgeComm(self.handle, buffer_flags) })
     }
+
+    fn write_request_to_send(&mut self, level: bool) -> io::Result<()> {
+        self.escape_comm_function(if level {
+            winbase::SETRTS
+        } else {
+            winbase::CLRRTS
+        })
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> io::Result<()> {
+        self.escape_comm_function(if level {
+            winbase::SETDTR
+        } else {
+            winbase::CLRDTR
+        })
+    }
+
+    fn read_clear_to_send(&self) -> io::Result<bool> {
+        Ok(self.modem_status()? & winbase::MS_CTS_ON != 0)
+    }
+
+    fn read_data_set_ready(&self) -> io::Result<bool> {
+        Ok(self.modem_status()? & winbase::MS_DSR_ON != 0)
+    }
+
+    fn read_ring_indicator(&self) -> io::Result<bool> {
+        Ok(self.modem_status()? & winbase::MS_RING_ON != 0)
+    }
+
+    fn read_carrier_detect(&self) -> io::Result<bool> {
+        Ok(self.modem_status()? & winbase::MS_RLSD_ON != 0)
+    }
+
+    fn set_break(&mut self) -> io::Result<()> {
+        winapi_result(unsafe { commapi::SetCommBreak(self.handle) })
+    }
+
+    fn clear_break(&mut self) -> io::Result<()> {
+        winapi_result(unsafe { commapi::ClearCommBreak(self.handle) })
+    }
+
+    fn send_break(&mut self, duration: Duration) -> io::Result<()> {
+        // Match the 0.25 to 0.5 second default of tcsendbreak
+        let duration = if duration.is_zero() {
+            Duration::from_millis(250)
+        } else {
+            duration
+        };
+
+        winapi_result(unsafe { fileapi::FlushFileBuffers(self.handle) })?;
+        self.set_break()?;
+        std::thread::sleep(duration);
+        self.clear_break()
+    }
+
+    fn wait_for_event(&mut self, mask: LineEvents, timeout: Duration) -> io::Result<LineEvents> {
+        if !self.is_open {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        }
+
+        let deadline = Instant::now().checked_add(timeout);
+
+        // Selected before the first sample, so nothing in between is missed
+        self.set_comm_mask(mask)?;
+
+        loop {
+            self.sample_events()?;
+
+            let events = self.pending_events & mask;
+            if !events.is_empty() {
+                self.pending_events = self.pending_events & !events;
+                return Ok(events);
+            }
+
+            let remaining = match deadline {
+                None => None,
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(std::io::ErrorKind::TimedOut.into());
+                    }
+
+                    Some(deadline - now)
+                }
+            };
+
+            self.wait_comm_event(remaining)?;
+        }
+    }
 }
 
 impl private::Private for ComPort {
     fn set_raw_path<'a>(&mut self, path: std::borrow::Cow<'a, str>) -> io::Result<()> {
         self.builder.path = path.into_owned();
+        self.builder.usb = None;
         Ok(())
     }
 }
@@ -351,21 +799,22 @@ impl std::io::Read for ComPort {
             return Err(std::io::ErrorKind::NotConnected.into());
         }
 
-        let mut bytes_read: DWORD = 0;
+        let mut overlapped = Overlapped::new()?;
 
-        winapi_result(unsafe {
+        // The read still ends after the `COMMTIMEOUTS` of the port
+        let started = unsafe {
             fileapi::ReadFile(
                 self.handle,
                 buf.as_mut_ptr() as LPVOID,
                 buf.len() as DWORD,
-                &mut bytes_read,
                 std::ptr::null_mut(),
+                &mut overlapped.0,
             )
-        })?;
+        };
 
-        match bytes_read {
-            0 => Err(std::io::ErrorKind::TimedOut.into()),
-            _ => Ok(bytes_read as usize),
+        match overlapped.complete(self.handle, started, winbase::INFINITE)? {
+            None | Some(0) => Err(std::io::ErrorKind::TimedOut.into()),
+            Some(bytes_read) => Ok(bytes_read as usize),
         }
     }
 }
@@ -376,19 +825,11 @@ impl std::io::Write for ComPort {
             return Err(std::io::ErrorKind::NotConnected.into());
         }
 
-        let mut bytes_written: DWORD = 0;
-
-        winapi_result(unsafe {
-            fileapi::WriteFile(
-                self.handle,
-                buf.as_ptr() as LPVOID,
-                buf.len() as DWORD,
-                &mut bytes_written,
-                std::ptr::null_mut(),
-            )
-        })?;
-
-        Ok(bytes_written as usize)
+        if self.builder.rs485.enabled {
+            self.write_rs485(buf)
+        } else {
+            self.write_some(buf)
+        }
     }
 
     fn flush(&mut self) -> io::Result<()> {
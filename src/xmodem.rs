@@ -0,0 +1,767 @@
+//! XMODEM file transfer.
+//!
+//! XMODEM sends data in numbered blocks of 128 bytes, or 1024 bytes with
+//! XMODEM-1K, each acknowledged by the receiver before the next one is sent.
+//! Blocks are protected by an 8-bit checksum or, with XMODEM-CRC, a CRC-16.
+//! The receiver starts the transfer and selects the check by sending `NAK`
+//! for the checksum or `C` for the CRC, and the sender follows its choice.
+//!
+//! [`send`] and [`receive`] transfer data with the default settings, and
+//! [`Xmodem`] configures the block size, the check, the retry limit, the
+//! timeout and a progress callback. A read that times out on the port is
+//! the protocol's retry timer: the receiver asks for the block again, and
+//! the sender sends it again.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(unix)] {
+//! use serialport::xmodem;
+//!
+//! let (mut sender, mut receiver) = serialport::pair()?;
+//! let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+//!
+//! let received = std::thread::scope(|s| {
+//!     let receiver = s.spawn(|| {
+//!         let mut received = Vec::new();
+//!         xmodem::receive(&mut *receiver, &mut received).map(|_| received)
+//!     });
+//!
+//!     xmodem::send(&mut *sender, data.as_slice())?;
+//!     receiver.join().unwrap()
+//! })?;
+//!
+//! // The last block is padded with SUB characters
+//! assert_eq!(received.len(), 1024);
+//! assert_eq!(received[..1000], data);
+//! assert!(received[1000..].iter().all(|&byte| byte == 0x1A));
+//! # }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use crate::SerialPort;
+
+/// Start of a 128-byte block
+pub(crate) const SOH: u8 = 0x01;
+/// Start of a 1024-byte block
+pub(crate) const STX: u8 = 0x02;
+/// End of transmission
+pub(crate) const EOT: u8 = 0x04;
+/// The block was received
+pub(crate) const ACK: u8 = 0x06;
+/// The block was not received and must be sent again
+pub(crate) const NAK: u8 = 0x15;
+/// Cancels the transfer when sent twice in a row
+pub(crate) const CAN: u8 = 0x18;
+/// Pads the last block
+pub(crate) const SUB: u8 = 0x1A;
+/// Starts a transfer with CRC-16 checks
+pub(crate) const CRC_REQUEST: u8 = b'C';
+
+/// How long the line must be silent before a NAK, so that the rest of a
+/// broken block is not taken for the next one.
+const PURGE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many times the receiver asks for CRC-16 before falling back to the
+/// checksum.
+const CRC_ATTEMPTS: u32 = 3;
+
+/// The check that protects each block.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Checksum {
+    /// The 8-bit sum of the data bytes
+    Standard,
+    /// A CRC-16, as used by XMODEM-CRC and XMODEM-1K
+    #[default]
+    Crc16,
+}
+
+impl Checksum {
+    fn len(self) -> usize {
+        match self {
+            Checksum::Standard => 1,
+            Checksum::Crc16 => 2,
+        }
+    }
+
+    fn compute(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Checksum::Standard => {
+                vec![data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))]
+            }
+            Checksum::Crc16 => crc16(data).to_be_bytes().to_vec(),
+        }
+    }
+}
+
+/// The size of the blocks a sender uses.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum BlockSize {
+    /// 128-byte blocks, understood by every receiver
+    #[default]
+    Bytes128,
+    /// 1024-byte blocks, as used by XMODEM-1K
+    Bytes1024,
+}
+
+impl BlockSize {
+    /// Returns the number of data bytes in a block.
+    pub fn size(self) -> usize {
+        match self {
+            BlockSize::Bytes128 => 128,
+            BlockSize::Bytes1024 => 1024,
+        }
+    }
+}
+
+/// The state of a transfer, reported to the progress callback after each
+/// block.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Progress {
+    /// The bytes transferred so far
+    pub bytes: u64,
+    /// The size of the file, if known
+    pub total: Option<u64>,
+    /// How many times a block was sent or requested again so far
+    pub retries: u32,
+}
+
+/// Computes the CRC-16 of XMODEM, with the polynomial 0x1021 and an initial
+/// value of zero.
+pub(crate) fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, &byte| {
+        (0..8).fold(crc ^ (u16::from(byte) << 8), |crc, _| match crc & 0x8000 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x1021,
+        })
+    })
+}
+
+/// Returns the error for a transfer cancelled by the other end.
+pub(crate) fn cancelled() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        "transfer cancelled by the other end",
+    )
+}
+
+/// Transfers data with XMODEM.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(unix)] {
+/// use std::sync::{Arc, Mutex};
+/// use std::time::Duration;
+/// use serialport::xmodem::{BlockSize, Xmodem};
+///
+/// let (mut sender, mut receiver) = serialport::pair()?;
+/// let data = vec![0x55u8; 3000];
+///
+/// let blocks = Arc::new(Mutex::new(Vec::new()));
+/// let progress = blocks.clone();
+///
+/// let received = std::thread::scope(|s| {
+///     let receiver = s.spawn(|| {
+///         let mut received = Vec::new();
+///         Xmodem::new()
+///             .timeout(Duration::from_millis(500))
+///             .receive(&mut *receiver, &mut received)
+///             .map(|_| received)
+///     });
+///
+///     Xmodem::new()
+///         .block_size(BlockSize::Bytes1024)
+///         .on_progress(move |p| progress.lock().unwrap().push(p.bytes))
+///         .send(&mut *sender, data.as_slice())?;
+///
+///     receiver.join().unwrap()
+/// })?;
+///
+/// // Three 1K blocks, the last one padded with SUB characters
+/// assert_eq!(*blocks.lock().unwrap(), [1024, 2048, 3000]);
+/// assert_eq!(received.len(), 3072);
+/// assert_eq!(received[..3000], data);
+/// # }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Xmodem {
+    block_size: BlockSize,
+    checksum: Checksum,
+    max_retries: u32,
+    timeout: Duration,
+    on_progress: Option<Box<dyn FnMut(Progress) + Send>>,
+}
+
+impl Default for Xmodem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Xmodem {
+    /// Creates a transfer with 128-byte blocks, CRC-16 checks, ten retries
+    /// and a timeout of ten seconds.
+    pub fn new() -> Self {
+        Self {
+            block_size: BlockSize::default(),
+            checksum: Checksum::default(),
+            max_retries: 10,
+            timeout: Duration::from_secs(10),
+            on_progress: None,
+        }
+    }
+
+    /// Sets the size of the blocks to send.
+    ///
+    /// A sender falls back to 128-byte blocks if the receiver asks for the
+    /// 8-bit checksum, and sends a last block of up to 128 bytes as a
+    /// 128-byte block.
+    #[must_use]
+    pub fn block_size(mut self, block_size: BlockSize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Sets the check a receiver asks for.
+    ///
+    /// A receiver that asks for CRC-16 falls back to the checksum if the
+    /// sender does not start after three requests. Senders use the check
+    /// the receiver asks for.
+    #[must_use]
+    pub fn checksum(mut self, checksum: Checksum) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Sets how many times in a row a block is sent or requested again
+    /// before the transfer is cancelled.
+    #[must_use]
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets how long to wait for the other end before sending or requesting
+    /// a block again.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets a callback that is called with the progress of the transfer
+    /// after every block.
+    #[must_use]
+    pub fn on_progress(mut self, callback: impl FnMut(Progress) + Send + 'static) -> Self {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Sends everything `reader` returns.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of bytes sent, without the padding of the last
+    /// block. Fails with `ConnectionAborted` if the receiver cancels the
+    /// transfer, and with `TimedOut` if it does not acknowledge a block
+    /// within the retry limit, in which case the transfer is cancelled. The
+    /// read timeout of the port is restored afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use serialport::mock::MockPort;
+    /// use serialport::xmodem::{Checksum, Xmodem};
+    ///
+    /// let mut block = vec![0x01, 0x01, 0xFE];
+    /// block.extend(b"hello");
+    /// block.extend([0x1A; 123]);
+    /// block.push(0x92);
+    ///
+    /// let mut port = MockPort::new(serialport::new("mock", 9600))
+    ///     // The receiver asks for the checksum
+    ///     .respond([0x15], Duration::ZERO)
+    ///     // The first attempt is rejected
+    ///     .expect_write(&block)
+    ///     .respond([0x15], Duration::from_millis(5))
+    ///     .expect_write(&block)
+    ///     .respond([0x06], Duration::from_millis(5))
+    ///     .expect_write([0x04])
+    ///     .respond([0x06], Duration::from_millis(5));
+    /// let handle = port.handle();
+    ///
+    /// let sent = Xmodem::new()
+    ///     .timeout(Duration::from_millis(100))
+    ///     .send(&mut port, &b"hello"[..])?;
+    ///
+    /// assert_eq!(sent, 5);
+    /// assert!(handle.is_done());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn send(&mut self, port: &mut dyn SerialPort, mut reader: impl Read) -> io::Result<u64> {
+        self.link(port, |link| {
+            let (checksum, _) = link.wait_for_receiver(false)?;
+
+            let bytes = link.send_data(&mut reader, None, checksum, false)?;
+
+            link.send_eot()?;
+            Ok(bytes)
+        })
+    }
+
+    /// Receives data into `writer` until the sender ends the transfer.
+    ///
+    /// XMODEM does not transfer the size of the data, so the last block is
+    /// written with its padding, usually SUB characters (0x1A).
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of bytes written. Fails with `ConnectionAborted`
+    /// if the sender cancels the transfer, and with `TimedOut` if no valid
+    /// block arrives within the retry limit, in which case the transfer is
+    /// cancelled. The read timeout of the port is restored afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use serialport::mock::MockPort;
+    /// use serialport::xmodem::Xmodem;
+    ///
+    /// let mut port = MockPort::new(serialport::new("mock", 9600))
+    ///     .expect_write(b"C")
+    ///     // The sender gives up
+    ///     .respond([0x18, 0x18], Duration::from_millis(5));
+    ///
+    /// let error = Xmodem::new()
+    ///     .timeout(Duration::from_millis(100))
+    ///     .receive(&mut port, Vec::new())
+    ///     .unwrap_err();
+    /// assert_eq!(error.kind(), std::io::ErrorKind::ConnectionAborted);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn receive(
+        &mut self,
+        port: &mut dyn SerialPort,
+        mut writer: impl Write,
+    ) -> io::Result<u64> {
+        self.link(port, |link| {
+            let checksum = link.settings.checksum;
+            let mut bytes = 0u64;
+
+            link.receive_blocks(checksum, false, false, |link, data| {
+                writer.write_all(data)?;
+                bytes += data.len() as u64;
+                link.report(bytes, None);
+                Ok(())
+            })?;
+
+            writer.flush()?;
+            Ok(bytes)
+        })
+    }
+
+    /// Runs a transfer on the port with the timeout of these settings, and
+    /// restores the read timeout of the port afterwards.
+    pub(crate) fn link<T>(
+        &mut self,
+        port: &mut dyn SerialPort,
+        transfer: impl FnOnce(&mut Link<'_>) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let read_timeout = port.read_timeout();
+        port.set_read_timeout(self.timeout)?;
+
+        let result = transfer(&mut Link {
+            port: &mut *port,
+            settings: self,
+            retries: 0,
+        });
+        port.set_read_timeout(read_timeout)?;
+
+        result
+    }
+}
+
+/// Sends everything `reader` returns with the default [`Xmodem`] settings.
+///
+/// See [`Xmodem::send`].
+pub fn send(port: &mut dyn SerialPort, reader: impl Read) -> io::Result<u64> {
+    Xmodem::new().send(port, reader)
+}
+
+/// Receives data into `writer` with the default [`Xmodem`] settings.
+///
+/// See [`Xmodem::receive`].
+pub fn receive(port: &mut dyn SerialPort, writer: impl Write) -> io::Result<u64> {
+    Xmodem::new().receive(port, writer)
+}
+
+/// Reads until `buffer` is full or the reader ends.
+fn fill(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(filled)
+}
+
+/// A block received from the sender.
+pub(crate) enum Packet {
+    /// A data block with its number
+    Block(u8, Vec<u8>),
+    /// The end of the transmission
+    End,
+}
+
+/// A port in the middle of a transfer, shared by XMODEM and YMODEM.
+pub(crate) struct Link<'a> {
+    pub(crate) port: &'a mut dyn SerialPort,
+    pub(crate) settings: &'a mut Xmodem,
+    /// Blocks sent or requested again during the transfer
+    retries: u32,
+}
+
+impl Link<'_> {
+    /// Calls the progress callback.
+    pub(crate) fn report(&mut self, bytes: u64, total: Option<u64>) {
+        if let Some(callback) = &mut self.settings.on_progress {
+            callback(Progress {
+                bytes,
+                total,
+                retries: self.retries,
+            });
+        }
+    }
+
+    /// Reads one byte, or returns `None` if the other end stays silent for
+    /// the timeout.
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0u8];
+
+        loop {
+            match self.port.read(&mut byte) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Checks whether a CAN is followed by another one.
+    fn is_cancel(&mut self) -> io::Result<bool> {
+        Ok(self.read_byte()? == Some(CAN))
+    }
+
+    /// Discards input until the line is silent.
+    fn purge(&mut self) -> io::Result<()> {
+        let mut buffer = [0u8; 1024];
+        self.port
+            .set_read_timeout(PURGE_TIMEOUT.min(self.settings.timeout))?;
+
+        let result = loop {
+            match self.port.read(&mut buffer) {
+                Ok(0) => break Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+
+        self.port.set_read_timeout(self.settings.timeout)?;
+        result
+    }
+
+    pub(crate) fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.port.write_all(data)?;
+        self.port.flush()
+    }
+
+    /// Cancels the transfer.
+    pub(crate) fn cancel(&mut self) -> io::Result<()> {
+        self.write(&[CAN; 3])
+    }
+
+    /// Cancels the transfer and returns `error`.
+    pub(crate) fn abort(&mut self, error: io::Error) -> io::Error {
+        // The original error matters more than a failure to cancel
+        let _ = self.cancel();
+        error
+    }
+
+    /// Counts a retry and fails once there were too many in a row.
+    pub(crate) fn retry(&mut self, attempts: &mut u32, what: &str) -> io::Result<()> {
+        *attempts += 1;
+
+        if *attempts > self.settings.max_retries {
+            return Err(self.abort(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{} after {} retries", what, self.settings.max_retries),
+            )));
+        }
+
+        self.retries += 1;
+        Ok(())
+    }
+
+    /// Waits for the receiver to start the transfer.
+    ///
+    /// # Returns
+    ///
+    /// Returns the check the receiver asked for, and whether it asked for
+    /// streaming without acknowledgements, which only YMODEM-G receivers do.
+    pub(crate) fn wait_for_receiver(&mut self, streaming: bool) -> io::Result<(Checksum, bool)> {
+        let mut attempts = 0;
+
+        loop {
+            match self.read_byte()? {
+                Some(NAK) => return Ok((Checksum::Standard, false)),
+                Some(CRC_REQUEST) => return Ok((Checksum::Crc16, false)),
+                Some(b'G') if streaming => return Ok((Checksum::Crc16, true)),
+                Some(CAN) if self.is_cancel()? => return Err(cancelled()),
+                // Noise on the line
+                Some(_) => {}
+                None => {
+                    attempts += 1;
+
+                    if attempts > self.settings.max_retries {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "the receiver did not start the transfer",
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends a block, padded to 128 bytes if `data` fits and to the block
+    /// size otherwise, and waits for it to be acknowledged unless
+    /// `streaming`.
+    pub(crate) fn send_block(
+        &mut self,
+        number: u8,
+        data: &[u8],
+        checksum: Checksum,
+        streaming: bool,
+    ) -> io::Result<()> {
+        let (header, len) = match data.len() {
+            0..=128 => (SOH, 128),
+            _ => (STX, 1024),
+        };
+
+        let mut block = Vec::with_capacity(3 + len + checksum.len());
+        block.extend_from_slice(&[header, number, !number]);
+        block.extend_from_slice(data);
+        block.resize(3 + len, SUB);
+        block.extend(checksum.compute(&block[3..]));
+
+        self.send_acknowledged(&block, streaming, "block was not acknowledged")
+    }
+
+    /// Sends everything `reader` returns in blocks numbered from 1, and
+    /// reports the progress against `total`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of bytes sent. A failing reader cancels the
+    /// transfer.
+    pub(crate) fn send_data(
+        &mut self,
+        reader: &mut impl Read,
+        total: Option<u64>,
+        checksum: Checksum,
+        streaming: bool,
+    ) -> io::Result<u64> {
+        let block_len = match checksum {
+            Checksum::Standard => 128,
+            Checksum::Crc16 => self.settings.block_size.size(),
+        };
+
+        let mut buffer = vec![0u8; block_len];
+        let mut number = 1u8;
+        let mut bytes = 0u64;
+
+        loop {
+            let n = match fill(reader, &mut buffer) {
+                Ok(n) => n,
+                Err(e) => return Err(self.abort(e)),
+            };
+
+            if n == 0 {
+                return Ok(bytes);
+            }
+
+            self.send_block(number, &buffer[..n], checksum, streaming)?;
+            number = number.wrapping_add(1);
+            bytes += n as u64;
+            self.report(bytes, total);
+
+            if n < block_len {
+                return Ok(bytes);
+            }
+        }
+    }
+
+    /// Ends the transmission.
+    pub(crate) fn send_eot(&mut self) -> io::Result<()> {
+        self.send_acknowledged(&[EOT], false, "end of transmission was not acknowledged")
+    }
+
+    fn send_acknowledged(&mut self, data: &[u8], streaming: bool, what: &str) -> io::Result<()> {
+        let mut attempts = 0;
+
+        'send: loop {
+            self.write(data)?;
+
+            if streaming {
+                return Ok(());
+            }
+
+            loop {
+                match self.read_byte()? {
+                    Some(ACK) => return Ok(()),
+                    Some(CAN) if self.is_cancel()? => return Err(cancelled()),
+                    Some(NAK) | None => {
+                        self.retry(&mut attempts, what)?;
+                        continue 'send;
+                    }
+                    // Noise, or a receiver still asking to start
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
+    /// Receives one block, or returns `None` if it was not received
+    /// correctly and must be requested again.
+    pub(crate) fn receive_packet(&mut self, checksum: Checksum) -> io::Result<Option<Packet>> {
+        let len = match self.read_byte()? {
+            Some(SOH) => 128,
+            Some(STX) => 1024,
+            Some(EOT) => return Ok(Some(Packet::End)),
+            Some(CAN) if self.is_cancel()? => return Err(cancelled()),
+            Some(_) => {
+                self.purge()?;
+                return Ok(None);
+            }
+            None => return Ok(None),
+        };
+
+        let mut block = vec![0u8; 2 + len + checksum.len()];
+
+        match self.port.read_exact(&mut block) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let (number, complement) = (block[0], block[1]);
+        let (data, check) = block[2..].split_at(len);
+
+        if number != !complement || check != checksum.compute(data) {
+            self.purge()?;
+            return Ok(None);
+        }
+
+        Ok(Some(Packet::Block(number, data.to_vec())))
+    }
+
+    /// Receives blocks numbered from 1 until the end of the transmission,
+    /// and passes each one to `write` once.
+    ///
+    /// Starts the transfer by asking for `checksum`, or for streaming if
+    /// `streaming`, and acknowledges the blocks unless streaming. Any error
+    /// cancels the transfer. In a YMODEM `batch`, the receiver keeps asking
+    /// for CRC-16 and confirms the end of the transmission by rejecting the
+    /// first EOT.
+    pub(crate) fn receive_blocks(
+        &mut self,
+        checksum: Checksum,
+        streaming: bool,
+        batch: bool,
+        mut write: impl FnMut(&mut Self, &[u8]) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut checksum = checksum;
+        let mut request = match (streaming, checksum) {
+            (true, _) => b'G',
+            (false, Checksum::Crc16) => CRC_REQUEST,
+            (false, Checksum::Standard) => NAK,
+        };
+
+        let mut expected = 1u8;
+        let mut attempts = 0;
+        let mut started = false;
+        let mut ending = false;
+
+        self.write(&[request])?;
+
+        loop {
+            match self.receive_packet(checksum)? {
+                Some(Packet::Block(number, data)) if number == expected => {
+                    started = true;
+                    attempts = 0;
+
+                    if let Err(e) = write(self, &data) {
+                        return Err(self.abort(e));
+                    }
+
+                    expected = expected.wrapping_add(1);
+
+                    if !streaming {
+                        self.write(&[ACK])?;
+                    }
+                }
+                // The acknowledgement of the previous block, or of the YMODEM
+                // header, was lost
+                Some(Packet::Block(number, _)) if number == expected.wrapping_sub(1) => {
+                    self.write(&[ACK])?;
+                }
+                Some(Packet::Block(..)) => {
+                    return Err(self.abort(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "block out of sequence",
+                    )));
+                }
+                Some(Packet::End) if batch && !ending => {
+                    ending = true;
+                    self.write(&[NAK])?;
+                }
+                Some(Packet::End) => {
+                    self.write(&[ACK])?;
+                    return Ok(());
+                }
+                None if streaming && started => {
+                    return Err(self.abort(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "block lost while streaming",
+                    )));
+                }
+                None => {
+                    self.retry(&mut attempts, "no valid block")?;
+
+                    if !started && !batch && request == CRC_REQUEST && attempts == CRC_ATTEMPTS {
+                        checksum = Checksum::Standard;
+                        request = NAK;
+                    }
+
+                    self.write(&[if started { NAK } else { request }])?;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,634 @@
+//! YMODEM batch file transfer.
+//!
+//! YMODEM sends several files in one session, with the blocks of XMODEM-1K
+//! and CRC-16 checks. Each file starts with block 0, which carries its name,
+//! size and modification time, so the receiver writes exactly the size of
+//! the file without the padding of the last block. A block 0 without a name
+//! ends the batch.
+//!
+//! YMODEM-G streams the blocks without waiting for acknowledgements, which
+//! is much faster on reliable links such as USB adapters, but any error
+//! cancels the whole transfer. The receiver asks for it with
+//! [`Ymodem::streaming`], and the sender follows.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(unix)] {
+//! use std::time::Duration;
+//! use serialport::ymodem::{FileInfo, Ymodem};
+//!
+//! let (mut sender, mut receiver) = serialport::pair()?;
+//!
+//! let files = [
+//!     (FileInfo::new("config.bin", 300), vec![0xA5u8; 300]),
+//!     (FileInfo::new("empty.txt", 0), Vec::new()),
+//! ];
+//!
+//! let received = std::thread::scope(|s| {
+//!     let receiver = s.spawn(|| {
+//!         Ymodem::new()
+//!             .timeout(Duration::from_millis(500))
+//!             .receive(&mut *receiver, |_| Ok(Vec::new()))
+//!     });
+//!
+//!     let sent = Ymodem::new().send(
+//!         &mut *sender,
+//!         files.iter().map(|(info, data)| (info.clone(), data.as_slice())),
+//!     )?;
+//!     assert_eq!(sent, 300);
+//!
+//!     receiver.join().unwrap()
+//! })?;
+//!
+//! assert_eq!(received, files);
+//! # }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    SerialPort,
+    xmodem::{ACK, BlockSize, Checksum, Link, Packet, Progress, Xmodem},
+};
+
+/// The name and attributes of a file, sent in block 0.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileInfo {
+    /// The name of the file, which may include a relative path
+    pub name: String,
+    /// The size of the file in bytes
+    pub size: Option<u64>,
+    /// When the file was last modified, to the second
+    pub modified: Option<SystemTime>,
+    /// The Unix permission bits of the file
+    pub mode: Option<u32>,
+}
+
+impl FileInfo {
+    /// Describes a file by its name and size.
+    pub fn new(name: impl Into<String>, size: u64) -> Self {
+        Self {
+            name: name.into(),
+            size: Some(size),
+            modified: None,
+            mode: None,
+        }
+    }
+
+    /// Describes a file on disk by its name, size, modification time and,
+    /// on Unix, its permission bits.
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let metadata = std::fs::metadata(path)?;
+
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+            .to_string_lossy()
+            .into_owned();
+
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(metadata.permissions().mode())
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        Ok(Self {
+            name,
+            size: Some(metadata.len()),
+            modified: metadata.modified().ok(),
+            mode,
+        })
+    }
+
+    /// Encodes the header: the name, a NUL, then the size in decimal and the
+    /// modification time and mode in octal, separated by spaces. Attributes
+    /// are left out from the first unknown one on.
+    fn encode(&self) -> io::Result<Vec<u8>> {
+        if self.name.is_empty() || self.name.contains('\0') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "file name must not be empty or contain NUL",
+            ));
+        }
+
+        let mut header = self.name.clone().into_bytes();
+        header.push(0);
+
+        let modified = self
+            .modified
+            .map(|time| time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()));
+
+        let attributes = [
+            self.size.map(|size| size.to_string()),
+            modified.map(|secs| format!("{:o}", secs)),
+            self.mode.map(|mode| format!("{:o}", mode)),
+        ];
+
+        let attributes: Vec<String> = attributes.into_iter().map_while(|a| a).collect();
+        header.extend(attributes.join(" ").into_bytes());
+
+        match header.len() {
+            0..=128 => header.resize(128, 0),
+            129..=1024 => header.resize(1024, 0),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "file name is too long",
+                ));
+            }
+        }
+
+        Ok(header)
+    }
+
+    /// Decodes a header, or returns `None` for the empty header that ends
+    /// the batch.
+    fn decode(block: &[u8]) -> io::Result<Option<Self>> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed YMODEM header");
+
+        let (name, rest) =
+            block.split_at(block.iter().position(|&b| b == 0).unwrap_or(block.len()));
+
+        if name.is_empty() {
+            return Ok(None);
+        }
+
+        let name = String::from_utf8_lossy(name).into_owned();
+        let rest = rest.get(1..).unwrap_or_default();
+        let rest = &rest[..rest.iter().position(|&b| b == 0).unwrap_or(rest.len())];
+        let rest = std::str::from_utf8(rest).map_err(|_| invalid())?;
+
+        // Senders such as lrzsz add further fields, which are ignored
+        // from_str_radix would also accept a sign
+        let mut fields = rest.split_ascii_whitespace().map(|field| {
+            field
+                .bytes()
+                .all(|c| c.is_ascii_digit())
+                .then_some(field)
+                .ok_or_else(invalid)
+        });
+
+        let size = fields
+            .next()
+            .transpose()?
+            .map(|size| size.parse::<u64>().map_err(|_| invalid()))
+            .transpose()?;
+        let modified = fields
+            .next()
+            .transpose()?
+            .map(|secs| u64::from_str_radix(secs, 8).map_err(|_| invalid()))
+            .transpose()?
+            .filter(|&secs| secs != 0)
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        let mode = fields
+            .next()
+            .transpose()?
+            .map(|mode| u32::from_str_radix(mode, 8).map_err(|_| invalid()))
+            .transpose()?
+            .filter(|&mode| mode != 0);
+
+        Ok(Some(Self {
+            name,
+            size,
+            modified,
+            mode,
+        }))
+    }
+}
+
+/// Transfers batches of files with YMODEM or YMODEM-G.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(unix)] {
+/// use std::sync::mpsc;
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use serialport::ymodem::{FileInfo, Ymodem};
+///
+/// let (mut sender, mut receiver) = serialport::pair()?;
+///
+/// let mut info = FileInfo::new("image.bin", 2500);
+/// info.modified = Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+/// info.mode = Some(0o644);
+/// let data = vec![0x3Cu8; 2500];
+///
+/// let (progress, reports) = mpsc::channel();
+///
+/// let received = std::thread::scope(|s| {
+///     let receiver = s.spawn(|| {
+///         Ymodem::new()
+///             .streaming(true)
+///             .timeout(Duration::from_millis(500))
+///             .receive(&mut *receiver, |_| Ok(Vec::new()))
+///     });
+///
+///     Ymodem::new()
+///         .on_progress(move |p| progress.send((p.bytes, p.total)).unwrap())
+///         .send(&mut *sender, [(info.clone(), data.as_slice())])?;
+///
+///     receiver.join().unwrap()
+/// })?;
+///
+/// assert_eq!(received, [(info, data)]);
+/// assert_eq!(
+///     reports.try_iter().collect::<Vec<_>>(),
+///     [(1024, Some(2500)), (2048, Some(2500)), (2500, Some(2500))]
+/// );
+/// # }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Ymodem {
+    xmodem: Xmodem,
+    streaming: bool,
+}
+
+impl Default for Ymodem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ymodem {
+    /// Creates a transfer with 1024-byte blocks, ten retries and a timeout
+    /// of ten seconds, which acknowledges every block.
+    pub fn new() -> Self {
+        Self {
+            xmodem: Xmodem::new().block_size(BlockSize::Bytes1024),
+            streaming: false,
+        }
+    }
+
+    /// Sets the size of the data blocks to send.
+    #[must_use]
+    pub fn block_size(mut self, block_size: BlockSize) -> Self {
+        self.xmodem = self.xmodem.block_size(block_size);
+        self
+    }
+
+    /// Sets how many times in a row a block is sent or requested again
+    /// before the transfer is cancelled.
+    #[must_use]
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.xmodem = self.xmodem.max_retries(max_retries);
+        self
+    }
+
+    /// Sets how long to wait for the other end before sending or requesting
+    /// a block again.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.xmodem = self.xmodem.timeout(timeout);
+        self
+    }
+
+    /// Sets a callback that is called with the progress of the current file
+    /// after every block.
+    #[must_use]
+    pub fn on_progress(mut self, callback: impl FnMut(Progress) + Send + 'static) -> Self {
+        self.xmodem = self.xmodem.on_progress(callback);
+        self
+    }
+
+    /// Sets whether a receiver asks for YMODEM-G, which streams the blocks
+    /// without acknowledgements. Senders stream whenever the receiver asks
+    /// for it.
+    #[must_use]
+    pub fn streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+
+    /// Sends a batch of files, each described by its [`FileInfo`] and read
+    /// from its reader.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of bytes sent in all files. Fails with
+    /// `ConnectionAborted` if the receiver cancels the transfer, and with
+    /// `TimedOut` if it does not acknowledge a block within the retry limit.
+    /// The read timeout of the port is restored afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use serialport::ymodem::{FileInfo, Ymodem};
+    ///
+    /// let mut port = serialport::new("/dev/ttyUSB0", 115200).build()?;
+    ///
+    /// let path = "firmware.bin";
+    /// Ymodem::new().send(&mut *port, [(FileInfo::from_path(path)?, File::open(path)?)])?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn send<R: Read>(
+        &mut self,
+        port: &mut dyn SerialPort,
+        files: impl IntoIterator<Item = (FileInfo, R)>,
+    ) -> io::Result<u64> {
+        self.xmodem.link(port, |link| {
+            let mut total = 0;
+
+            for (info, mut reader) in files {
+                let header = match info.encode() {
+                    Ok(header) => header,
+                    Err(e) => return Err(link.abort(e)),
+                };
+
+                let (checksum, streaming) = link.wait_for_receiver(true)?;
+                link.send_block(0, &header, checksum, streaming)?;
+
+                let (checksum, streaming) = link.wait_for_receiver(true)?;
+                total += link.send_data(&mut reader, info.size, checksum, streaming)?;
+                link.send_eot()?;
+            }
+
+            let (checksum, streaming) = link.wait_for_receiver(true)?;
+            link.send_block(0, &[0; 128], checksum, streaming)?;
+
+            Ok(total)
+        })
+    }
+
+    /// Receives a batch of files.
+    ///
+    /// `open` is called with the [`FileInfo`] of each file and returns the
+    /// writer to store it in. Only the size of the file is written if the
+    /// sender sent it; otherwise the last block is written with its padding.
+    /// The name is the one the sender chose, so it should be checked before
+    /// it is used as a path.
+    ///
+    /// # Returns
+    ///
+    /// Returns the files received, each with the writer it was stored in.
+    /// Fails with `ConnectionAborted` if the
+    /// sender cancels the transfer, and with `TimedOut` if no valid block
+    /// arrives within the retry limit. If `open` or a write fails, the
+    /// transfer is cancelled and the error returned. The read timeout of
+    /// the port is restored afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use std::path::Path;
+    /// use serialport::ymodem::Ymodem;
+    ///
+    /// let mut port = serialport::new("/dev/ttyUSB0", 115200).build()?;
+    ///
+    /// let files = Ymodem::new().streaming(true).receive(&mut *port, |info| {
+    ///     // Keep only the file name, whatever path the sender gave
+    ///     let name = Path::new(&info.name).file_name().unwrap_or("unnamed".as_ref());
+    ///     File::create(Path::new("downloads").join(name))
+    /// })?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn receive<W: Write>(
+        &mut self,
+        port: &mut dyn SerialPort,
+        mut open: impl FnMut(&FileInfo) -> io::Result<W>,
+    ) -> io::Result<Vec<(FileInfo, W)>> {
+        let streaming = self.streaming;
+
+        self.xmodem.link(port, |link| {
+            let mut files = Vec::new();
+
+            while let Some(info) = receive_header(link, streaming)? {
+                let mut writer = match open(&info) {
+                    Ok(writer) => writer,
+                    Err(e) => return Err(link.abort(e)),
+                };
+
+                let mut written = 0u64;
+
+                link.receive_blocks(Checksum::Crc16, streaming, true, |link, data| {
+                    let len = match info.size {
+                        Some(size) => data.len().min((size - written) as usize),
+                        None => data.len(),
+                    };
+
+                    writer.write_all(&data[..len])?;
+                    written += len as u64;
+                    link.report(written, info.size);
+                    Ok(())
+                })?;
+
+                if let Err(e) = writer.flush() {
+                    return Err(link.abort(e));
+                }
+
+                files.push((info, writer));
+            }
+
+            Ok(files)
+        })
+    }
+}
+
+/// Asks for block 0 and decodes it.
+///
+/// # Returns
+///
+/// Returns the file it describes, or `None` at the end of the batch.
+fn receive_header(link: &mut Link<'_>, streaming: bool) -> io::Result<Option<FileInfo>> {
+    let request = if streaming { b'G' } else { b'C' };
+    let mut attempts = 0;
+
+    link.write(&[request])?;
+
+    loop {
+        match link.receive_packet(Checksum::Crc16)? {
+            Some(Packet::Block(0, data)) => {
+                let info = match FileInfo::decode(&data) {
+                    Ok(info) => info,
+                    Err(e) => return Err(link.abort(e)),
+                };
+
+                // Streaming receivers acknowledge a file by asking for its data
+                if !streaming || info.is_none() {
+                    link.write(&[ACK])?;
+                }
+
+                return Ok(info);
+            }
+            Some(Packet::Block(..)) => {
+                return Err(link.abort(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "expected a YMODEM header",
+                )));
+            }
+            // The acknowledgement of the previous file's EOT was lost
+            Some(Packet::End) => link.write(&[ACK])?,
+            None => {
+                link.retry(&mut attempts, "no YMODEM header")?;
+                link.write(&[request])?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str) -> FileInfo {
+        FileInfo {
+            name: name.into(),
+            size: None,
+            modified: None,
+            mode: None,
+        }
+    }
+
+    /// Pads a header to a block of 128 bytes.
+    fn block(header: &[u8]) -> Vec<u8> {
+        let mut block = header.to_vec();
+        block.resize(128, 0);
+        block
+    }
+
+    #[test]
+    fn encode_name_only() {
+        let header = info("foo.txt").encode().unwrap();
+        assert_eq!(header, block(b"foo.txt\0"));
+        assert_eq!(FileInfo::decode(&header).unwrap(), Some(info("foo.txt")));
+    }
+
+    #[test]
+    fn encode_size_only() {
+        let header = FileInfo::new("foo.txt", 1234).encode().unwrap();
+        assert_eq!(header, block(b"foo.txt\x001234"));
+        assert_eq!(
+            FileInfo::decode(&header).unwrap(),
+            Some(FileInfo::new("foo.txt", 1234))
+        );
+    }
+
+    #[test]
+    fn encode_all_attributes() {
+        let info = FileInfo {
+            modified: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            mode: Some(0o100644),
+            ..FileInfo::new("dir/foo.txt", 1234)
+        };
+
+        let header = info.encode().unwrap();
+        assert_eq!(header, block(b"dir/foo.txt\x001234 14524770400 100644"));
+        assert_eq!(FileInfo::decode(&header).unwrap(), Some(info));
+    }
+
+    #[test]
+    fn encode_stops_at_first_unknown_attribute() {
+        let info = FileInfo {
+            mode: Some(0o644),
+            ..info("foo.txt")
+        };
+
+        assert_eq!(info.encode().unwrap(), block(b"foo.txt\0"));
+    }
+
+    #[test]
+    fn zero_modified_and_mode_are_unknown() {
+        let info = FileInfo {
+            modified: Some(UNIX_EPOCH),
+            mode: Some(0),
+            ..FileInfo::new("foo.txt", 0)
+        };
+
+        let header = info.encode().unwrap();
+        assert_eq!(header, block(b"foo.txt\x000 0 0"));
+        assert_eq!(
+            FileInfo::decode(&header).unwrap(),
+            Some(FileInfo::new("foo.txt", 0))
+        );
+    }
+
+    #[test]
+    fn encode_long_header() {
+        // The name and its NUL fill a block of 128 bytes exactly
+        let name = "a".repeat(127);
+        assert_eq!(info(&name).encode().unwrap().len(), 128);
+
+        let name = "a".repeat(128);
+        let header = info(&name).encode().unwrap();
+        assert_eq!(header.len(), 1024);
+        assert_eq!(FileInfo::decode(&header).unwrap(), Some(info(&name)));
+
+        // The attributes push the header over 128 bytes
+        let info = FileInfo::new("a".repeat(120), 1_000_000_000);
+        let header = info.encode().unwrap();
+        assert_eq!(header.len(), 1024);
+        assert_eq!(FileInfo::decode(&header).unwrap(), Some(info));
+    }
+
+    #[test]
+    fn encode_rejects_long_name() {
+        assert_eq!(info(&"a".repeat(1023)).encode().unwrap().len(), 1024);
+
+        let error = info(&"a".repeat(1024)).encode().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn encode_rejects_invalid_name() {
+        for name in ["", "foo\0.txt"] {
+            let error = info(name).encode().unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn decode_end_of_batch() {
+        assert_eq!(FileInfo::decode(&[0; 128]).unwrap(), None);
+    }
+
+    #[test]
+    fn decode_malformed_attributes() {
+        for header in [
+            &b"foo.txt\x0012x4"[..],
+            b"foo.txt\x001234 14524770408",
+            b"foo.txt\x001234 14524770400 100649",
+            b"foo.txt\x00+1234",
+            b"foo.txt\x001234 -1",
+            b"foo.txt\x00-1",
+            b"foo.txt\x001234 777777777777777777777777",
+            b"foo.txt\x00\xFF",
+        ] {
+            let error = FileInfo::decode(&block(header)).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn decode_lrzsz_header() {
+        // sb also sends a serial number, the number of files left and their
+        // total size
+        let header = block(b"foo.txt\x001234 14524770400 100644 0 1 1234");
+
+        assert_eq!(
+            FileInfo::decode(&header).unwrap(),
+            Some(FileInfo {
+                modified: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+                mode: Some(0o100644),
+                ..FileInfo::new("foo.txt", 1234)
+            })
+        );
+    }
+
+    #[test]
+    fn decode_ignores_data_after_nul() {
+        let header = block(b"foo.txt\x001234\0garbage 12x");
+
+        assert_eq!(
+            FileInfo::decode(&header).unwrap(),
+            Some(FileInfo::new("foo.txt", 1234))
+        );
+    }
+}